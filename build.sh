@@ -2,4 +2,6 @@
 set -e
 cargo build --all --target wasm32-unknown-unknown --release
 cp target/wasm32-unknown-unknown/release/spot_token.wasm compiled/
-cp target/wasm32-unknown-unknown/release/registrar.wasm compiled/
\ No newline at end of file
+cp target/wasm32-unknown-unknown/release/registrar.wasm compiled/
+cp target/wasm32-unknown-unknown/release/art_echo.wasm compiled/
+cp target/wasm32-unknown-unknown/release/art_market.wasm compiled/
@@ -1,7 +1,7 @@
 use crate::*;
 use near_sdk::{assert_one_yocto, ext_contract, near};
 
-pub trait NonFungibleTokenCore {
+pub trait NonFungibleTokenApproval {
     //approve an account ID to transfer a token on your behalf
     fn nft_approve(&mut self, token_id: TokenId, account_id: AccountId, msg: Option<String>);
 
@@ -20,6 +20,7 @@ pub trait NonFungibleTokenCore {
     fn nft_revoke_all(&mut self, token_id: TokenId);
 }
 
+#[allow(dead_code)]
 #[ext_contract(ext_non_fungible_approval_receiver)]
 trait NonFungibleTokenApprovalsReceiver {
     //cross contract call to an external contract that is initiated during nft_approve
@@ -32,8 +33,63 @@ trait NonFungibleTokenApprovalsReceiver {
     );
 }
 
+//logs the approval of an account on a token
+fn log_nft_approve(owner_id: &AccountId, token_id: &TokenId, account_id: &AccountId, approval_id: u32) {
+    // Construct the approve log.
+    let nft_approve_log: EventLog = EventLog {
+        // Standard name ("nep171").
+        standard: NFT_STANDARD_NAME.to_string(),
+        // Version of the standard ("nft-1.0.0").
+        version: NFT_METADATA_SPEC.to_string(),
+        // The data related with the event stored in a vector.
+        event: EventLogVariant::NftApprove(vec![NftApproveLog {
+            // Owner of the token.
+            owner_id: owner_id.to_string(),
+            // The token the account was approved on.
+            token_id: token_id.to_string(),
+            // The account that was approved.
+            account_id: account_id.to_string(),
+            // The approval ID the account was given.
+            approval_id,
+        }]),
+    };
+
+    // Log the serialized json.
+    env::log_str(&nft_approve_log.to_string());
+}
+
+//logs the approvals that were revoked from a token
+fn log_nft_revoke<'a, I>(owner_id: &AccountId, token_id: &TokenId, account_ids: I)
+where
+    I: Iterator<Item = &'a AccountId>,
+{
+    //sort the account IDs so the log doesn't depend on the order of the approvals
+    let mut account_ids: Vec<String> = account_ids.map(|account_id| account_id.to_string()).collect();
+    account_ids.sort();
+
+    // Construct the revoke log.
+    let nft_revoke_log: EventLog = EventLog {
+        // Standard name ("nep171").
+        standard: NFT_STANDARD_NAME.to_string(),
+        // Version of the standard ("nft-1.0.0").
+        version: NFT_METADATA_SPEC.to_string(),
+        // The data related with the event stored in a vector.
+        event: EventLogVariant::NftRevoke(vec![NftRevokeLog {
+            // Owner of the token.
+            owner_id: owner_id.to_string(),
+            // The token the approvals were revoked from.
+            token_id: token_id.to_string(),
+            // The accounts that are no longer approved.
+            account_ids,
+        }]),
+    };
+
+    // Log the serialized json.
+    env::log_str(&nft_revoke_log.to_string());
+}
+
 #[near]
-impl NonFungibleTokenCore for ArtEcho {
+impl NonFungibleTokenApproval for ArtEcho {
     //allow a specific account ID to approve a token on your behalf
     #[payable]
     fn nft_approve(&mut self, token_id: TokenId, account_id: AccountId, msg: Option<String>) {
@@ -44,7 +100,7 @@ impl NonFungibleTokenCore for ArtEcho {
         assert_at_least_one_yocto();
 
         //get the token object from the token ID
-        let mut token = self.tokens_by_id.get(&token_id).expect("No token");
+        let mut token = self.tokens_by_id.get(&token_id).cloned().expect("No token");
 
         //make sure that the person calling the function is the owner of the token
         assert_eq!(
@@ -52,6 +108,8 @@ impl NonFungibleTokenCore for ArtEcho {
             &token.owner_id,
             "Predecessor must be the token owner."
         );
+        //soulbound tokens can't be approved since nobody could transfer them
+        self.assert_transferable(&token_id, &token);
 
         //get the next approval ID if we need a new approval
         let approval_id: u32 = token.next_approval_id;
@@ -75,10 +133,13 @@ impl NonFungibleTokenCore for ArtEcho {
         //increment the token's next approval ID by 1
         token.next_approval_id += 1;
         //insert the token back into the tokens_by_id collection
-        self.tokens_by_id.insert(&token_id, &token);
+        self.tokens_by_id.insert(token_id.clone(), token.clone());
 
         //refund any excess storage attached by the user. If the user didn't attach enough, panic. 
-        refund_deposit(storage_used);
+        self.refund_deposit(storage_used);
+
+        //log the approval so indexers can follow it
+        log_nft_approve(&token.owner_id, &token_id, &account_id, approval_id);
 
         //if some message was passed into the function, we initiate a cross contract call on the
         //account we're giving access to. 
@@ -129,7 +190,7 @@ impl NonFungibleTokenCore for ArtEcho {
         //assert that the user attached exactly 1 yoctoNEAR for security reasons
         assert_one_yocto();
         //get the token object using the passed in token_id
-        let mut token = self.tokens_by_id.get(&token_id).expect("No token");
+        let mut token = self.tokens_by_id.get(&token_id).cloned().expect("No token");
 
         //get the caller of the function and assert that they are the owner of the token
         let predecessor_account_id = env::predecessor_account_id();
@@ -142,10 +203,12 @@ impl NonFungibleTokenCore for ArtEcho {
             .is_some()
         {
             //refund the funds released by removing the approved_account_id to the caller of the function
-            refund_approved_account_ids_iter(predecessor_account_id, [account_id].iter());
+            refund_approved_account_ids_iter(predecessor_account_id.clone(), [&account_id].into_iter());
+            //log the revoked approval
+            log_nft_revoke(&predecessor_account_id, &token_id, [&account_id].into_iter());
 
             //insert the token back into the tokens_by_id collection with the account_id removed from the approval list
-            self.tokens_by_id.insert(&token_id, &token);
+            self.tokens_by_id.insert(token_id.clone(), token);
         }
     }
 
@@ -156,7 +219,7 @@ impl NonFungibleTokenCore for ArtEcho {
         assert_one_yocto();
 
         //get the token object from the passed in token ID
-        let mut token = self.tokens_by_id.get(&token_id).expect("No token");
+        let mut token = self.tokens_by_id.get(&token_id).cloned().expect("No token");
         //get the caller and make sure they are the owner of the tokens
         let predecessor_account_id = env::predecessor_account_id();
         assert_eq!(&predecessor_account_id, &token.owner_id);
@@ -164,11 +227,190 @@ impl NonFungibleTokenCore for ArtEcho {
         //only revoke if the approved account IDs for the token is not empty
         if !token.approved_account_ids.is_empty() {
             //refund the approved account IDs to the caller of the function
-            refund_approved_account_ids(predecessor_account_id, &token.approved_account_ids);
+            refund_approved_account_ids(predecessor_account_id.clone(), &token.approved_account_ids);
+            //log every approval that was revoked
+            log_nft_revoke(&predecessor_account_id, &token_id, token.approved_account_ids.keys());
             //clear the approved account IDs
             token.approved_account_ids.clear();
             //insert the token back into the tokens_by_id collection with the approved account IDs cleared
-            self.tokens_by_id.insert(&token_id, &token);
+            self.tokens_by_id.insert(token_id.clone(), token);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::{get_created_receipts, get_logs, VMContextBuilder};
+    use near_sdk::{testing_env, PromiseResult, RuntimeFeesConfig};
+
+    const TOKEN_ID: &str = "token";
+
+    //a contract with a token owned by the owner
+    fn setup_with_token() -> ArtEcho {
+        let mut contract = setup_contract();
+        contract.internal_mint(TOKEN_ID.to_string(), sample_metadata(), owner(), None, None, owner());
+        contract
+    }
+
+    fn approve(contract: &mut ArtEcho, account_id: &str) {
+        set_context(owner(), NearToken::from_millinear(100));
+        contract.nft_approve(TOKEN_ID.to_string(), account(account_id), None);
+    }
+
+    //the storage cost of the approvals of the passed in accounts
+    fn approval_cost(account_ids: &[&str]) -> NearToken {
+        let bytes = account_ids.iter().map(|account_id| bytes_for_approved_account_id(&account(account_id))).sum();
+        env::storage_byte_cost().saturating_mul(bytes)
+    }
+
+    #[test]
+    fn approve_logs_event_and_refunds_excess_deposit() {
+        let mut contract = setup_with_token();
+        approve(&mut contract, "market.near");
+
+        assert!(contract.nft_is_approved(TOKEN_ID.to_string(), account("market.near"), Some(0)));
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"nep171","version":"1.0.0","event":"nft_approve","data":[{"owner_id":"owner.near","token_id":"token","account_id":"market.near","approval_id":0}]}"#]
+        );
+        assert_eq!(
+            refunded_to(&owner()),
+            NearToken::from_millinear(100).saturating_sub(approval_cost(&["market.near"]))
+        );
+    }
+
+    #[test]
+    fn revoke_refunds_storage_and_logs_event() {
+        let mut contract = setup_with_token();
+        approve(&mut contract, "market.near");
+        approve(&mut contract, "auction.near");
+
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.nft_revoke(TOKEN_ID.to_string(), account("market.near"));
+
+        assert!(!contract.nft_is_approved(TOKEN_ID.to_string(), account("market.near"), None));
+        assert!(contract.nft_is_approved(TOKEN_ID.to_string(), account("auction.near"), None));
+        assert_eq!(refunded_to(&owner()), approval_cost(&["market.near"]));
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"nep171","version":"1.0.0","event":"nft_revoke","data":[{"owner_id":"owner.near","token_id":"token","account_ids":["market.near"]}]}"#]
+        );
+    }
+
+    #[test]
+    fn revoke_of_unapproved_account_does_nothing() {
+        let mut contract = setup_with_token();
+
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.nft_revoke(TOKEN_ID.to_string(), account("market.near"));
+
+        assert!(get_created_receipts().is_empty());
+        assert!(get_logs().is_empty());
+    }
+
+    #[test]
+    fn revoke_all_refunds_every_approval_and_logs_event() {
+        let mut contract = setup_with_token();
+        approve(&mut contract, "market.near");
+        approve(&mut contract, "auction.near");
+
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.nft_revoke_all(TOKEN_ID.to_string());
+
+        assert!(!contract.nft_is_approved(TOKEN_ID.to_string(), account("market.near"), None));
+        assert!(!contract.nft_is_approved(TOKEN_ID.to_string(), account("auction.near"), None));
+        assert_eq!(refunded_to(&owner()), approval_cost(&["market.near", "auction.near"]));
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"nep171","version":"1.0.0","event":"nft_revoke","data":[{"owner_id":"owner.near","token_id":"token","account_ids":["auction.near","market.near"]}]}"#]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn revoke_by_other_account_panics() {
+        let mut contract = setup_with_token();
+        approve(&mut contract, "market.near");
+
+        set_context(account("market.near"), ONE_YOCTONEAR);
+        contract.nft_revoke(TOKEN_ID.to_string(), account("market.near"));
+    }
+
+    #[test]
+    fn transfer_refunds_approvals_to_previous_owner() {
+        let mut contract = setup_with_token();
+        approve(&mut contract, "market.near");
+        approve(&mut contract, "auction.near");
+
+        set_context(account("market.near"), ONE_YOCTONEAR);
+        contract.nft_transfer(account("buyer.near"), TOKEN_ID.to_string(), Some(0), None);
+
+        assert!(!contract.nft_is_approved(TOKEN_ID.to_string(), account("auction.near"), None));
+        assert_eq!(refunded_to(&owner()), approval_cost(&["market.near", "auction.near"]));
+    }
+
+    #[test]
+    fn resolved_transfer_call_refunds_approvals_to_previous_owner() {
+        let mut contract = setup_with_token();
+        approve(&mut contract, "market.near");
+
+        set_context(owner(), ONE_YOCTONEAR);
+        let previous_token =
+            contract.internal_transfer(&owner(), &account("receiver.near"), &TOKEN_ID.to_string(), None, None);
+
+        //the receiver keeps the token
+        testing_env!(
+            VMContextBuilder::new().current_account_id(account("art-echo.near")).build(),
+            near_sdk::test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(b"false".to_vec())],
+        );
+        assert!(contract.nft_resolve_transfer(
+            None,
+            owner(),
+            account("receiver.near"),
+            TOKEN_ID.to_string(),
+            previous_token.approved_account_ids,
+            None,
+        ));
+        assert_eq!(refunded_to(&owner()), approval_cost(&["market.near"]));
+    }
+
+    #[test]
+    fn returned_transfer_call_refunds_receiver_approvals() {
+        let mut contract = setup_with_token();
+        approve(&mut contract, "market.near");
+
+        set_context(owner(), ONE_YOCTONEAR);
+        let previous_token =
+            contract.internal_transfer(&owner(), &account("receiver.near"), &TOKEN_ID.to_string(), None, None);
+
+        //the receiver approves an account of their own before returning the token
+        set_context(account("receiver.near"), NearToken::from_millinear(100));
+        contract.nft_approve(TOKEN_ID.to_string(), account("auction.near"), None);
+
+        testing_env!(
+            VMContextBuilder::new().current_account_id(account("art-echo.near")).build(),
+            near_sdk::test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(b"true".to_vec())],
+        );
+        assert!(!contract.nft_resolve_transfer(
+            None,
+            owner(),
+            account("receiver.near"),
+            TOKEN_ID.to_string(),
+            previous_token.approved_account_ids,
+            None,
+        ));
+
+        //the previous owner gets their approvals back and the receiver is refunded theirs
+        assert!(contract.nft_is_approved(TOKEN_ID.to_string(), account("market.near"), Some(0)));
+        assert!(!contract.nft_is_approved(TOKEN_ID.to_string(), account("auction.near"), None));
+        assert_eq!(refunded_to(&account("receiver.near")), approval_cost(&["auction.near"]));
+    }
+}
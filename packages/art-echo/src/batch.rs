@@ -0,0 +1,346 @@
+use crate::*;
+use near_sdk::Gas;
+
+//the most items a batch can have, so a batch always fits in the gas limit of a single call
+pub const MAX_BATCH_SIZE: usize = 50;
+//the gas needed to mint a token of a batch
+const GAS_PER_BATCH_MINT: Gas = Gas::from_tgas(5);
+//the gas needed to transfer a token of a batch
+const GAS_PER_BATCH_TRANSFER: Gas = Gas::from_tgas(4);
+
+//make sure a batch isn't empty and can be processed with the gas that was attached
+fn assert_batch_size(batch_size: usize, gas_per_item: Gas) {
+    assert!(batch_size > 0, "Batch cannot be empty");
+    assert!(
+        batch_size <= MAX_BATCH_SIZE,
+        "Batch cannot have more than {} items", MAX_BATCH_SIZE
+    );
+
+    //fail right away instead of running out of gas halfway through the batch
+    let required_gas = gas_per_item.saturating_mul(batch_size as u64);
+    assert!(
+        required_gas <= env::prepaid_gas(),
+        "Must attach at least {} to process {} items", required_gas, batch_size
+    );
+}
+
+#[near]
+impl ArtEcho {
+    //mint a batch of tokens with the same royalties. Only minters can batch mint.
+    //the storage of the whole batch is paid for at once and the mints are logged in a single event
+    #[payable]
+    pub fn nft_batch_mint(
+        &mut self,
+        tokens: Vec<(TokenId, TokenMetadata, AccountId)>,
+        //we add an optional parameter for perpetual royalties
+        perpetual_royalties: Option<HashMap<AccountId, u32>>,
+        //tokens that aren't transferable stay with the receiver until the minter burns them. Defaults to true
+        transferable: Option<bool>,
+    ) {
+        //make sure mints aren't stopped before minting any of the batch
+        self.assert_not_paused();
+        //make sure the caller is allowed to mint without going through the registrar
+        self.assert_minter();
+        assert_batch_size(tokens.len(), GAS_PER_BATCH_MINT);
+        if let Some(perpetual_royalties) = perpetual_royalties.as_ref() {
+            assert_valid_royalties(perpetual_royalties);
+        }
+
+        //the minter is the issuer if the tokens aren't transferable
+        let minter_id = env::predecessor_account_id();
+        let soulbound_issuer_id = (!transferable.unwrap_or(true)).then(|| minter_id.clone());
+
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        //mint every token and group the minted token IDs by owner for the log
+        let mut nft_mint_logs: Vec<NftMintLog> = Vec::new();
+        for (token_id, metadata, receiver_id) in tokens {
+            assert_valid_token_id(&token_id);
+            let token = self.internal_mint(
+                token_id.clone(),
+                metadata,
+                receiver_id,
+                perpetual_royalties.clone(),
+                soulbound_issuer_id.clone(),
+                minter_id.clone(),
+            );
+
+            match nft_mint_logs.iter_mut().find(|log| log.owner_id == token.owner_id.as_str()) {
+                Some(nft_mint_log) => nft_mint_log.token_ids.push(token_id),
+                None => nft_mint_logs.push(NftMintLog {
+                    owner_id: token.owner_id.to_string(),
+                    token_ids: vec![token_id],
+                    memo: None,
+                }),
+            }
+        }
+
+        // Construct the mint log as per the events standard.
+        let nft_mint_log: EventLog = EventLog {
+            // Standard name ("nep171").
+            standard: NFT_STANDARD_NAME.to_string(),
+            // Version of the standard ("nft-1.0.0").
+            version: NFT_METADATA_SPEC.to_string(),
+            // The data related with the event stored in a vector.
+            event: EventLogVariant::NftMint(nft_mint_logs),
+        };
+
+        // Log the serialized json.
+        env::log_str(&nft_mint_log.to_string());
+
+        //calculate the required storage which was the used - initial
+        let required_storage_in_bytes = self.internal_storage_usage() - initial_storage_usage;
+
+        //refund any excess storage if the user attached too much. Panic if they didn't attach enough to cover the required.
+        self.refund_deposit(required_storage_in_bytes.into());
+    }
+
+    //transfer a batch of tokens. Every token has to be owned by the caller or approved for the caller.
+    //the transfers are logged in a single event
+    #[payable]
+    pub fn nft_batch_transfer(&mut self, transfers: Vec<(TokenId, AccountId)>, memo: Option<String>) {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+        assert_batch_size(transfers.len(), GAS_PER_BATCH_TRANSFER);
+
+        //get the sender to transfer the tokens from
+        let sender_id = env::predecessor_account_id();
+
+        //move every token and group the transfers with the same accounts for the log
+        let mut nft_transfer_logs: Vec<NftTransferLog> = Vec::new();
+        //keep track of the approvals that were cleared for every previous owner
+        let mut approved_account_ids: HashMap<AccountId, Vec<AccountId>> = HashMap::new();
+        for (token_id, receiver_id) in transfers {
+            let previous_token = self.internal_move_token(&sender_id, &receiver_id, &token_id, None);
+
+            //if the sender isn't the owner of the token, we set the authorized ID equal to the sender
+            let authorized_id = (sender_id != previous_token.owner_id).then(|| sender_id.to_string());
+            match nft_transfer_logs.iter_mut().find(|log| {
+                log.authorized_id == authorized_id
+                    && log.old_owner_id == previous_token.owner_id.as_str()
+                    && log.new_owner_id == receiver_id.as_str()
+            }) {
+                Some(nft_transfer_log) => nft_transfer_log.token_ids.push(token_id),
+                None => nft_transfer_logs.push(NftTransferLog {
+                    authorized_id,
+                    old_owner_id: previous_token.owner_id.to_string(),
+                    new_owner_id: receiver_id.to_string(),
+                    token_ids: vec![token_id],
+                    memo: memo.clone(),
+                }),
+            }
+
+            approved_account_ids
+                .entry(previous_token.owner_id)
+                .or_default()
+                .extend(previous_token.approved_account_ids.into_keys());
+        }
+
+        //if there was some memo attached, we log it.
+        if let Some(memo) = memo.as_ref() {
+            env::log_str(&format!("Memo: {}", memo));
+        }
+
+        // Construct the transfer log as per the events standard.
+        let nft_transfer_log: EventLog = EventLog {
+            // Standard name ("nep171").
+            standard: NFT_STANDARD_NAME.to_string(),
+            // Version of the standard ("nft-1.0.0").
+            version: NFT_METADATA_SPEC.to_string(),
+            // The data related with the event stored in a vector.
+            event: EventLogVariant::NftTransfer(nft_transfer_logs),
+        };
+
+        // Log the serialized json.
+        env::log_str(&nft_transfer_log.to_string());
+
+        //refund every previous owner once for the storage used up by the approvals that were cleared
+        for (owner_id, approved_account_ids) in approved_account_ids {
+            if !approved_account_ids.is_empty() {
+                refund_approved_account_ids_iter(owner_id, approved_account_ids.iter());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::{get_created_receipts, get_logs};
+
+    fn batch(token_ids: &[&str], receiver_id: &str) -> Vec<(TokenId, TokenMetadata, AccountId)> {
+        token_ids
+            .iter()
+            .map(|token_id| (token_id.to_string(), sample_metadata(), account(receiver_id)))
+            .collect()
+    }
+
+    #[test]
+    fn batch_mint_logs_one_event_and_charges_storage_once() {
+        let mut contract = setup_contract();
+        let mut tokens = batch(&["1", "2"], "alice.near");
+        tokens.extend(batch(&["3"], "bob.near"));
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_batch_mint(tokens, None, None);
+
+        assert_eq!(contract.nft_total_supply(), U64(3));
+        assert_eq!(contract.nft_supply_for_owner(account("alice.near")), U64(2));
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"nep171","version":"1.0.0","event":"nft_mint","data":[{"owner_id":"alice.near","token_ids":["1","2"]},{"owner_id":"bob.near","token_ids":["3"]}]}"#]
+        );
+        //the excess deposit is refunded in a single transfer
+        assert_eq!(get_created_receipts().len(), 1);
+        assert!(refunded_to(&owner()) > NearToken::from_millinear(900));
+    }
+
+    #[test]
+    fn batch_mint_applies_royalties_and_transferability_to_every_token() {
+        let mut contract = setup_contract();
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_batch_mint(
+            batch(&["1", "2"], "alice.near"),
+            Some(HashMap::from([(account("artist.near"), 1000)])),
+            Some(false),
+        );
+
+        for token_id in ["1", "2"] {
+            let token = contract.nft_token(token_id.to_string()).unwrap();
+            assert_eq!(token.royalty[&account("artist.near")], 1000);
+            assert!(!token.transferable);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn paused_contract_blocks_batch_mints() {
+        let mut contract = setup_contract();
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.nft_pause(None);
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_batch_mint(batch(&["1", "2"], "alice.near"), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Must attach")]
+    fn batch_mint_requires_storage_for_the_whole_batch() {
+        let mut contract = setup_contract();
+
+        set_context(owner(), NearToken::from_millinear(5));
+        contract.nft_batch_mint(batch(&["1", "2", "3"], "alice.near"), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unauthorized minter")]
+    fn only_minters_batch_mint() {
+        let mut contract = setup_contract();
+
+        set_context(account("alice.near"), NearToken::from_near(1));
+        contract.nft_batch_mint(batch(&["1"], "alice.near"), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Token already exists")]
+    fn batch_mint_rejects_duplicate_token_ids() {
+        let mut contract = setup_contract();
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_batch_mint(batch(&["1", "1"], "alice.near"), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Batch cannot have more than 50 items")]
+    fn batch_mint_is_limited() {
+        let mut contract = setup_contract();
+        let token_ids: Vec<String> = (0..51).map(|index| index.to_string()).collect();
+        let token_ids: Vec<&str> = token_ids.iter().map(String::as_str).collect();
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_batch_mint(batch(&token_ids, "alice.near"), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Batch cannot be empty")]
+    fn batch_transfer_cannot_be_empty() {
+        let mut contract = setup_contract();
+
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.nft_batch_transfer(vec![], None);
+    }
+
+    #[test]
+    fn batch_transfer_logs_one_event_and_refunds_approvals_once() {
+        let mut contract = setup_contract();
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_batch_mint(batch(&["1", "2", "3"], "alice.near"), None, None);
+        for token_id in ["1", "2"] {
+            set_context(account("alice.near"), NearToken::from_millinear(10));
+            contract.nft_approve(token_id.to_string(), account("market.near"), None);
+        }
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_batch_transfer(
+            vec![
+                ("1".to_string(), account("bob.near")),
+                ("2".to_string(), account("bob.near")),
+                ("3".to_string(), account("carol.near")),
+            ],
+            Some("drop".to_string()),
+        );
+
+        assert_eq!(contract.nft_supply_for_owner(account("bob.near")), U64(2));
+        assert_eq!(contract.nft_supply_for_owner(account("alice.near")), U64(0));
+        assert_eq!(
+            get_logs(),
+            vec![
+                "Memo: drop",
+                r#"EVENT_JSON:{"standard":"nep171","version":"1.0.0","event":"nft_transfer","data":[{"old_owner_id":"alice.near","new_owner_id":"bob.near","token_ids":["1","2"],"memo":"drop"},{"old_owner_id":"alice.near","new_owner_id":"carol.near","token_ids":["3"],"memo":"drop"}]}"#,
+            ]
+        );
+        //both approvals are refunded to alice in a single transfer
+        assert_eq!(get_created_receipts().len(), 1);
+        assert_eq!(
+            refunded_to(&account("alice.near")),
+            env::storage_byte_cost().saturating_mul(2 * bytes_for_approved_account_id(&account("market.near")))
+        );
+    }
+
+    #[test]
+    fn approved_account_batch_transfers_as_authorized_id() {
+        let mut contract = setup_contract();
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_batch_mint(batch(&["1"], "alice.near"), None, None);
+        set_context(account("alice.near"), NearToken::from_millinear(10));
+        contract.nft_approve("1".to_string(), account("market.near"), None);
+
+        set_context(account("market.near"), ONE_YOCTONEAR);
+        contract.nft_batch_transfer(vec![("1".to_string(), account("bob.near"))], None);
+
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"nep171","version":"1.0.0","event":"nft_transfer","data":[{"authorized_id":"market.near","old_owner_id":"alice.near","new_owner_id":"bob.near","token_ids":["1"]}]}"#]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The token owner and the receiver should be different")]
+    fn batch_transfer_fails_if_any_transfer_fails() {
+        let mut contract = setup_contract();
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_batch_mint(batch(&["1", "2"], "alice.near"), None, None);
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_batch_transfer(
+            vec![
+                ("1".to_string(), account("carol.near")),
+                ("2".to_string(), account("alice.near")),
+            ],
+            None,
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use crate::*;
+
+#[near]
+impl ArtEcho {
+    //burn a token. The owner or an approved account can burn it, and the owner is refunded the released storage.
+    //a token that can't be transferred can only be burned by its issuer. Burning a wrapped token releases the original to the owner
+    #[payable]
+    pub fn nft_burn(&mut self, token_id: TokenId, memo: Option<String>) {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+        //get the caller of the function
+        let sender_id = env::predecessor_account_id();
+
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        //burn the token and get the burned token object back
+        let token = self.internal_burn(&sender_id, &token_id, memo);
+
+        //calculate the storage that was released by removing the token
+        let released_storage_in_bytes = initial_storage_usage.saturating_sub(self.internal_storage_usage());
+
+        //refund the owner for the storage that was released
+        let refund = env::storage_byte_cost().saturating_mul(released_storage_in_bytes.into());
+        if refund.gt(&NearToken::from_yoctonear(0)) {
+            Promise::new(token.owner_id).transfer(refund);
+        }
+    }
+}
+
+impl ArtEcho {
+    //burns the token (internal method and can't be called directly via CLI).
+    pub(crate) fn internal_burn(
+        &mut self,
+        sender_id: &AccountId,
+        token_id: &TokenId,
+        memo: Option<String>,
+    ) -> Token {
+        //make sure the token isn't still being migrated
+        self.assert_migrated();
+
+        //get the token object by passing in the token_id
+        let token = self.tokens_by_id.get(token_id).cloned().expect("No token");
+
+        //a token frozen by a moderator has to stay as it is
+        assert!(!self.transfer_frozen_tokens.contains_key(token_id), "Token is frozen");
+
+        //soulbound tokens can only be revoked by their issuer
+        let mut authorized_id = None;
+        if let Some(issuer_id) = self.internal_soulbound_issuer(token_id, &token) {
+            assert_eq!(sender_id, &issuer_id, "Only the issuer can burn a non-transferable token");
+            if sender_id != &token.owner_id {
+                authorized_id = Some(sender_id.to_string());
+            }
+        //if the sender doesn't equal the owner, we check if the sender is in the approval list
+        } else if sender_id != &token.owner_id {
+            //if the token's approved account IDs doesn't contain the sender, we panic
+            assert!(token.approved_account_ids.contains_key(sender_id), "Unauthorized");
+            authorized_id = Some(sender_id.to_string());
+        }
+
+        //remove the token from the owner, the series, the collection and the token collections
+        self.internal_remove_token_from_owner(&token.owner_id, token_id);
+        if let Some(series_id) = token.series_id.as_ref() {
+            self.internal_remove_token_from_series(series_id, token_id);
+        }
+        self.internal_remove_token_from_collection(token_id);
+        self.tokens_by_id.remove(token_id);
+        self.token_metadata_by_id.remove(token_id);
+        self.sorted_token_ids.remove(token_id);
+        self.frozen_tokens.remove(token_id);
+        self.soulbound_tokens.remove(token_id);
+        self.collab_tokens.remove(token_id);
+        //the storage of the rental listing and the lease is refunded with the rest of the token
+        self.internal_remove_rental(token_id);
+        self.internal_remove_token_from_creator(token_id);
+        //the original of a wrapped token is released to the owner
+        if let Some(wrapped) = self.wrapped_tokens.remove(token_id) {
+            self.internal_release_wrapped(token_id.clone(), wrapped, token.owner_id.clone());
+        }
+
+        // Construct the burn log as per the events standard.
+        let nft_burn_log: EventLog = EventLog {
+            // Standard name ("nep171").
+            standard: NFT_STANDARD_NAME.to_string(),
+            // Version of the standard ("nft-1.0.0").
+            version: NFT_METADATA_SPEC.to_string(),
+            // The data related with the event stored in a vector.
+            event: EventLogVariant::NftBurn(vec![NftBurnLog {
+                // The owner of the token that was burned.
+                owner_id: token.owner_id.to_string(),
+                // The optional authorized account ID that burned the token on behalf of the owner.
+                authorized_id,
+                // A vector containing the token IDs as strings.
+                token_ids: vec![token_id.to_string()],
+                // An optional memo to include.
+                memo,
+            }]),
+        };
+
+        // Log the serialized json.
+        env::log_str(&nft_burn_log.to_string());
+
+        //return the token object that was burned
+        token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::get_logs;
+
+    const TOKEN_ID: &str = "piece";
+
+    //a contract with a token owned by alice
+    fn setup_token() -> ArtEcho {
+        let mut contract = setup_contract();
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint(TOKEN_ID.to_string(), sample_metadata(), account("alice.near"), None, None);
+        contract
+    }
+
+    #[test]
+    fn owner_burn_refunds_storage() {
+        let mut contract = setup_token();
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_burn(TOKEN_ID.to_string(), None);
+
+        assert!(contract.nft_token(TOKEN_ID.to_string()).is_none());
+        assert!(refunded_to(&account("alice.near")) > NearToken::from_yoctonear(0));
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"nep171","version":"1.0.0","event":"nft_burn","data":[{"owner_id":"alice.near","token_ids":["piece"]}]}"#]
+        );
+    }
+
+    #[test]
+    fn approved_account_burn_is_logged_as_authorized() {
+        let mut contract = setup_token();
+        set_context(account("alice.near"), NearToken::from_millinear(10));
+        contract.nft_approve(TOKEN_ID.to_string(), account("bob.near"), None);
+
+        set_context(account("bob.near"), ONE_YOCTONEAR);
+        contract.nft_burn(TOKEN_ID.to_string(), Some("cleanup".to_string()));
+
+        assert!(contract.nft_token(TOKEN_ID.to_string()).is_none());
+        //the owner gets the storage back, not the approved account
+        assert!(refunded_to(&account("alice.near")) > NearToken::from_yoctonear(0));
+        assert_eq!(refunded_to(&account("bob.near")), NearToken::from_yoctonear(0));
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"nep171","version":"1.0.0","event":"nft_burn","data":[{"owner_id":"alice.near","authorized_id":"bob.near","token_ids":["piece"],"memo":"cleanup"}]}"#]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Unauthorized")]
+    fn unauthorized_burn_panics() {
+        let mut contract = setup_token();
+
+        set_context(account("bob.near"), ONE_YOCTONEAR);
+        contract.nft_burn(TOKEN_ID.to_string(), None);
+    }
+
+    #[test]
+    fn burned_token_leaves_supply_and_enumeration() {
+        let mut contract = setup_token();
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint("another".to_string(), sample_metadata(), account("alice.near"), None, None);
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_burn(TOKEN_ID.to_string(), None);
+
+        assert_eq!(contract.nft_total_supply(), U64(1));
+        assert_eq!(contract.nft_supply_for_owner(account("alice.near")), U64(1));
+        let token_ids: Vec<TokenId> = contract
+            .nft_tokens(None, None)
+            .into_iter()
+            .map(|token| token.token_id)
+            .collect();
+        assert_eq!(token_ids, vec!["another".to_string()]);
+        let owned_ids: Vec<TokenId> = contract
+            .nft_tokens_for_owner(account("alice.near"), None, None)
+            .into_iter()
+            .map(|token| token.token_id)
+            .collect();
+        assert_eq!(owned_ids, vec!["another".to_string()]);
+    }
+}
@@ -0,0 +1,450 @@
+use crate::*;
+use near_sdk::log;
+
+//the storage a collaborative token can take up once it's minted, on top of the storage of its pending collaboration.
+//the token keeps the metadata, receiver and creators of the pending collaboration, so it takes up at most as much again,
+//plus a record in each index it's added to. Every record is sized for a hashed storage prefix and the longest account ID
+pub(crate) fn bytes_for_collab_token(token_id: &TokenId, pending_storage_in_bytes: u64) -> u64 {
+    //the token, its metadata, its creators and its issuer, the sorted token IDs and the owner's and creator's sets
+    const INDEX_RECORDS: u64 = 12;
+    let record = 40 + 33 + 4 + token_id.len() as u64 + 4 + MAX_ACCOUNT_ID_LEN as u64 + 32;
+    pending_storage_in_bytes + INDEX_RECORDS * record
+}
+
+//whether a co-creator signed off on a collaborative token
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug, NearSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum CreatorStatus {
+  Pending,
+  Accepted,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, NearSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct Creator {
+  //the co-creator
+  pub account_id: AccountId,
+  //the royalty the co-creator is paid on every sale, in basis points
+  pub share: u32,
+  //whether the co-creator accepted the mint and the split
+  pub status: CreatorStatus,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, NearSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct Collab {
+  //the account that minted the token and paid for its storage
+  pub minter_id: AccountId,
+  //when the collaboration is cancelled if not every co-creator accepted, in milliseconds since the Unix epoch
+  pub deadline: u64,
+  //the co-creators and their splits
+  pub creators: Vec<Creator>,
+}
+
+//a collaborative token that isn't minted until every co-creator accepted
+#[derive(BorshDeserialize, BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct PendingCollab {
+  //the account the token is minted to
+  pub receiver_id: AccountId,
+  //the metadata the token is minted with
+  pub metadata: TokenMetadata,
+  //whether the token can be transferred once it's minted
+  pub transferable: bool,
+  //the part of the minter's deposit that pays for the storage of the token once it's minted
+  pub deposit: NearToken,
+  //the co-creators and whether each of them accepted
+  pub collab: Collab,
+}
+
+impl Collab {
+  //a collaborative token is pending until every co-creator accepted
+  pub fn is_pending(&self) -> bool {
+      self.creators.iter().any(|creator| creator.status == CreatorStatus::Pending)
+  }
+}
+
+#[near]
+impl ArtEcho {
+    /*
+        mint a token made by several creators. Every creator gets their share of the split as a royalty, but
+        the token isn't minted until each of them accepted with nft_accept_collab. The minter accepts by minting.
+        The minter pays for the storage of the pending collaboration and leaves enough to pay for the storage of the
+        token, so the last creator to accept can always mint it. What the token doesn't use is refunded once it's minted.
+        If a creator rejects or the deadline passes first, the mint is cancelled and the minter is refunded.
+        Only minters can mint collaborative tokens
+    */
+    #[payable]
+    pub fn nft_mint_collab(
+        &mut self,
+        token_id: TokenId,
+        metadata: TokenMetadata,
+        receiver_id: AccountId,
+        //the share of every co-creator in basis points
+        creators: HashMap<AccountId, u32>,
+        deadline: u64,
+        transferable: Option<bool>,
+    ) {
+        //make sure mints aren't stopped and the tokens aren't being migrated
+        self.assert_not_paused();
+        self.assert_migrated();
+        //make sure the caller is allowed to mint
+        self.assert_minter();
+        //make sure the token ID can't collide with the editions of a series
+        assert_valid_token_id(&token_id);
+        assert!(
+            self.tokens_by_id.get(&token_id).is_none() && !self.pending_collabs.contains_key(&token_id),
+            "Token already exists"
+        );
+        //a collaboration needs someone to collaborate with and time to accept
+        assert!(creators.len() > 1, "A collaborative token needs at least two creators");
+        assert!(deadline > env::block_timestamp_ms(), "Deadline must be in the future");
+        //make sure the splits can be payed out as royalties
+        assert_valid_royalties(&creators);
+        //make sure the media can be linked if it points to an upload, so it's still there once the token is minted
+        let mut metadata = metadata;
+        link_upload(&mut self.uploads, &mut metadata);
+
+        //get the caller of the function
+        let minter_id = env::predecessor_account_id();
+
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        //the minter accepts by minting if they are one of the creators
+        let creators = creators
+            .into_iter()
+            .map(|(account_id, share)| Creator {
+                status: if account_id == minter_id { CreatorStatus::Accepted } else { CreatorStatus::Pending },
+                account_id,
+                share,
+            })
+            .collect();
+        let pending_collab = PendingCollab {
+            receiver_id,
+            metadata,
+            transferable: transferable.unwrap_or(true),
+            deposit: NearToken::from_yoctonear(0),
+            collab: Collab { minter_id: minter_id.clone(), deadline, creators },
+        };
+        self.pending_collabs.insert(token_id.clone(), pending_collab);
+
+        //calculate the required storage which was the used - initial
+        let required_storage_in_bytes = self.internal_storage_usage() - initial_storage_usage;
+
+        //the storage of the pending collaboration and of the token it becomes are both covered up front
+        let storage_cost = env::storage_byte_cost().saturating_mul(required_storage_in_bytes.into());
+        let token_cost = env::storage_byte_cost()
+            .saturating_mul(bytes_for_collab_token(&token_id, required_storage_in_bytes).into());
+        let required_cost = storage_cost.saturating_add(token_cost);
+
+        //they're debited from the minter's storage balance or the attached deposit
+        let attached_deposit = env::attached_deposit();
+        let deposit = if self.internal_debit_storage(&minter_id, required_cost) {
+            token_cost.saturating_add(attached_deposit)
+        } else {
+            assert!(
+                required_cost <= attached_deposit,
+                "Must attach {} yoctoNEAR to cover storage",
+                required_cost,
+            );
+            attached_deposit.saturating_sub(storage_cost)
+        };
+
+        //what's left for the token is kept with the collaboration until it's minted
+        self.pending_collabs.get_mut(&token_id).unwrap().deposit = deposit;
+    }
+
+    //accept the mint and the split of a collaborative token. The token is minted once the last creator accepts
+    pub fn nft_accept_collab(&mut self, token_id: TokenId) {
+        //get the caller of the function
+        let creator_id = env::predecessor_account_id();
+
+        //get the collaboration and make sure it can still be accepted
+        let collab = &mut self.pending_collabs.get_mut(&token_id).expect("No pending collaboration").collab;
+        assert!(env::block_timestamp_ms() < collab.deadline, "Collaboration deadline has passed");
+
+        //mark the creator as accepted
+        let creator = collab
+            .creators
+            .iter_mut()
+            .find(|creator| creator.account_id == creator_id)
+            .expect("Not a creator of the token");
+        assert!(creator.status == CreatorStatus::Pending, "Creator already accepted");
+        creator.status = CreatorStatus::Accepted;
+
+        //the last creator to accept completes the mint
+        if !collab.is_pending() {
+            self.internal_complete_collab(&token_id);
+        }
+    }
+
+    //reject the mint or the split of a collaborative token. The mint is cancelled and the minter is refunded
+    pub fn nft_reject_collab(&mut self, token_id: TokenId) {
+        //make sure the caller is one of the creators of a token that is still pending
+        let pending_collab = self.pending_collabs.get(&token_id).expect("No pending collaboration");
+        assert!(
+            pending_collab.collab.creators.iter().any(|creator| creator.account_id == env::predecessor_account_id()),
+            "Not a creator of the token"
+        );
+
+        self.internal_cancel_collab(&token_id);
+    }
+
+    //cancel the mint of a collaborative token whose deadline passed before every creator accepted.
+    //anyone can call this, the minter is refunded
+    pub fn nft_cancel_collab(&mut self, token_id: TokenId) {
+        //make sure the token is still pending after its deadline
+        let pending_collab = self.pending_collabs.get(&token_id).expect("No pending collaboration");
+        assert!(env::block_timestamp_ms() >= pending_collab.collab.deadline, "Collaboration deadline hasn't passed");
+
+        self.internal_cancel_collab(&token_id);
+    }
+
+    //get the co-creators of a collaborative token and whether each of them accepted, whether it's minted or not
+    pub fn nft_collab(&self, token_id: TokenId) -> Option<Collab> {
+        match self.pending_collabs.get(&token_id) {
+            Some(pending_collab) => Some(pending_collab.collab.clone()),
+            None => self.collab_tokens.get(&token_id).cloned(),
+        }
+    }
+}
+
+impl ArtEcho {
+    //mint a collaborative token every creator accepted. The storage of the token is paid for by the deposit the minter
+    //left with the collaboration, which nft_mint_collab made sure covers it. The rest is refunded
+    fn internal_complete_collab(&mut self, token_id: &TokenId) {
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        //move the token out of the pending collaborations and mint it with the splits as its royalties
+        let PendingCollab { receiver_id, metadata, transferable, deposit, collab } =
+            self.pending_collabs.remove(token_id).unwrap();
+        let royalty = collab.creators.iter().map(|creator| (creator.account_id.clone(), creator.share)).collect();
+        let soulbound_issuer_id = (!transferable).then(|| collab.minter_id.clone());
+        self.internal_mint(token_id.clone(), metadata, receiver_id.clone(), Some(royalty), soulbound_issuer_id, collab.minter_id.clone());
+        let minter_id = collab.minter_id.clone();
+        self.collab_tokens.insert(token_id.clone(), collab);
+        log_nft_mint(&receiver_id, vec![token_id.clone()]);
+
+        //the token takes up more storage than the pending collaboration it replaced
+        let required_storage_in_bytes = self.internal_storage_usage().saturating_sub(initial_storage_usage);
+        let storage_cost = env::storage_byte_cost().saturating_mul(required_storage_in_bytes.into());
+
+        //pay for it with the deposit and refund the rest
+        let refund = deposit.saturating_sub(storage_cost);
+        if refund.gt(&NearToken::from_yoctonear(0)) {
+            Promise::new(minter_id).transfer(refund);
+        }
+    }
+
+    //remove a pending collaborative token and refund the minter the deposit and the storage that was released
+    fn internal_cancel_collab(&mut self, token_id: &TokenId) {
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        //the token was never minted so there is nothing else to remove and we don't log a burn
+        let pending_collab = self.pending_collabs.remove(token_id).unwrap();
+        log!("Collaboration on token {} was cancelled", token_id);
+
+        //calculate the storage that was released by removing the pending collaboration
+        let released_storage_in_bytes = initial_storage_usage.saturating_sub(self.internal_storage_usage());
+
+        //refund the minter the deposit and the storage that was released
+        let refund = env::storage_byte_cost()
+            .saturating_mul(released_storage_in_bytes.into())
+            .saturating_add(pending_collab.deposit);
+        if refund.gt(&NearToken::from_yoctonear(0)) {
+            Promise::new(pending_collab.collab.minter_id).transfer(refund);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_sdk::test_utils::get_logs;
+
+    const TOKEN_ID: &str = "duet";
+
+    //a contract with a token by the owner, alice and bob that is pending until 1000
+    fn setup_collab() -> ArtEcho {
+        let mut contract = setup_contract();
+        let creators = HashMap::from([(owner(), 500), (account("alice.near"), 300), (account("bob.near"), 200)]);
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint_collab(TOKEN_ID.to_string(), sample_metadata(), account("carol.near"), creators, 1000, None);
+        contract
+    }
+
+    fn status_of(contract: &ArtEcho, account_id: &str) -> CreatorStatus {
+        let collab = contract.nft_collab(TOKEN_ID.to_string()).unwrap();
+        collab.creators.into_iter().find(|creator| creator.account_id == account(account_id)).unwrap().status
+    }
+
+    #[test]
+    fn token_is_pending_until_every_creator_accepts() {
+        let mut contract = setup_collab();
+        assert_eq!(status_of(&contract, "owner.near"), CreatorStatus::Accepted);
+        assert_eq!(status_of(&contract, "alice.near"), CreatorStatus::Pending);
+        assert!(get_logs().is_empty());
+
+        set_context(account("alice.near"), NearToken::from_yoctonear(0));
+        contract.nft_accept_collab(TOKEN_ID.to_string());
+        assert!(contract.nft_token(TOKEN_ID.to_string()).is_none());
+        assert!(get_logs().is_empty());
+
+        set_context(account("bob.near"), NearToken::from_yoctonear(0));
+        contract.nft_accept_collab(TOKEN_ID.to_string());
+        assert_eq!(status_of(&contract, "bob.near"), CreatorStatus::Accepted);
+        assert!(get_logs()[0].contains("nft_mint"));
+
+        //the token is minted to the receiver with its co-creators
+        let token = contract.nft_token(TOKEN_ID.to_string()).unwrap();
+        assert_eq!(token.owner_id, account("carol.near"));
+        assert_eq!(contract.nft_collab(TOKEN_ID.to_string()).unwrap().creators.len(), 3);
+        assert_eq!(contract.nft_total_supply(), U64(1));
+        assert_eq!(contract.nft_supply_for_owner(account("carol.near")), U64(1));
+
+        //the minter is refunded the deposit the token didn't use
+        assert!(refunded_to(&owner()) > NearToken::from_yoctonear(0));
+
+        //the splits are paid out as royalties
+        let payout = contract.nft_payout(TOKEN_ID.to_string(), U128(10_000), None).payout;
+        assert_eq!(payout[&owner()], U128(500));
+        assert_eq!(payout[&account("alice.near")], U128(300));
+        assert_eq!(payout[&account("bob.near")], U128(200));
+        assert_eq!(payout[&account("carol.near")], U128(9_000));
+    }
+
+    #[test]
+    fn pending_token_is_not_minted() {
+        let contract = setup_collab();
+
+        assert!(contract.nft_token(TOKEN_ID.to_string()).is_none());
+        assert_eq!(contract.nft_total_supply(), U64(0));
+        assert_eq!(contract.nft_supply_for_owner(account("carol.near")), U64(0));
+        assert!(contract.nft_tokens(None, None).is_empty());
+        assert!(contract.nft_tokens_for_owner(account("carol.near"), None, None).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "No token")]
+    fn pending_token_cannot_be_transferred() {
+        let mut contract = setup_collab();
+
+        set_context(account("carol.near"), ONE_YOCTONEAR);
+        contract.nft_transfer(account("dave.near"), TOKEN_ID.to_string(), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "No token")]
+    fn pending_token_cannot_be_frozen() {
+        let mut contract = setup_collab();
+
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.nft_freeze_tokens(vec![TOKEN_ID.to_string()], None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Token already exists")]
+    fn pending_token_id_cannot_be_minted() {
+        let mut contract = setup_collab();
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint(TOKEN_ID.to_string(), sample_metadata(), account("dave.near"), None, None);
+    }
+
+    #[test]
+    fn rejection_cancels_the_mint_and_refunds_the_minter() {
+        let mut contract = setup_collab();
+
+        set_context(account("bob.near"), NearToken::from_yoctonear(0));
+        contract.nft_reject_collab(TOKEN_ID.to_string());
+
+        assert!(contract.nft_collab(TOKEN_ID.to_string()).is_none());
+        assert!(contract.nft_supply_for_owner(account("carol.near")).0 == 0);
+        //the minter gets back the storage of the collaboration and the deposit kept for the token
+        assert_eq!(refunded_to(&owner()), NearToken::from_near(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn paused_contract_blocks_collab_mints() {
+        let mut contract = setup_contract();
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.nft_pause(None);
+
+        let creators = HashMap::from([(owner(), 500), (account("alice.near"), 300)]);
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint_collab(TOKEN_ID.to_string(), sample_metadata(), account("carol.near"), creators, 1000, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Collaboration deadline has passed")]
+    fn cannot_accept_after_the_deadline() {
+        let mut contract = setup_collab();
+
+        set_context_at(account("alice.near"), NearToken::from_yoctonear(0), 1000);
+        contract.nft_accept_collab(TOKEN_ID.to_string());
+    }
+
+    #[test]
+    fn anyone_can_cancel_after_the_deadline() {
+        let mut contract = setup_collab();
+
+        set_context_at(account("dave.near"), NearToken::from_yoctonear(0), 1000);
+        contract.nft_cancel_collab(TOKEN_ID.to_string());
+
+        assert!(contract.nft_collab(TOKEN_ID.to_string()).is_none());
+        assert_eq!(refunded_to(&owner()), NearToken::from_near(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Collaboration deadline hasn't passed")]
+    fn cannot_cancel_before_the_deadline() {
+        let mut contract = setup_collab();
+
+        set_context_at(account("dave.near"), NearToken::from_yoctonear(0), 999);
+        contract.nft_cancel_collab(TOKEN_ID.to_string());
+    }
+
+    #[test]
+    fn storage_of_the_token_is_covered_up_front() {
+        let mut contract = setup_contract();
+        set_context(owner(), NearToken::from_near(1));
+        contract.storage_deposit(None, None);
+
+        //the minter doesn't attach anything, so the pending collaboration and the token are paid for by their balance
+        let receiver_id = account("a-receiver-with-a-long-account-id-that-owns-nothing-yet.near");
+        let creators = HashMap::from([
+            (owner(), 500),
+            (account("a-co-creator-with-a-long-account-id-that-made-nothing-yet.near"), 300),
+        ]);
+        set_context(owner(), NearToken::from_yoctonear(0));
+        contract.nft_mint_collab(TOKEN_ID.to_string(), sample_metadata(), receiver_id.clone(), creators, 1000, Some(false));
+        assert!(contract.storage_balance_of(owner()).unwrap().available < NearToken::from_near(1));
+
+        set_context(account("a-co-creator-with-a-long-account-id-that-made-nothing-yet.near"), NearToken::from_yoctonear(0));
+        contract.nft_accept_collab(TOKEN_ID.to_string());
+
+        //the token was minted with what the minter left and the part it didn't use is refunded
+        assert_eq!(contract.nft_token(TOKEN_ID.to_string()).unwrap().owner_id, receiver_id);
+        assert!(refunded_to(&owner()) > NearToken::from_yoctonear(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "to cover storage")]
+    fn collab_mint_must_cover_the_token_up_front() {
+        let mut contract = setup_contract();
+
+        let creators = HashMap::from([(owner(), 500), (account("alice.near"), 300)]);
+        set_context(owner(), NearToken::from_millinear(1));
+        contract.nft_mint_collab(TOKEN_ID.to_string(), sample_metadata(), account("carol.near"), creators, 1000, None);
+    }
+}
@@ -0,0 +1,481 @@
+use crate::*;
+
+pub type CollectionId = String;
+
+//the delimiter between the collection ID and the token ID in the token IDs of a collection, e.g. "birds/robin"
+pub const COLLECTION_DELIMITER: char = '/';
+
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct Collection {
+  //the account that manages the collection and decides who can mint in it
+  pub owner_id: AccountId,
+  //the name, symbol, icon and base URI of the collection
+  pub metadata: NFTContractMetadata,
+  //the perpetual royalties tokens are minted with if the minter doesn't pass any
+  pub royalty: HashMap<AccountId, u32>,
+  //accounts that are allowed to mint in the collection besides the owner
+  pub minters: Vec<AccountId>,
+}
+
+//The Json collection is what will be returned from view calls.
+#[derive(Serialize, Deserialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonCollection {
+  //collection ID
+  pub collection_id: CollectionId,
+  //owner of the collection
+  pub owner_id: AccountId,
+  //name, symbol, icon and base URI of the collection
+  pub metadata: NFTContractMetadata,
+  //default perpetual royalties of the tokens
+  pub royalty: HashMap<AccountId, u32>,
+  //accounts that can mint in the collection besides the owner
+  pub minters: Vec<AccountId>,
+  //number of tokens in the collection
+  pub supply: U64,
+}
+
+//get the collection a token was minted in from its token ID
+pub(crate) fn collection_of(token_id: &TokenId) -> Option<CollectionId> {
+    //wrapped tokens keep the token ID of the original, which can contain the delimiter of its own collections
+    if token_id.contains(WRAP_DELIMITER) {
+        return None;
+    }
+    token_id
+        .split_once(COLLECTION_DELIMITER)
+        .map(|(collection_id, _)| collection_id.to_string())
+}
+
+#[near]
+impl ArtEcho {
+    /*
+        create a collection that tokens can be minted in, with its own metadata and default royalties.
+        Only minters can create collections. The collection is owned by `owner_id`, which defaults to the caller
+    */
+    #[payable]
+    pub fn nft_create_collection(
+        &mut self,
+        collection_id: CollectionId,
+        metadata: NFTContractMetadata,
+        //the perpetual royalties tokens get if they are minted without any
+        royalty: Option<HashMap<AccountId, u32>>,
+        owner_id: Option<AccountId>,
+    ) {
+        //make sure the caller is allowed to create collections
+        self.assert_minter();
+
+        //make sure the collection ID can be used as the prefix of the token IDs
+        assert!(!collection_id.is_empty(), "Collection ID cannot be empty");
+        assert!(
+            !collection_id.contains(COLLECTION_DELIMITER),
+            "Collection ID cannot contain '{}'", COLLECTION_DELIMITER
+        );
+        assert!(
+            !collection_id.contains(WRAP_DELIMITER),
+            "Collection ID cannot contain '{}'", WRAP_DELIMITER
+        );
+
+        //make sure the default royalties can be payed out
+        let royalty = royalty.unwrap_or_default();
+        assert_valid_royalties(&royalty);
+
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        //specify the collection struct, owned by the caller unless an owner was passed in
+        let collection = Collection {
+            owner_id: owner_id.unwrap_or_else(env::predecessor_account_id),
+            metadata,
+            royalty,
+            minters: vec![],
+        };
+
+        //insert the collection ID and collection struct and make sure that the collection doesn't exist
+        assert!(
+            self.collections_by_id.insert(collection_id, collection).is_none(),
+            "Collection already exists"
+        );
+
+        //calculate the required storage which was the used - initial
+        let required_storage_in_bytes = self.internal_storage_usage() - initial_storage_usage;
+
+        //refund any excess storage if the user attached too much. Panic if they didn't attach enough to cover the required.
+        self.refund_deposit(required_storage_in_bytes.into());
+    }
+
+    /*
+        mint a token in a collection. Its token ID is the collection ID and `token_id` joined by the delimiter.
+        Only the owner and the minters of the collection can mint in it. Tokens minted without perpetual royalties
+        get the default royalties of the collection. Returns the token ID of the minted token
+    */
+    #[payable]
+    pub fn nft_collection_mint(
+        &mut self,
+        collection_id: CollectionId,
+        token_id: TokenId,
+        metadata: TokenMetadata,
+        receiver_id: AccountId,
+        perpetual_royalties: Option<HashMap<AccountId, u32>>,
+        //tokens that aren't transferable stay with the receiver until the minter burns them. Defaults to true
+        transferable: Option<bool>,
+    ) -> TokenId {
+        //make sure mints aren't stopped
+        self.assert_not_paused();
+
+        //get the caller of the function
+        let minter_id = env::predecessor_account_id();
+
+        //make sure the caller can mint in the collection
+        let collection = self.collections_by_id.get(&collection_id).expect("No collection");
+        assert!(
+            collection.owner_id == minter_id || collection.minters.contains(&minter_id),
+            "Unauthorized collection minter"
+        );
+        let perpetual_royalties = perpetual_royalties.unwrap_or_else(|| collection.royalty.clone());
+
+        //namespace the token ID by the collection
+        assert!(!token_id.is_empty(), "Token ID cannot be empty");
+        assert_valid_token_id(&token_id);
+        let token_id = format!("{}{}{}", collection_id, COLLECTION_DELIMITER, token_id);
+
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        //mint the token and add it to the collection
+        let soulbound_issuer_id = (!transferable.unwrap_or(true)).then(|| minter_id.clone());
+        let token = self.internal_mint(
+            token_id.clone(),
+            metadata,
+            receiver_id,
+            Some(perpetual_royalties),
+            soulbound_issuer_id,
+            minter_id,
+        );
+        self.internal_add_token_to_collection(&collection_id, &token_id);
+        log_nft_mint(&token.owner_id, vec![token_id.clone()]);
+
+        //calculate the required storage which was the used - initial
+        let required_storage_in_bytes = self.internal_storage_usage() - initial_storage_usage;
+
+        //refund any excess storage if the user attached too much. Panic if they didn't attach enough to cover the required.
+        self.refund_deposit(required_storage_in_bytes.into());
+
+        token_id
+    }
+
+    //allow an account to mint in a collection. Only the owner of the collection can add minters
+    #[payable]
+    pub fn nft_add_collection_minter(&mut self, collection_id: CollectionId, account_id: AccountId) {
+        //assert at least one yocto for security reasons and to pay for storage growth
+        assert_at_least_one_yocto();
+
+        //get the collection object and make sure the caller is the owner
+        let collection = self.collections_by_id.get(&collection_id).expect("No collection");
+        self.assert_collection_owner(collection);
+        assert!(!collection.minters.contains(&account_id), "Already a collection minter");
+
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        self.collections_by_id.get_mut(&collection_id).unwrap().minters.push(account_id);
+
+        //charge the caller for the extra storage used
+        self.refund_storage_difference(initial_storage_usage);
+    }
+
+    //stop an account from minting in a collection. Only the owner of the collection can remove minters
+    #[payable]
+    pub fn nft_remove_collection_minter(&mut self, collection_id: CollectionId, account_id: AccountId) {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+
+        //get the collection object and make sure the caller is the owner
+        let collection = self.collections_by_id.get(&collection_id).expect("No collection");
+        self.assert_collection_owner(collection);
+
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        //find the account in the list of minters and remove it
+        let minters = &mut self.collections_by_id.get_mut(&collection_id).unwrap().minters;
+        if let Some(index) = minters.iter().position(|minter_id| minter_id == &account_id) {
+            minters.swap_remove(index);
+        } else {
+            env::panic_str("Not a collection minter");
+        }
+
+        //refund the caller the storage that was released
+        self.refund_storage_difference(initial_storage_usage);
+    }
+
+    //replace the metadata and default royalties of a collection. Only the owner of the collection can update it
+    #[payable]
+    pub fn nft_update_collection(
+        &mut self,
+        collection_id: CollectionId,
+        metadata: NFTContractMetadata,
+        royalty: Option<HashMap<AccountId, u32>>,
+    ) {
+        //assert at least one yocto for security reasons and to pay for storage growth
+        assert_at_least_one_yocto();
+
+        //get the collection object and make sure the caller is the owner
+        let collection = self.collections_by_id.get(&collection_id).expect("No collection");
+        self.assert_collection_owner(collection);
+
+        //make sure the default royalties can be payed out
+        let royalty = royalty.unwrap_or_default();
+        assert_valid_royalties(&royalty);
+
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        //replace the metadata and royalties of the collection. Tokens that were already minted keep their royalties
+        let collection = self.collections_by_id.get_mut(&collection_id).unwrap();
+        collection.metadata = metadata;
+        collection.royalty = royalty;
+
+        //charge the caller for the extra storage used or refund them the storage that was released
+        self.refund_storage_difference(initial_storage_usage);
+    }
+
+    //get the information for a specific collection ID
+    pub fn nft_get_collection(&self, collection_id: CollectionId) -> Option<JsonCollection> {
+        self.collections_by_id
+            .get(&collection_id)
+            .map(|collection| self.internal_collection_to_json(collection_id.clone(), collection.clone()))
+    }
+
+    //Query for the collections on the contract using pagination
+    pub fn nft_collections(&self, from_index: Option<U128>, limit: Option<u32>) -> Vec<JsonCollection> {
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        //iterate through each collection using an iterator
+        self.collections_by_id.iter()
+            //skip to the index we specified in the start variable
+            .skip(start as usize)
+            //take the first "limit" elements in the vector, capped so the view can't run out of gas
+            .take(page_limit(limit))
+            //we'll map the collections into Json Collections
+            .map(|(collection_id, collection)| self.internal_collection_to_json(collection_id.clone(), collection.clone()))
+            //since we turned the collections into an iterator, we need to turn it back into a vector to return
+            .collect()
+    }
+
+    //get the number of tokens minted in a given collection
+    pub fn nft_collection_supply(&self, collection_id: CollectionId) -> U64 {
+        //get the set of tokens for the passed in collection
+        if let Some(tokens_for_collection_set) = self.tokens_per_collection.get(&collection_id) {
+            U64(tokens_for_collection_set.len().into())
+        } else {
+            //if there isn't a set of tokens for the passed in collection ID, we'll return 0
+            U64(0)
+        }
+    }
+
+    //Query for all the tokens minted in a collection
+    pub fn nft_tokens_for_collection(
+        &self,
+        collection_id: CollectionId,
+        from_index: Option<U128>,
+        limit: Option<u32>,
+    ) -> Vec<JsonToken> {
+        //get the set of tokens for the passed in collection
+        let tokens = if let Some(tokens_for_collection_set) = self.tokens_per_collection.get(&collection_id) {
+            tokens_for_collection_set
+        } else {
+            //if there is no set of tokens, we'll simply return an empty vector.
+            return vec![];
+        };
+
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        //iterate through the keys vector
+        tokens.iter()
+            //skip to the index we specified in the start variable
+            .skip(start as usize)
+            //take the first "limit" elements in the vector, capped so the view can't run out of gas
+            .take(page_limit(limit))
+            //we'll map the token IDs which are strings into Json Tokens
+            .map(|token_id| self.nft_token(token_id.clone()).unwrap())
+            //since we turned the keys into an iterator, we need to turn it back into a vector to return
+            .collect()
+    }
+}
+
+impl ArtEcho {
+    //make sure the caller owns the collection
+    pub(crate) fn assert_collection_owner(&self, collection: &Collection) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            collection.owner_id,
+            "Predecessor must be the collection owner."
+        );
+    }
+
+    //add a token to the set of tokens minted in a collection
+    pub(crate) fn internal_add_token_to_collection(
+        &mut self,
+        collection_id: &CollectionId,
+        token_id: &TokenId,
+    ) {
+        //get the set of tokens for the given collection
+        let tokens_set = self.tokens_per_collection.entry(collection_id.clone()).or_insert_with(|| {
+            //if the collection doesn't have any tokens, we create a new iterable set
+            IterableSet::new(
+                StorageKey::TokensPerCollectionInner {
+                    //we get a new unique prefix for the collection
+                    collection_id_hash: hash_collection_id(collection_id),
+                },
+            )
+        });
+
+        //we insert the token ID into the set and write it to storage
+        tokens_set.insert(token_id.clone());
+        tokens_set.flush();
+    }
+
+    //remove a token from the set of tokens minted in its collection, if it was minted in one
+    pub(crate) fn internal_remove_token_from_collection(&mut self, token_id: &TokenId) {
+        let Some(collection_id) = collection_of(token_id) else {
+            return;
+        };
+
+        //we get the set of tokens minted in the collection
+        if let Some(tokens_set) = self.tokens_per_collection.get_mut(&collection_id) {
+            //we remove the the token_id from the set of tokens and write it to storage
+            tokens_set.remove(token_id);
+            tokens_set.flush();
+
+            //if the token set is now empty, we remove the collection from the tokens_per_collection collection
+            if tokens_set.is_empty() {
+                self.tokens_per_collection.remove(&collection_id);
+            }
+        }
+    }
+
+    //convert a collection into the struct returned from view calls
+    pub(crate) fn internal_collection_to_json(&self, collection_id: CollectionId, collection: Collection) -> JsonCollection {
+        JsonCollection {
+            supply: self.nft_collection_supply(collection_id.clone()),
+            collection_id,
+            owner_id: collection.owner_id,
+            metadata: collection.metadata,
+            royalty: collection.royalty,
+            minters: collection.minters,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    const COLLECTION_ID: &str = "birds";
+
+    fn collection_metadata() -> NFTContractMetadata {
+        NFTContractMetadata {
+            spec: "nft-1.0.0".to_string(),
+            name: "Birds".to_string(),
+            symbol: "BIRD".to_string(),
+            icon: None,
+            base_uri: Some("https://birds.example".to_string()),
+            reference: None,
+            reference_hash: None,
+        }
+    }
+
+    //a contract with a collection owned by alice that pays 5% to alice by default
+    fn setup_collection() -> ArtEcho {
+        let mut contract = setup_contract();
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_create_collection(
+            COLLECTION_ID.to_string(),
+            collection_metadata(),
+            Some(HashMap::from([(account("alice.near"), 500)])),
+            Some(account("alice.near")),
+        );
+        contract
+    }
+
+    fn mint(contract: &mut ArtEcho, minter_id: &str, token_id: &str) -> TokenId {
+        set_context(account(minter_id), NearToken::from_near(1));
+        contract.nft_collection_mint(
+            COLLECTION_ID.to_string(),
+            token_id.to_string(),
+            sample_metadata(),
+            account("carol.near"),
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn tokens_are_namespaced_by_the_collection() {
+        let mut contract = setup_collection();
+        let token_id = mint(&mut contract, "alice.near", "robin");
+        assert_eq!(token_id, "birds/robin");
+
+        let token = contract.nft_token(token_id).unwrap();
+        assert_eq!(token.collection_id, Some(COLLECTION_ID.to_string()));
+        //the token gets the default royalties of the collection
+        assert_eq!(token.royalty, HashMap::from([(account("alice.near"), 500)]));
+
+        let collection = contract.nft_get_collection(COLLECTION_ID.to_string()).unwrap();
+        assert_eq!(collection.owner_id, account("alice.near"));
+        assert_eq!(collection.metadata.symbol, "BIRD");
+        assert_eq!(collection.supply, U64(1));
+    }
+
+    #[test]
+    fn tokens_can_be_enumerated_per_collection() {
+        let mut contract = setup_collection();
+        mint(&mut contract, "alice.near", "robin");
+        mint(&mut contract, "alice.near", "wren");
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint("other".to_string(), sample_metadata(), account("carol.near"), None, None);
+
+        assert_eq!(contract.nft_collection_supply(COLLECTION_ID.to_string()), U64(2));
+        let tokens = contract.nft_tokens_for_collection(COLLECTION_ID.to_string(), Some(U128(1)), Some(10));
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].token_id, "birds/wren");
+
+        //burned tokens leave the collection
+        set_context(account("carol.near"), ONE_YOCTONEAR);
+        contract.nft_burn("birds/robin".to_string(), None);
+        assert_eq!(contract.nft_collection_supply(COLLECTION_ID.to_string()), U64(1));
+    }
+
+    #[test]
+    fn collection_minters_can_mint() {
+        let mut contract = setup_collection();
+
+        set_context(account("alice.near"), NearToken::from_millinear(10));
+        contract.nft_add_collection_minter(COLLECTION_ID.to_string(), account("bob.near"));
+        mint(&mut contract, "bob.near", "robin");
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_remove_collection_minter(COLLECTION_ID.to_string(), account("bob.near"));
+        assert!(contract.nft_get_collection(COLLECTION_ID.to_string()).unwrap().minters.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Unauthorized collection minter")]
+    fn contract_minters_cannot_mint_in_a_collection_they_dont_own() {
+        let mut contract = setup_collection();
+        mint(&mut contract, "owner.near", "robin");
+    }
+
+    #[test]
+    #[should_panic(expected = "Token ID cannot contain '/'")]
+    fn one_off_tokens_cannot_use_the_collection_namespace() {
+        let mut contract = setup_collection();
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint("birds/robin".to_string(), sample_metadata(), account("carol.near"), None, None);
+    }
+}
@@ -0,0 +1,257 @@
+use crate::*;
+use crate::mint::ext_registrar;
+use near_sdk::{log, Gas, PromiseResult};
+
+const GAS_FOR_IS_VERIFIED: Gas = Gas::from_tgas(5);
+const GAS_FOR_RESOLVE_VERIFY_CREATOR: Gas = Gas::from_tgas(5);
+
+#[near]
+impl ArtEcho {
+    //get the total supply of NFTs created by a given account
+    pub fn nft_supply_for_creator(&self, account_id: AccountId) -> U64 {
+        //if there is some set of tokens, we'll return the length
+        self.tokens_per_creator
+            .get(&account_id)
+            .map_or(U64(0), |tokens_for_creator_set| U64(tokens_for_creator_set.len().into()))
+    }
+
+    //Query for all the tokens created by an account, whoever owns them now
+    pub fn nft_tokens_for_creator(
+        &self,
+        account_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u32>,
+    ) -> Vec<JsonToken> {
+        //get the set of tokens for the passed in creator
+        let tokens = if let Some(tokens_for_creator_set) = self.tokens_per_creator.get(&account_id) {
+            tokens_for_creator_set
+        } else {
+            //if there is no set of tokens, we'll simply return an empty vector.
+            return vec![];
+        };
+
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        //iterate through the keys vector
+        tokens.iter()
+            //skip to the index we specified in the start variable
+            .skip(start as usize)
+            //take the first "limit" elements in the vector, capped so the view can't run out of gas
+            .take(page_limit(limit))
+            //we'll map the token IDs which are strings into Json Tokens
+            .map(|token_id| self.nft_token(token_id.clone()).unwrap())
+            //since we turned the keys into an iterator, we need to turn it back into a vector to return
+            .collect()
+    }
+
+    //get whether a creator was verified by the registrar the last time they were checked
+    pub fn nft_is_creator_verified(&self, account_id: AccountId) -> bool {
+        self.verified_creators.contains(&account_id)
+    }
+
+    /*
+        ask the registrar whether a creator is a verified artist and remember the answer, so views can report it.
+        Anyone can call this to refresh the answer after the creator was verified or unverified in the registrar
+    */
+    pub fn nft_verify_creator(&mut self, account_id: AccountId) -> Promise {
+        //the check is optional, so there's nothing to ask without a registrar
+        let registrar_id = self.registrar_id.clone().expect("No registrar");
+
+        // Ask the registrar if the creator is verified and remember the answer on our own contract
+        ext_registrar::ext(registrar_id)
+            .with_static_gas(GAS_FOR_IS_VERIFIED)
+            .is_verified(account_id.clone())
+        .then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_VERIFY_CREATOR)
+                .nft_resolve_verify_creator(account_id)
+        )
+    }
+
+    //resolves the registrar check of nft_verify_creator. Returns whether the creator is verified
+    #[private]
+    pub fn nft_resolve_verify_creator(&mut self, account_id: AccountId) -> bool {
+        let is_verified = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice::<bool>(&value).unwrap_or(false)
+            }
+            //a failed check doesn't tell us anything, so we keep the last answer
+            _ => {
+                log!("The registrar couldn't check @{}, keeping the last answer", account_id);
+                return self.verified_creators.contains(&account_id);
+            }
+        };
+
+        //the storage of a verified creator is paid for by the contract
+        if is_verified {
+            self.verified_creators.insert(account_id);
+        } else {
+            self.verified_creators.remove(&account_id);
+        }
+
+        is_verified
+    }
+}
+
+impl ArtEcho {
+    //record the creator of a token and add the token to the set of tokens the creator made
+    pub(crate) fn internal_add_token_to_creator(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &TokenId,
+    ) {
+        self.token_creators.insert(token_id.clone(), account_id.clone());
+
+        //get the set of tokens for the given creator
+        let tokens_set = self.tokens_per_creator.entry(account_id.clone()).or_insert_with(|| {
+            //if the creator doesn't have any tokens, we create a new iterable set
+            IterableSet::new(
+                StorageKey::TokensPerCreatorInner {
+                    //we get a new unique prefix for the collection
+                    account_id_hash: hash_account_id(account_id),
+                },
+            )
+        });
+
+        //we insert the token ID into the set and write it to storage
+        tokens_set.insert(token_id.clone());
+        tokens_set.flush();
+    }
+
+    //forget the creator of a token and remove the token from the set of tokens the creator made
+    pub(crate) fn internal_remove_token_from_creator(&mut self, token_id: &TokenId) {
+        //tokens minted before creators were recorded don't have one
+        let Some(account_id) = self.token_creators.remove(token_id) else {
+            return;
+        };
+
+        //we get the set of tokens created by the creator
+        if let Some(tokens_set) = self.tokens_per_creator.get_mut(&account_id) {
+            //we remove the the token_id from the set of tokens and write it to storage
+            tokens_set.remove(token_id);
+            tokens_set.flush();
+
+            //if the token set is now empty, we remove the creator from the tokens_per_creator collection
+            if tokens_set.is_empty() {
+                self.tokens_per_creator.remove(&account_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::{testing_env, RuntimeFeesConfig};
+
+    //resolves a registrar check that answered `is_verified`
+    fn resolve_verify_creator(contract: &mut ArtEcho, account_id: &str, is_verified: bool) -> bool {
+        testing_env!(
+            VMContextBuilder::new().current_account_id(account("art-echo.near")).build(),
+            near_sdk::test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(is_verified.to_string().into_bytes())],
+        );
+        contract.nft_resolve_verify_creator(account(account_id))
+    }
+
+    //resolves a registrar check that failed
+    fn fail_verify_creator(contract: &mut ArtEcho, account_id: &str) -> bool {
+        testing_env!(
+            VMContextBuilder::new().current_account_id(account("art-echo.near")).build(),
+            near_sdk::test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        contract.nft_resolve_verify_creator(account(account_id))
+    }
+
+    #[test]
+    fn creator_stays_attributed_after_a_transfer() {
+        let mut contract = setup_contract();
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint("one".to_string(), sample_metadata(), account("alice.near"), None, None);
+        contract.nft_mint("two".to_string(), sample_metadata(), account("bob.near"), None, None);
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_transfer(account("bob.near"), "one".to_string(), None, None);
+
+        let token = contract.nft_token("one".to_string()).unwrap();
+        assert_eq!(token.owner_id, account("bob.near"));
+        assert_eq!(token.creator_id, Some(owner()));
+
+        assert_eq!(contract.nft_supply_for_creator(owner()), U64(2));
+        let tokens = contract.nft_tokens_for_creator(owner(), Some(U128(1)), Some(1));
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].token_id, "two");
+    }
+
+    #[test]
+    fn editions_are_attributed_to_the_series_creator() {
+        let mut contract = setup_contract();
+        let mut series_metadata = sample_metadata();
+        series_metadata.copies = Some(2);
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_create_series("series".to_string(), series_metadata, None, None, None);
+        let token_id = contract.nft_mint_series("series".to_string(), account("alice.near"));
+
+        assert_eq!(contract.nft_token(token_id).unwrap().creator_id, Some(owner()));
+        assert_eq!(contract.nft_supply_for_creator(owner()), U64(1));
+    }
+
+    #[test]
+    fn burned_tokens_are_removed_from_the_creator() {
+        let mut contract = setup_contract();
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint("one".to_string(), sample_metadata(), account("alice.near"), None, None);
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_burn("one".to_string(), None);
+
+        assert_eq!(contract.nft_supply_for_creator(owner()), U64(0));
+        assert!(contract.nft_tokens_for_creator(owner(), None, None).is_empty());
+    }
+
+    #[test]
+    fn registrar_answer_is_reported_by_views() {
+        let mut contract = setup_contract();
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint("one".to_string(), sample_metadata(), account("alice.near"), None, None);
+        assert!(!contract.nft_token("one".to_string()).unwrap().creator_verified);
+
+        assert!(resolve_verify_creator(&mut contract, "owner.near", true));
+        assert!(contract.nft_is_creator_verified(owner()));
+        assert!(contract.nft_token("one".to_string()).unwrap().creator_verified);
+
+        //the creator was unverified in the registrar since they were last checked
+        assert!(!resolve_verify_creator(&mut contract, "owner.near", false));
+        assert!(!contract.nft_token("one".to_string()).unwrap().creator_verified);
+    }
+
+    #[test]
+    fn failed_check_keeps_the_last_answer() {
+        let mut contract = setup_contract();
+        assert!(resolve_verify_creator(&mut contract, "owner.near", true));
+
+        assert!(fail_verify_creator(&mut contract, "owner.near"));
+        assert!(contract.nft_is_creator_verified(owner()));
+
+        //a creator that was never verified stays unverified
+        assert!(!fail_verify_creator(&mut contract, "alice.near"));
+        assert!(!contract.nft_is_creator_verified(account("alice.near")));
+    }
+
+    #[test]
+    #[should_panic(expected = "No registrar")]
+    fn verifying_needs_a_registrar() {
+        let mut contract = setup_contract();
+
+        set_context(account("alice.near"), NearToken::from_yoctonear(0));
+        contract.nft_verify_creator(owner());
+    }
+}
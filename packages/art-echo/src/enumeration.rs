@@ -1,11 +1,59 @@
 use crate::*;
+use std::ops::Bound;
+
+//the number of items a paginated view returns if no limit is passed
+pub const DEFAULT_PAGE_LIMIT: u32 = 50;
+//the most items a paginated view returns, so it can't run out of gas
+pub const MAX_PAGE_LIMIT: u32 = 100;
+
+//get the number of items a paginated view should return for the passed in limit
+pub(crate) fn page_limit(limit: Option<u32>) -> usize {
+    limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT) as usize
+}
+
+//A page of tokens returned by the cursor based views.
+#[derive(Serialize, Deserialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonTokenPage {
+  //the tokens of the page, ordered by token ID
+  pub tokens: Vec<JsonToken>,
+  //the token ID to pass as the cursor to get the next page. None if this is the last page
+  pub next_cursor: Option<TokenId>,
+}
+
+//get a page of the token IDs strictly after the cursor, with the last token ID as the next cursor if there are more
+fn token_page(contract: &ArtEcho, token_ids: &TreeMap<TokenId, ()>, cursor: Option<TokenId>, limit: Option<u32>) -> JsonTokenPage {
+    let limit = page_limit(limit);
+    //take one more token ID than we return to know if there is a next page
+    let mut token_ids: Vec<TokenId> = match cursor {
+        //the range starts after the cursor
+        Some(cursor) => token_ids
+            .range::<_, TokenId>((Bound::Excluded(cursor), Bound::Unbounded))
+            .map(|(token_id, _)| token_id.clone())
+            .take(limit + 1)
+            .collect(),
+        None => token_ids.keys().take(limit + 1).cloned().collect(),
+    };
+    let next_cursor = if token_ids.len() > limit {
+        token_ids.truncate(limit);
+        token_ids.last().cloned()
+    } else {
+        None
+    };
+
+    JsonTokenPage {
+        tokens: token_ids.into_iter().map(|token_id| contract.nft_token(token_id).unwrap()).collect(),
+        next_cursor,
+    }
+}
 
 #[near]
 impl ArtEcho {
     //Query for the total supply of NFTs on the contract
     pub fn nft_total_supply(&self) -> U64 {
-        //return the length of the token metadata by ID
-        U64(self.token_metadata_by_id.len())
+        //return the length of the token metadata by ID, including the tokens that still have to be migrated
+        let legacy_supply = self.legacy_tokens.as_ref().map_or(0, |legacy| legacy.token_metadata_by_id.len());
+        U64(u64::from(self.token_metadata_by_id.len()) + legacy_supply)
     }
 
     //Query for nft tokens on the contract regardless of the owner using pagination
@@ -15,10 +63,10 @@ impl ArtEcho {
 
         //iterate through each token using an iterator
         self.token_metadata_by_id.keys()
-            //skip to the index we specified in the start variable
+            //skip to the index we specified in the start variable. The keys are stored by index, so the skipped keys aren't read
             .skip(start as usize) 
-            //take the first "limit" elements in the vector. If we didn't specify a limit, use 50
-            .take(limit.unwrap_or(50) as usize) 
+            //take the first "limit" elements in the vector, capped so the view can't run out of gas
+            .take(page_limit(limit)) 
             //we'll map the token IDs which are strings into Json Tokens
             .map(|token_id| self.nft_token(token_id.clone()).unwrap())
             //since we turned the keys into an iterator, we need to turn it back into a vector to return
@@ -35,7 +83,7 @@ impl ArtEcho {
 
         //if there is some set of tokens, we'll return the length
         if let Some(tokens_for_owner_set) = tokens_for_owner_set {
-            U64(tokens_for_owner_set.len())
+            U64(tokens_for_owner_set.len().into())
         } else {
             //if there isn't a set of tokens for the passed in account ID, we'll return 0
             U64(0)
@@ -63,14 +111,133 @@ impl ArtEcho {
         let start = u128::from(from_index.unwrap_or(U128(0)));
 
         //iterate through the keys vector
-        tokens.iter()
+        tokens.keys()
             //skip to the index we specified in the start variable
             .skip(start as usize) 
-            //take the first "limit" elements in the vector. If we didn't specify a limit, use 50
-            .take(limit.unwrap_or(50) as usize) 
+            //take the first "limit" elements in the vector, capped so the view can't run out of gas
+            .take(page_limit(limit)) 
+            //we'll map the token IDs which are strings into Json Tokens
+            .map(|token_id| self.nft_token(token_id.clone()).unwrap())
+            //since we turned the keys into an iterator, we need to turn it back into a vector to return
+            .collect()
+    }
+
+    //Query for all the tokens minted for a series
+    pub fn nft_tokens_for_series(
+        &self,
+        series_id: SeriesId,
+        from_index: Option<U128>,
+        limit: Option<u32>,
+    ) -> Vec<JsonToken> {
+        //get the set of tokens for the passed in series
+        let tokens = if let Some(tokens_for_series_set) = self.tokens_per_series.get(&series_id) {
+            tokens_for_series_set
+        } else {
+            //if there is no set of tokens, we'll simply return an empty vector.
+            return vec![];
+        };
+
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        //iterate through the keys vector
+        tokens.iter()
+            //skip to the index we specified in the start variable
+            .skip(start as usize)
+            //take the first "limit" elements in the vector, capped so the view can't run out of gas
+            .take(page_limit(limit))
             //we'll map the token IDs which are strings into Json Tokens
             .map(|token_id| self.nft_token(token_id.clone()).unwrap())
             //since we turned the keys into an iterator, we need to turn it back into a vector to return
             .collect()
     }
+
+    /*
+        Query for nft tokens on the contract in order of their token ID, starting after the `cursor` token ID.
+        Unlike nft_tokens, tokens that are minted or burned between calls don't make other tokens get skipped.
+        Pass the next_cursor of a page to get the next one
+    */
+    pub fn nft_tokens_by_cursor(&self, cursor: Option<TokenId>, limit: Option<u32>) -> JsonTokenPage {
+        token_page(self, &self.sorted_token_ids, cursor, limit)
+    }
+
+    //Query for all the tokens for an owner in order of their token ID, starting after the `cursor` token ID
+    pub fn nft_tokens_for_owner_by_cursor(
+        &self,
+        account_id: AccountId,
+        cursor: Option<TokenId>,
+        limit: Option<u32>,
+    ) -> JsonTokenPage {
+        //get the sorted set of tokens for the passed in owner
+        let Some(tokens) = self.tokens_per_owner.get(&account_id) else {
+            //if there is no set of tokens, we'll simply return an empty page.
+            return JsonTokenPage { tokens: vec![], next_cursor: None };
+        };
+
+        token_page(self, tokens, cursor, limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn mint(contract: &mut ArtEcho, token_id: &str, receiver_id: &str) {
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint(token_id.to_string(), sample_metadata(), account(receiver_id), None, None);
+    }
+
+    fn token_ids(page: &JsonTokenPage) -> Vec<&str> {
+        page.tokens.iter().map(|token| token.token_id.as_str()).collect()
+    }
+
+    #[test]
+    fn cursor_resumes_after_tokens_are_burned_and_minted() {
+        let mut contract = setup_contract();
+        for token_id in ["a", "b", "c", "d", "e"] {
+            mint(&mut contract, token_id, "alice.near");
+        }
+
+        let page = contract.nft_tokens_by_cursor(None, Some(2));
+        assert_eq!(token_ids(&page), vec!["a", "b"]);
+        assert_eq!(page.next_cursor, Some("b".to_string()));
+
+        //burning a token of the first page and minting one before the cursor doesn't shift the next page
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_burn("a".to_string(), None);
+        mint(&mut contract, "aa", "alice.near");
+
+        let page = contract.nft_tokens_by_cursor(page.next_cursor, Some(2));
+        assert_eq!(token_ids(&page), vec!["c", "d"]);
+
+        let page = contract.nft_tokens_by_cursor(page.next_cursor, Some(2));
+        assert_eq!(token_ids(&page), vec!["e"]);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn owner_cursor_follows_transfers() {
+        let mut contract = setup_contract();
+        for token_id in ["a", "b", "c"] {
+            mint(&mut contract, token_id, "alice.near");
+        }
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_transfer(account("bob.near"), "b".to_string(), None, None);
+
+        let page = contract.nft_tokens_for_owner_by_cursor(account("alice.near"), Some("a".to_string()), None);
+        assert_eq!(token_ids(&page), vec!["c"]);
+        assert_eq!(page.next_cursor, None);
+        let page = contract.nft_tokens_for_owner_by_cursor(account("bob.near"), None, None);
+        assert_eq!(token_ids(&page), vec!["b"]);
+        assert!(contract.nft_tokens_for_owner_by_cursor(account("carol.near"), None, None).tokens.is_empty());
+    }
+
+    #[test]
+    fn page_size_is_capped() {
+        assert_eq!(page_limit(None), DEFAULT_PAGE_LIMIT as usize);
+        assert_eq!(page_limit(Some(10)), 10);
+        assert_eq!(page_limit(Some(u32::MAX)), MAX_PAGE_LIMIT as usize);
+    }
 }
@@ -1,9 +1,12 @@
 use std::fmt;
 
 use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json;
 
 /// Enum that represents the data type of the EventLog.
-/// The enum can either be an NftMint or an NftTransfer.
+/// The enum can either be an NftMint, an NftTransfer, an NftBurn, an NftApprove, an NftRevoke,
+/// an NftMetadataUpdate or a ContractMetadataUpdate from the NFT standard, or a ContractPause, a ContractUnpause,
+/// an NftFreeze or an NftUnfreeze, which are logged under the art-echo standard.
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(tag = "event", content = "data")]
 #[serde(rename_all = "snake_case")]
@@ -12,6 +15,15 @@ use near_sdk::serde::{Deserialize, Serialize};
 pub enum EventLogVariant {
     NftMint(Vec<NftMintLog>),
     NftTransfer(Vec<NftTransferLog>),
+    NftBurn(Vec<NftBurnLog>),
+    NftApprove(Vec<NftApproveLog>),
+    NftRevoke(Vec<NftRevokeLog>),
+    NftMetadataUpdate(Vec<NftMetadataUpdateLog>),
+    ContractMetadataUpdate(Vec<ContractMetadataUpdateLog>),
+    ContractPause(Vec<ContractPauseLog>),
+    ContractUnpause(Vec<ContractPauseLog>),
+    NftFreeze(Vec<NftFreezeLog>),
+    NftUnfreeze(Vec<NftFreezeLog>),
 }
 
 /// Interface to capture data about an event
@@ -35,7 +47,7 @@ impl fmt::Display for EventLog {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_fmt(format_args!(
             "EVENT_JSON:{}",
-            &serde_json::to_string(self).map_err(|_| fmt::Error)?
+            serde_json::to_string(self).map_err(|_| fmt::Error)?
         ))
     }
 }
@@ -78,6 +90,112 @@ pub struct NftTransferLog {
     pub memo: Option<String>,
 }
 
+/// An event log to capture token burning
+///
+/// Arguments
+/// * `owner_id`: owner of tokens to burn
+/// * `authorized_id`: approved account to burn, if applicable
+/// * `token_ids`: ["1","2"]
+/// * `memo`: optional message
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftBurnLog {
+    pub owner_id: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authorized_id: Option<String>,
+
+    pub token_ids: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+/// An event log to capture an account being approved to transfer a token
+///
+/// Arguments
+/// * `owner_id`: owner of the token
+/// * `token_id`: "1"
+/// * `account_id`: "market.near"
+/// * `approval_id`: 0
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftApproveLog {
+    pub owner_id: String,
+    pub token_id: String,
+    pub account_id: String,
+    pub approval_id: u32,
+}
+
+/// An event log to capture approvals being revoked from a token
+///
+/// Arguments
+/// * `owner_id`: owner of the token
+/// * `token_id`: "1"
+/// * `account_ids`: ["market.near"], every approved account when all approvals are revoked
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftRevokeLog {
+    pub owner_id: String,
+    pub token_id: String,
+    pub account_ids: Vec<String>,
+}
+
+/// An event log to capture token metadata updates
+///
+/// Arguments
+/// * `token_ids`: ["1", "abc"]
+/// * `memo`: optional message
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftMetadataUpdateLog {
+    pub token_ids: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+/// An event log to capture contract metadata updates
+///
+/// Arguments
+/// * `memo`: optional message
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContractMetadataUpdateLog {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+/// An event log to capture the contract being paused or unpaused
+///
+/// Arguments
+/// * `owner_id`: the contract owner that paused or unpaused it
+/// * `memo`: optional message
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContractPauseLog {
+    pub owner_id: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+/// An event log to capture tokens being frozen or unfrozen by a moderator
+///
+/// Arguments
+/// * `moderator_id`: "moderator.near"
+/// * `token_ids`: ["1", "abc"]
+/// * `memo`: optional message
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftFreezeLog {
+    pub moderator_id: String,
+    pub token_ids: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -135,4 +253,123 @@ mod tests {
         };
         assert_eq!(expected, log.to_string());
     }
-} 
+
+    #[test]
+    fn nep_format_burn() {
+        let expected = r#"EVENT_JSON:{"standard":"nep171","version":"1.0.0","event":"nft_burn","data":[{"owner_id":"foundation.near","token_ids":["aurora","proximitylabs"]}]}"#;
+        let log = EventLog {
+            standard: "nep171".to_string(),
+            version: "1.0.0".to_string(),
+            event: EventLogVariant::NftBurn(vec![NftBurnLog {
+                owner_id: "foundation.near".to_owned(),
+                authorized_id: None,
+                token_ids: vec!["aurora".to_string(), "proximitylabs".to_string()],
+                memo: None,
+            }]),
+        };
+        assert_eq!(expected, log.to_string());
+    }
+
+    #[test]
+    fn nep_format_burn_all_fields() {
+        let expected = r#"EVENT_JSON:{"standard":"nep171","version":"1.0.0","event":"nft_burn","data":[{"owner_id":"user1.near","authorized_id":"market.near","token_ids":["token"],"memo":"Retired"}]}"#;
+        let log = EventLog {
+            standard: "nep171".to_string(),
+            version: "1.0.0".to_string(),
+            event: EventLogVariant::NftBurn(vec![NftBurnLog {
+                owner_id: "user1.near".to_owned(),
+                authorized_id: Some("market.near".to_string()),
+                token_ids: vec!["token".to_string()],
+                memo: Some("Retired".to_owned()),
+            }]),
+        };
+        assert_eq!(expected, log.to_string());
+    }
+
+    #[test]
+    fn nep_format_approve() {
+        let expected = r#"EVENT_JSON:{"standard":"nep171","version":"1.0.0","event":"nft_approve","data":[{"owner_id":"user1.near","token_id":"token","account_id":"market.near","approval_id":3}]}"#;
+        let log = EventLog {
+            standard: "nep171".to_string(),
+            version: "1.0.0".to_string(),
+            event: EventLogVariant::NftApprove(vec![NftApproveLog {
+                owner_id: "user1.near".to_owned(),
+                token_id: "token".to_string(),
+                account_id: "market.near".to_string(),
+                approval_id: 3,
+            }]),
+        };
+        assert_eq!(expected, log.to_string());
+    }
+
+    #[test]
+    fn nep_format_revoke() {
+        let expected = r#"EVENT_JSON:{"standard":"nep171","version":"1.0.0","event":"nft_revoke","data":[{"owner_id":"user1.near","token_id":"token","account_ids":["market.near","auction.near"]}]}"#;
+        let log = EventLog {
+            standard: "nep171".to_string(),
+            version: "1.0.0".to_string(),
+            event: EventLogVariant::NftRevoke(vec![NftRevokeLog {
+                owner_id: "user1.near".to_owned(),
+                token_id: "token".to_string(),
+                account_ids: vec!["market.near".to_string(), "auction.near".to_string()],
+            }]),
+        };
+        assert_eq!(expected, log.to_string());
+    }
+
+    #[test]
+    fn nep_format_metadata_update() {
+        let expected = r#"EVENT_JSON:{"standard":"nep171","version":"1.1.0","event":"nft_metadata_update","data":[{"token_ids":["aurora","proximitylabs"]}]}"#;
+        let log = EventLog {
+            standard: "nep171".to_string(),
+            version: "1.1.0".to_string(),
+            event: EventLogVariant::NftMetadataUpdate(vec![NftMetadataUpdateLog {
+                token_ids: vec!["aurora".to_string(), "proximitylabs".to_string()],
+                memo: None,
+            }]),
+        };
+        assert_eq!(expected, log.to_string());
+    }
+
+    #[test]
+    fn nep_format_contract_metadata_update() {
+        let expected = r#"EVENT_JSON:{"standard":"nep171","version":"1.2.0","event":"contract_metadata_update","data":[{"memo":"New icon"}]}"#;
+        let log = EventLog {
+            standard: "nep171".to_string(),
+            version: "1.2.0".to_string(),
+            event: EventLogVariant::ContractMetadataUpdate(vec![ContractMetadataUpdateLog {
+                memo: Some("New icon".to_owned()),
+            }]),
+        };
+        assert_eq!(expected, log.to_string());
+    }
+
+    #[test]
+    fn art_echo_format_pause() {
+        let expected = r#"EVENT_JSON:{"standard":"art_echo","version":"1.0.0","event":"contract_pause","data":[{"owner_id":"owner.near","memo":"incident"}]}"#;
+        let log = EventLog {
+            standard: "art_echo".to_string(),
+            version: "1.0.0".to_string(),
+            event: EventLogVariant::ContractPause(vec![ContractPauseLog {
+                owner_id: "owner.near".to_string(),
+                memo: Some("incident".to_string()),
+            }]),
+        };
+        assert_eq!(expected, log.to_string());
+    }
+
+    #[test]
+    fn art_echo_format_freeze() {
+        let expected = r#"EVENT_JSON:{"standard":"art_echo","version":"1.0.0","event":"nft_freeze","data":[{"moderator_id":"moderator.near","token_ids":["stolen"]}]}"#;
+        let log = EventLog {
+            standard: "art_echo".to_string(),
+            version: "1.0.0".to_string(),
+            event: EventLogVariant::NftFreeze(vec![NftFreezeLog {
+                moderator_id: "moderator.near".to_string(),
+                token_ids: vec!["stolen".to_string()],
+                memo: None,
+            }]),
+        };
+        assert_eq!(expected, log.to_string());
+    }
+}
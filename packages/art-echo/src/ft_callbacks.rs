@@ -0,0 +1,400 @@
+use crate::*;
+use near_sdk::{ext_contract, log, Gas, PromiseResult};
+
+const GAS_FOR_FT_TRANSFER: Gas = Gas::from_tgas(10);
+const GAS_FOR_RESOLVE_SPOT_TRANSFER: Gas = Gas::from_tgas(5);
+
+#[allow(dead_code)]
+#[ext_contract(ext_ft_contract)]
+pub(crate) trait FungibleToken {
+    //transfers SPOT from the contract to the receiver
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+}
+
+//The message passed to ft_transfer_call on the SPOT contract, naming what the SPOT pays for
+#[derive(Serialize, Deserialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum FtTransferMsg {
+    //rent a token that is listed for rent in SPOT, e.g. {"rent": {"token_id": "1"}}
+    Rent {
+        token_id: TokenId,
+    },
+    /*
+      buy a token for SPOT that is paid out per the token's royalties, e.g. {"buy": {"token_id": "1", "approval_id": 0}}.
+      The sender has to be the owner or an approved account, like a marketplace the buyer paid. The token goes to
+      `receiver_id`, which defaults to the sender, and `price` defaults to the whole amount transferred.
+      Payees that can't be transferred their share, e.g. because they aren't registered with SPOT, can claim it with claim_spot
+    */
+    Buy {
+        token_id: TokenId,
+        approval_id: Option<u32>,
+        receiver_id: Option<AccountId>,
+        price: Option<U128>,
+        max_len_payout: Option<u32>,
+    },
+}
+
+#[near]
+impl ArtEcho {
+    /*
+        called by the SPOT contract during ft_transfer_call to pay for a token with SPOT. The msg says what the
+        SPOT pays for. Returns the amount of SPOT that wasn't used, which the SPOT contract refunds to the sender
+    */
+    pub fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        //make sure the tokens that were transferred are SPOT
+        assert_eq!(
+            Some(env::predecessor_account_id()),
+            self.spot_token_id,
+            "Only SPOT can be used to pay for tokens"
+        );
+
+        let msg: FtTransferMsg = near_sdk::serde_json::from_str(&msg).expect("Not a valid msg");
+        match msg {
+            FtTransferMsg::Rent { token_id } => {
+                //lend the token to the sender and make sure they sent enough SPOT
+                let (owner_id, price) = self.internal_rent(&token_id, sender_id);
+                let price = match price {
+                    RentalPrice::Spot(price) => Some(price),
+                    RentalPrice::Near(_) => None,
+                }
+                .expect("Rental is priced in NEAR, use nft_rent");
+                assert!(
+                    amount.0 >= price.0,
+                    "Must transfer at least {} SPOT",
+                    price.0
+                );
+
+                //pay the owner the price and return the rest
+                if price.0 > 0 {
+                    self.internal_transfer_spot(owner_id, price, "rental fee");
+                }
+                PromiseOrValue::Value(U128(amount.0 - price.0))
+            }
+            FtTransferMsg::Buy {
+                token_id,
+                approval_id,
+                receiver_id,
+                price,
+                max_len_payout,
+            } => {
+                //make sure the sender sent enough SPOT
+                let price = price.unwrap_or(amount);
+                assert!(
+                    amount.0 >= price.0,
+                    "Must transfer at least {} SPOT",
+                    price.0
+                );
+
+                //transfer the token to the receiver and get the previous token object back
+                let receiver_id = receiver_id.unwrap_or_else(|| sender_id.clone());
+                let previous_token =
+                    self.internal_transfer(&sender_id, &receiver_id, &token_id, approval_id, None);
+
+                //refund the previous token owner for the storage used up by the previous approved account IDs
+                refund_approved_account_ids(
+                    previous_token.owner_id.clone(),
+                    &previous_token.approved_account_ids,
+                );
+
+                //split the price between the royalties and the previous owner and pay everyone in SPOT
+                let payout = compute_payout(
+                    &previous_token.owner_id,
+                    &previous_token.royalty,
+                    price.0,
+                    max_len_payout,
+                );
+                for (account_id, amount) in payout.payout {
+                    if amount.0 > 0 {
+                        self.internal_transfer_spot(account_id, amount, "royalty payout");
+                    }
+                }
+
+                //return the SPOT that wasn't used
+                PromiseOrValue::Value(U128(amount.0 - price.0))
+            }
+        }
+    }
+
+    //claim the SPOT that couldn't be paid out to the caller, e.g. after registering with the SPOT contract
+    #[payable]
+    pub fn claim_spot(&mut self) -> Promise {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+
+        let account_id = env::predecessor_account_id();
+        let amount = self
+            .unpaid_spot
+            .remove(&account_id)
+            .expect("No unpaid SPOT");
+        self.internal_transfer_spot(account_id, amount, "claimed payout")
+    }
+
+    //resolves a transfer of SPOT out of the contract. If it failed, the amount is kept track of so the receiver
+    //can claim it with claim_spot. Returns true if the SPOT was transferred
+    #[private]
+    pub fn resolve_spot_transfer(&mut self, receiver_id: AccountId, amount: U128) -> bool {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return true;
+        }
+
+        log!(
+            "{} SPOT could not be paid to @{}, it can be claimed with claim_spot",
+            amount.0,
+            receiver_id
+        );
+        let unpaid = self
+            .unpaid_spot
+            .get(&receiver_id)
+            .map_or(0, |unpaid| unpaid.0);
+        self.unpaid_spot
+            .insert(receiver_id, U128(unpaid + amount.0));
+        false
+    }
+
+    //get the SPOT that couldn't be paid out to an account and can be claimed with claim_spot
+    pub fn spot_unpaid_to(&self, account_id: AccountId) -> U128 {
+        self.unpaid_spot
+            .get(&account_id)
+            .cloned()
+            .unwrap_or(U128(0))
+    }
+}
+
+impl ArtEcho {
+    //transfer SPOT the contract holds to the receiver. If the transfer fails, the receiver can claim it later
+    pub(crate) fn internal_transfer_spot(
+        &self,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: &str,
+    ) -> Promise {
+        let spot_token_id = self.spot_token_id.clone().expect("No SPOT contract");
+        ext_ft_contract::ext(spot_token_id)
+            .with_attached_deposit(ONE_YOCTONEAR)
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(receiver_id.clone(), amount, Some(memo.to_string()))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_SPOT_TRANSFER)
+                    .resolve_spot_transfer(receiver_id, amount),
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+    use near_sdk::{testing_env, RuntimeFeesConfig};
+
+    const TOKEN_ID: &str = "piece";
+
+    //a contract that accepts SPOT, with a token owned by alice that is listed for rent for 100 SPOT
+    fn setup_spot_rental() -> ArtEcho {
+        let mut contract = setup_contract();
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.set_spot_token(Some(account("spot.near")));
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint(
+            TOKEN_ID.to_string(),
+            sample_metadata(),
+            account("alice.near"),
+            None,
+            None,
+        );
+
+        set_context(account("alice.near"), NearToken::from_millinear(10));
+        contract.nft_list_rental(TOKEN_ID.to_string(), RentalPrice::Spot(U128(100)), 1000);
+        contract
+    }
+
+    fn rent_msg() -> String {
+        format!(r#"{{"rent": {{"token_id": "{}"}}}}"#, TOKEN_ID)
+    }
+
+    //resolve a transfer of SPOT to the receiver that failed, e.g. because the receiver isn't registered with SPOT
+    fn fail_spot_transfer(contract: &mut ArtEcho, receiver_id: AccountId, amount: U128) {
+        testing_env!(
+            VMContextBuilder::new()
+                .current_account_id(account("art-echo.near"))
+                .build(),
+            near_sdk::test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        assert!(!contract.resolve_spot_transfer(receiver_id, amount));
+    }
+
+    //a contract that accepts SPOT, with a token owned by alice that pays 10% to the artist and is approved for market.near
+    fn setup_spot_sale() -> ArtEcho {
+        let mut contract = setup_contract();
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.set_spot_token(Some(account("spot.near")));
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint(
+            TOKEN_ID.to_string(),
+            sample_metadata(),
+            account("alice.near"),
+            Some(HashMap::from([(account("artist.near"), 1000)])),
+            None,
+        );
+
+        set_context(account("alice.near"), NearToken::from_millinear(10));
+        contract.nft_approve(TOKEN_ID.to_string(), account("market.near"), None);
+        contract
+    }
+
+    fn buy_msg(price: Option<u128>) -> String {
+        near_sdk::serde_json::json!({
+            "buy": {
+                "token_id": TOKEN_ID,
+                "approval_id": 0,
+                "receiver_id": "bob.near",
+                "price": price.map(|price| price.to_string()),
+            }
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn rent_in_spot_pays_the_owner_and_returns_the_rest() {
+        let mut contract = setup_spot_rental();
+
+        set_context(account("spot.near"), NearToken::from_yoctonear(0));
+        let unused = match contract.ft_on_transfer(account("gallery.near"), U128(150), rent_msg()) {
+            PromiseOrValue::Value(unused) => unused,
+            PromiseOrValue::Promise(_) => panic!("Expected the unused amount"),
+        };
+
+        assert_eq!(unused, U128(50));
+        assert_eq!(
+            contract.nft_user_of(TOKEN_ID.to_string()),
+            Some(account("gallery.near"))
+        );
+        //the fee is transferred to alice and the transfer is resolved on the contract
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 2);
+        assert_eq!(receipts[0].receiver_id, account("spot.near"));
+        assert_eq!(receipts[1].receiver_id, account("art-echo.near"));
+    }
+
+    #[test]
+    fn failed_rental_fee_can_be_claimed() {
+        let mut contract = setup_spot_rental();
+        set_context(account("spot.near"), NearToken::from_yoctonear(0));
+        contract.ft_on_transfer(account("gallery.near"), U128(100), rent_msg());
+
+        fail_spot_transfer(&mut contract, account("alice.near"), U128(100));
+        assert_eq!(contract.spot_unpaid_to(account("alice.near")), U128(100));
+
+        //alice claims the fee once she registered with SPOT
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.claim_spot();
+        assert_eq!(contract.spot_unpaid_to(account("alice.near")), U128(0));
+        assert_eq!(get_created_receipts()[0].receiver_id, account("spot.near"));
+    }
+
+    #[test]
+    fn failed_transfers_add_up() {
+        let mut contract = setup_spot_rental();
+
+        fail_spot_transfer(&mut contract, account("alice.near"), U128(100));
+        fail_spot_transfer(&mut contract, account("alice.near"), U128(50));
+
+        assert_eq!(contract.spot_unpaid_to(account("alice.near")), U128(150));
+    }
+
+    #[test]
+    #[should_panic(expected = "No unpaid SPOT")]
+    fn nothing_to_claim_without_failed_transfers() {
+        let mut contract = setup_spot_rental();
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.claim_spot();
+    }
+
+    #[test]
+    fn buy_in_spot_pays_out_the_royalties() {
+        let mut contract = setup_spot_sale();
+
+        set_context(account("spot.near"), NearToken::from_yoctonear(0));
+        let unused =
+            match contract.ft_on_transfer(account("market.near"), U128(1000), buy_msg(None)) {
+                PromiseOrValue::Value(unused) => unused,
+                PromiseOrValue::Promise(_) => panic!("Expected the unused amount"),
+            };
+
+        assert_eq!(unused, U128(0));
+        assert_eq!(
+            contract.nft_token(TOKEN_ID.to_string()).unwrap().owner_id,
+            account("bob.near")
+        );
+        //the artist and alice are both paid through the SPOT contract
+        let payouts = get_created_receipts()
+            .into_iter()
+            .filter(|receipt| receipt.receiver_id == account("spot.near"))
+            .count();
+        assert_eq!(payouts, 2);
+        assert!(refunded_to(&account("alice.near")) > NearToken::from_yoctonear(0));
+    }
+
+    #[test]
+    fn failed_royalty_payout_can_be_claimed() {
+        let mut contract = setup_spot_sale();
+        set_context(account("spot.near"), NearToken::from_yoctonear(0));
+        contract.ft_on_transfer(account("market.near"), U128(1000), buy_msg(None));
+
+        //the artist isn't registered with SPOT, so their share is kept for them
+        fail_spot_transfer(&mut contract, account("artist.near"), U128(100));
+        assert_eq!(contract.spot_unpaid_to(account("artist.near")), U128(100));
+        assert_eq!(contract.spot_unpaid_to(account("alice.near")), U128(0));
+        assert_eq!(
+            contract.nft_token(TOKEN_ID.to_string()).unwrap().owner_id,
+            account("bob.near")
+        );
+
+        set_context(account("artist.near"), ONE_YOCTONEAR);
+        contract.claim_spot();
+        assert_eq!(contract.spot_unpaid_to(account("artist.near")), U128(0));
+    }
+
+    #[test]
+    fn buy_returns_the_amount_above_the_price() {
+        let mut contract = setup_spot_sale();
+
+        set_context(account("spot.near"), NearToken::from_yoctonear(0));
+        let unused =
+            match contract.ft_on_transfer(account("market.near"), U128(1000), buy_msg(Some(800))) {
+                PromiseOrValue::Value(unused) => unused,
+                PromiseOrValue::Promise(_) => panic!("Expected the unused amount"),
+            };
+
+        assert_eq!(unused, U128(200));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only SPOT can be used to pay for tokens")]
+    fn other_tokens_are_rejected() {
+        let mut contract = setup_spot_rental();
+
+        set_context(account("fake-spot.near"), NearToken::from_yoctonear(0));
+        contract.ft_on_transfer(account("gallery.near"), U128(150), rent_msg());
+    }
+
+    #[test]
+    #[should_panic(expected = "Must transfer at least 100 SPOT")]
+    fn rent_needs_the_whole_price() {
+        let mut contract = setup_spot_rental();
+
+        set_context(account("spot.near"), NearToken::from_yoctonear(0));
+        contract.ft_on_transfer(account("gallery.near"), U128(99), rent_msg());
+    }
+}
@@ -0,0 +1,133 @@
+use crate::*;
+
+//what an account has to hold a token of, e.g. {"series": "sunsets"} or {"collection": "birds"}
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, NearSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum HoldsFilter {
+  //any edition of the series
+  Series(SeriesId),
+  //any token minted in the collection
+  Collection(CollectionId),
+}
+
+#[near]
+impl ArtEcho {
+    /*
+        check if an account holds at least one token of a series or a collection. This only reads a single
+        counter, so other contracts can cheaply gate features on it with a cross contract call and a callback
+    */
+    pub fn nft_holds(&self, account_id: AccountId, filter: HoldsFilter) -> bool {
+        self.holdings.contains_key(&(account_id, filter))
+    }
+
+    //get the number of editions of a series an account holds
+    pub fn nft_balance_of_series(&self, account_id: AccountId, series_id: SeriesId) -> U64 {
+        U64(self.holdings.get(&(account_id, HoldsFilter::Series(series_id))).copied().unwrap_or(0))
+    }
+}
+
+impl ArtEcho {
+    //get the series and the collection a token counts towards
+    fn internal_holds_filters(&self, token_id: &TokenId) -> Vec<HoldsFilter> {
+        let series_id = self.tokens_by_id.get(token_id).and_then(|token| token.series_id.clone());
+        series_id.map(HoldsFilter::Series).into_iter()
+            .chain(collection_of(token_id).map(HoldsFilter::Collection))
+            .collect()
+    }
+
+    //count a token an account received towards the series and the collection it belongs to
+    pub(crate) fn internal_add_holdings(&mut self, account_id: &AccountId, token_id: &TokenId) {
+        for filter in self.internal_holds_filters(token_id) {
+            *self.holdings.entry((account_id.clone(), filter)).or_insert(0) += 1;
+        }
+    }
+
+    //stop counting a token an account no longer has. The counter is removed once it reaches 0
+    pub(crate) fn internal_remove_holdings(&mut self, account_id: &AccountId, token_id: &TokenId) {
+        for filter in self.internal_holds_filters(token_id) {
+            let key = (account_id.clone(), filter);
+            let balance = self.holdings.get(&key).copied().unwrap_or(0);
+            if balance > 1 {
+                self.holdings.insert(key, balance - 1);
+            } else {
+                self.holdings.remove(&key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    const SERIES_ID: &str = "sunsets";
+
+    //a contract with a series of 10 editions, two of which were minted to alice
+    fn setup_series() -> ArtEcho {
+        let mut contract = setup_contract();
+        let mut metadata = sample_metadata();
+        metadata.copies = Some(10);
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_create_series(SERIES_ID.to_string(), metadata, None, None, None);
+        contract.nft_mint_series(SERIES_ID.to_string(), account("alice.near"));
+        contract.nft_mint_series(SERIES_ID.to_string(), account("alice.near"));
+        contract
+    }
+
+    fn series() -> HoldsFilter {
+        HoldsFilter::Series(SERIES_ID.to_string())
+    }
+
+    #[test]
+    fn editions_are_counted_per_owner() {
+        let contract = setup_series();
+
+        assert!(contract.nft_holds(account("alice.near"), series()));
+        assert!(!contract.nft_holds(account("bob.near"), series()));
+        assert_eq!(contract.nft_balance_of_series(account("alice.near"), SERIES_ID.to_string()), U64(2));
+    }
+
+    #[test]
+    fn holdings_follow_transfers_and_burns() {
+        let mut contract = setup_series();
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_transfer(account("bob.near"), "sunsets:1".to_string(), None, None);
+        assert_eq!(contract.nft_balance_of_series(account("alice.near"), SERIES_ID.to_string()), U64(1));
+        assert_eq!(contract.nft_balance_of_series(account("bob.near"), SERIES_ID.to_string()), U64(1));
+
+        contract.nft_burn("sunsets:2".to_string(), None);
+        assert!(!contract.nft_holds(account("alice.near"), series()));
+        assert!(contract.holdings.get(&(account("alice.near"), series())).is_none());
+    }
+
+    #[test]
+    fn collection_tokens_are_counted() {
+        let mut contract = setup_contract();
+        set_context(owner(), NearToken::from_near(1));
+        let metadata = NFTContractMetadata {
+            spec: "nft-1.0.0".to_string(),
+            name: "Birds".to_string(),
+            symbol: "BIRD".to_string(),
+            icon: None,
+            base_uri: None,
+            reference: None,
+            reference_hash: None,
+        };
+        contract.nft_create_collection("birds".to_string(), metadata, None, None);
+        contract.nft_collection_mint(
+            "birds".to_string(),
+            "robin".to_string(),
+            sample_metadata(),
+            account("carol.near"),
+            None,
+            None,
+        );
+
+        assert!(contract.nft_holds(account("carol.near"), HoldsFilter::Collection("birds".to_string())));
+        assert!(!contract.nft_holds(account("carol.near"), HoldsFilter::Collection("fish".to_string())));
+    }
+}
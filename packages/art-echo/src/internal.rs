@@ -37,6 +37,24 @@ pub(crate) fn hash_account_id(account_id: &AccountId) -> CryptoHash {
     hash
 }
 
+//used to generate a unique prefix for the collection of tokens minted for a series
+pub(crate) fn hash_series_id(series_id: &SeriesId) -> CryptoHash {
+    //get the default hash
+    let mut hash = CryptoHash::default();
+    //we hash the series ID and return it
+    hash.copy_from_slice(&env::sha256(series_id.as_bytes()));
+    hash
+}
+
+//used to generate a unique prefix in our storage collections (this is to avoid data collisions)
+pub(crate) fn hash_collection_id(collection_id: &CollectionId) -> CryptoHash {
+    //get the default hash
+    let mut hash = CryptoHash::default();
+    //we hash the collection ID and return it
+    hash.copy_from_slice(&env::sha256(collection_id.as_bytes()));
+    hash
+}
+
 //used to make sure the user attached exactly 1 yoctoNEAR
 pub(crate) fn assert_one_yocto() {
     assert_eq!(
@@ -54,52 +72,161 @@ pub(crate) fn assert_at_least_one_yocto() {
     )
 }
 
-//refund the initial deposit based on the amount of storage that was used up
-pub(crate) fn refund_deposit(storage_used: u128) {
-    //get how much it would cost to store the information
-    let required_cost = env::storage_byte_cost().saturating_mul(storage_used);
-    //get the attached deposit
-    let attached_deposit = env::attached_deposit();
+//convert the royalty percentage and amount to pay into a payout, rounded down.
+//we split the amount on the basis points first so the multiplication can't overflow
+pub(crate) fn royalty_to_payout(royalty_percentage: u32, amount_to_pay: u128) -> U128 {
+    let royalty_percentage = royalty_percentage as u128;
+    U128(amount_to_pay / 10000 * royalty_percentage + amount_to_pay % 10000 * royalty_percentage / 10000)
+}
 
-    //make sure that the attached deposit is greater than or equal to the required cost
-    assert!(
-        required_cost <= attached_deposit,
-        "Must attach {} yoctoNEAR to cover storage",
-        required_cost,
-    );
+impl ArtEcho {
+    //refund the initial deposit based on the amount of storage that was used up
+    pub(crate) fn refund_deposit(&mut self, storage_used: u128) {
+        self.refund_deposit_with_price(storage_used, NearToken::from_yoctonear(0))
+    }
 
-    //get the refund amount from the attached deposit - required cost
-    let refund = attached_deposit.saturating_sub(required_cost);
+    //refund the initial deposit based on the amount of storage that was used up and the price that was paid.
+    //the storage is debited from the caller's storage balance if it covers it, otherwise the attached deposit has to
+    pub(crate) fn refund_deposit_with_price(&mut self, storage_used: u128, price: NearToken) {
+        //get how much it would cost to store the information
+        let mut storage_cost = env::storage_byte_cost().saturating_mul(storage_used);
+        if self.internal_debit_storage(&env::predecessor_account_id(), storage_cost) {
+            storage_cost = NearToken::from_yoctonear(0);
+        }
+        //plus the price that has to be paid
+        let required_cost = storage_cost.saturating_add(price);
+        //get the attached deposit
+        let attached_deposit = env::attached_deposit();
 
-    //if the refund is greater than 1 yocto NEAR, we refund the predecessor that amount
-    if refund.gt(&ONE_YOCTONEAR) {
-        Promise::new(env::predecessor_account_id()).transfer(refund);
+        //make sure that the attached deposit is greater than or equal to the required cost
+        assert!(
+            required_cost <= attached_deposit,
+            "Must attach {} yoctoNEAR to cover storage",
+            required_cost,
+        );
+
+        //get the refund amount from the attached deposit - required cost
+        let refund = attached_deposit.saturating_sub(required_cost);
+
+        //if the refund is greater than 1 yocto NEAR, we refund the predecessor that amount
+        if refund.gt(&ONE_YOCTONEAR) {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    //charge the caller for the storage that was added since the initial storage usage, or refund them
+    //the deposit and the storage that was released if the storage went down
+    pub(crate) fn refund_storage_difference(&mut self, initial_storage_usage: u64) {
+        let storage_usage = self.internal_storage_usage();
+        if storage_usage > initial_storage_usage {
+            //charge the caller for the extra storage used
+            self.refund_deposit((storage_usage - initial_storage_usage).into());
+        } else {
+            //refund the caller the deposit and the storage that was released
+            let released = env::storage_byte_cost().saturating_mul((initial_storage_usage - storage_usage).into());
+            let refund = released.saturating_add(env::attached_deposit());
+            if refund.gt(&ONE_YOCTONEAR) {
+                Promise::new(env::predecessor_account_id()).transfer(refund);
+            }
+        }
     }
 }
 
 impl ArtEcho {
+    //write the cached changes of the collections to storage and get the storage used by the contract.
+    //the collections only write their changes when they're dropped, so this has to be used to measure storage
+    pub(crate) fn internal_storage_usage(&mut self) -> u64 {
+        self.tokens_per_owner.flush();
+        self.sorted_token_ids.flush();
+        self.tokens_by_id.flush();
+        self.token_metadata_by_id.flush();
+        self.series_by_id.flush();
+        self.tokens_per_series.flush();
+        self.soulbound_tokens.flush();
+        self.collab_tokens.flush();
+        self.pending_collabs.flush();
+        self.token_creators.flush();
+        self.tokens_per_creator.flush();
+        self.collections_by_id.flush();
+        self.tokens_per_collection.flush();
+        self.rental_listings.flush();
+        self.leases.flush();
+        self.transfer_frozen_tokens.flush();
+        self.storage_balances.flush();
+        self.holdings.flush();
+        self.wrapped_tokens.flush();
+        self.unreleased_tokens.flush();
+        self.unpaid_spot.flush();
+        self.uploads.flush();
+        self.upload_pages.flush();
+        self.metadata.flush();
+        env::storage_usage()
+    }
+
+    //make sure all the tokens were moved over from the previous state before changing them
+    pub(crate) fn assert_migrated(&self) {
+        assert!(self.legacy_tokens.is_none(), "Tokens are still being migrated");
+    }
+
+    //make sure the caller is the owner of the contract
+    pub(crate) fn assert_owner(&self) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Predecessor must be the contract owner."
+        );
+    }
+
+    //get the issuer of a token that can't be transferred. Editions of a soulbound series are issued by the series creator.
+    //returns None if the token can be transferred
+    pub(crate) fn internal_soulbound_issuer(&self, token_id: &TokenId, token: &Token) -> Option<AccountId> {
+        if let Some(series_id) = token.series_id.as_ref() {
+            if self.soulbound_series.contains(series_id) {
+                return self.series_by_id.get(series_id).map(|series| series.creator_id.clone());
+            }
+        }
+        self.soulbound_tokens.get(token_id).cloned()
+    }
+
+    //make sure the token can be transferred and approved
+    pub(crate) fn assert_transferable(&self, token_id: &TokenId, token: &Token) {
+        self.assert_not_paused();
+        assert!(!self.transfer_frozen_tokens.contains_key(token_id), "Token is frozen");
+        assert!(
+            self.internal_soulbound_issuer(token_id, token).is_none(),
+            "Token is not transferable"
+        );
+    }
+
+    //make sure the caller is allowed to mint without going through the registrar
+    pub(crate) fn assert_minter(&self) {
+        assert!(
+            self.minters.contains(&env::predecessor_account_id()),
+            "Unauthorized minter"
+        );
+    }
+
     //add a token to the set of tokens an owner has
     pub(crate) fn internal_add_token_to_owner(
         &mut self,
         account_id: &AccountId,
         token_id: &TokenId,
     ) {
-        //get the set of tokens for the given account
-        let mut tokens_set = self.tokens_per_owner.get(account_id).unwrap_or_else(|| {
-            //if the account doesn't have any tokens, we create a new unordered set
-            UnorderedSet::new(
-                StorageKey::TokenPerOwnerInner {
-                    //we get a new unique prefix for the collection
-                    account_id_hash: hash_account_id(&account_id),
-                },
-            )
+        //get the sorted set of tokens for the given account
+        let tokens_set = self.tokens_per_owner.entry(account_id.clone()).or_insert_with(|| {
+            //if the account doesn't have any tokens, we create a new sorted set
+            TreeMap::new(StorageKey::SortedTokensPerOwnerInner {
+                //we get a new unique prefix for the collection
+                account_id_hash: hash_account_id(account_id),
+            })
         });
 
-        //we insert the token ID into the set
-        tokens_set.insert(token_id);
+        //we insert the token ID into the set and write it to storage
+        tokens_set.insert(token_id.clone(), ());
+        tokens_set.flush();
 
-        //we insert that set for the given account ID. 
-        self.tokens_per_owner.insert(account_id, &tokens_set);
+        //count the token towards the series and the collection the owner holds
+        self.internal_add_holdings(account_id, token_id);
     }
 
     //remove a token from an owner (internal method and can't be called directly via CLI).
@@ -109,22 +236,23 @@ impl ArtEcho {
         token_id: &TokenId,
     ) {
         //we get the set of tokens that the owner has
-        let mut tokens_set = self
+        let tokens_set = self
             .tokens_per_owner
-            .get(account_id)
+            .get_mut(account_id)
             //if there is no set of tokens for the owner, we panic with the following message:
             .expect("Token should be owned by the sender");
 
-        //we remove the the token_id from the set of tokens
+        //we remove the the token_id from the set of tokens and write it to storage
         tokens_set.remove(token_id);
+        tokens_set.flush();
 
         //if the token set is now empty, we remove the owner from the tokens_per_owner collection
         if tokens_set.is_empty() {
             self.tokens_per_owner.remove(account_id);
-        } else {
-            //if the token set is not empty, we simply insert it back for the account ID. 
-            self.tokens_per_owner.insert(account_id, &tokens_set);
         }
+
+        //the token no longer counts towards the series and the collection the owner holds
+        self.internal_remove_holdings(account_id, token_id);
     }
 
     //transfers the NFT to the receiver_id (internal method and can't be called directly via CLI).
@@ -137,8 +265,69 @@ impl ArtEcho {
         approval_id: Option<u32>,
         memo: Option<String>,
     ) -> Token {
+        //move the token to the receiver and get the previous token object back
+        let token = self.internal_move_token(sender_id, receiver_id, token_id, approval_id);
+
+        //if there was some memo attached, we log it. 
+        if let Some(memo) = memo.as_ref() {
+            env::log_str(&format!("Memo: {}", memo).to_string());
+        }
+
+        // Default the authorized ID to be None for the logs.
+        let mut authorized_id = None;
+        //if the approval ID was provided, set the authorized ID equal to the sender
+        if approval_id.is_some() {
+            authorized_id = Some(sender_id.to_string());
+        }
+
+        // Construct the transfer log as per the events standard.
+        let nft_transfer_log: EventLog = EventLog {
+            // Standard name ("nep171").
+            standard: NFT_STANDARD_NAME.to_string(),
+            // Version of the standard ("nft-1.0.0").
+            version: NFT_METADATA_SPEC.to_string(),
+            // The data related with the event stored in a vector.
+            event: EventLogVariant::NftTransfer(vec![NftTransferLog {
+                // The optional authorized account ID to transfer the token on behalf of the old owner.
+                authorized_id,
+                // The old owner's account ID.
+                old_owner_id: token.owner_id.to_string(),
+                // The account ID of the new owner of the token.
+                new_owner_id: receiver_id.to_string(),
+                // A vector containing the token IDs as strings.
+                token_ids: vec![token_id.to_string()],
+                // An optional memo to include.
+                memo,
+            }]),
+        };
+
+        // Log the serialized json.
+        env::log_str(&nft_transfer_log.to_string());
+        
+        //return the previous token object that was transferred.
+        token
+    }
+
+    //moves the NFT to the receiver_id without logging it, so transfers can be logged together (internal method and can't be called directly via CLI).
+    pub(crate) fn internal_move_token(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        token_id: &TokenId,
+        approval_id: Option<u32>,
+    ) -> Token {
+        //make sure the token isn't still being migrated
+        self.assert_migrated();
+
         //get the token object by passing in the token_id
-        let token = self.tokens_by_id.get(token_id).expect("No token");
+        let token = self.tokens_by_id.get(token_id).cloned().expect("No token");
+        //soulbound tokens stay with their owner
+        self.assert_transferable(token_id, &token);
+        //expired tokens can't be transferred if the validity is enforced
+        if self.enforce_validity {
+            let metadata = self.token_metadata_by_id.get(token_id).expect("No token");
+            assert!(!is_expired(metadata), "Token has expired");
+        }
 
         //if the sender doesn't equal the owner, we check if the sender is in the approval list
         if sender_id != &token.owner_id {
@@ -171,6 +360,12 @@ impl ArtEcho {
             "The token owner and the receiver should be different"
         );
 
+        //the rental listing and the lease end when the token changes hands, and the owner gets their storage back
+        let rental_storage_released = self.internal_remove_rental(token_id);
+        if rental_storage_released > 0 {
+            Promise::new(token.owner_id.clone()).transfer(env::storage_byte_cost().saturating_mul(rental_storage_released));
+        }
+
         //we remove the token from it's current owner's set
         self.internal_remove_token_from_owner(&token.owner_id, token_id);
         //we then add the token to the receiver_id's set
@@ -182,46 +377,13 @@ impl ArtEcho {
             //reset the approval account IDs
             approved_account_ids: Default::default(),
             next_approval_id: token.next_approval_id,
+            //the royalties and series stay with the token
+            royalty: token.royalty.clone(),
+            series_id: token.series_id.clone(),
         };
         //insert that new token into the tokens_by_id, replacing the old entry 
-        self.tokens_by_id.insert(token_id, &new_token);
+        self.tokens_by_id.insert(token_id.clone(), new_token);
 
-        //if there was some memo attached, we log it. 
-        if let Some(memo) = memo.as_ref() {
-            env::log_str(&format!("Memo: {}", memo).to_string());
-        }
-
-        // Default the authorized ID to be None for the logs.
-        let mut authorized_id = None;
-        //if the approval ID was provided, set the authorized ID equal to the sender
-        if approval_id.is_some() {
-            authorized_id = Some(sender_id.to_string());
-        }
-
-        // Construct the transfer log as per the events standard.
-        let nft_transfer_log: EventLog = EventLog {
-            // Standard name ("nep171").
-            standard: NFT_STANDARD_NAME.to_string(),
-            // Version of the standard ("nft-1.0.0").
-            version: NFT_METADATA_SPEC.to_string(),
-            // The data related with the event stored in a vector.
-            event: EventLogVariant::NftTransfer(vec![NftTransferLog {
-                // The optional authorized account ID to transfer the token on behalf of the old owner.
-                authorized_id,
-                // The old owner's account ID.
-                old_owner_id: token.owner_id.to_string(),
-                // The account ID of the new owner of the token.
-                new_owner_id: receiver_id.to_string(),
-                // A vector containing the token IDs as strings.
-                token_ids: vec![token_id.to_string()],
-                // An optional memo to include.
-                memo,
-            }]),
-        };
-
-        // Log the serialized json.
-        env::log_str(&nft_transfer_log.to_string());
-        
         //return the previous token object that was transferred.
         token
     }
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
-use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LazyOption, LookupMap, UnorderedMap, UnorderedSet};
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::store::{IterableMap, IterableSet, LazyOption, LookupMap, LookupSet, TreeMap};
 use near_sdk::json_types::{Base64VecU8, U64, U128};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
@@ -15,6 +15,17 @@ pub use crate::approval::*;
 pub use crate::royalty::*;
 pub use crate::enumeration::*;
 pub use crate::events::*;
+pub use crate::series::*;
+use crate::validity::*;
+pub use crate::batch::*;
+pub use crate::upload::*;
+pub use crate::collab::*;
+pub use crate::collection::*;
+pub use crate::rental::*;
+pub use crate::ft_callbacks::*;
+pub use crate::wrap::*;
+pub use crate::holdings::*;
+pub use crate::migrate::*;
 
 mod internal;
 mod enumeration; 
@@ -24,11 +35,36 @@ mod nft_core;
 mod approval; 
 mod royalty; 
 mod events;
+mod series;
+mod burn;
+mod owner;
+mod validity;
+mod batch;
+mod verification;
+mod upload;
+mod collab;
+mod creator;
+mod collection;
+mod rental;
+mod ft_callbacks;
+mod wrap;
+mod holdings;
+mod pause;
+mod storage;
+mod migrate;
+#[cfg(test)]
+mod test_utils;
 
 /// This spec can be treated like a version of the standard.
 pub const NFT_METADATA_SPEC: &str = "1.0.0";
 /// This is the name of the NFT standard we're using
 pub const NFT_STANDARD_NAME: &str = "nep171";
+/// The versions of the standard that introduced the `nft_metadata_update` and `contract_metadata_update` events
+pub const NFT_METADATA_UPDATE_VERSION: &str = "1.1.0";
+pub const CONTRACT_METADATA_UPDATE_VERSION: &str = "1.2.0";
+/// The name and version the events that aren't part of the NFT standard are logged under
+pub const ART_ECHO_STANDARD_NAME: &str = "art_echo";
+pub const ART_ECHO_EVENTS_VERSION: &str = "1.0.0";
 
 //Basic NEAR amounts as constants
 const ONE_YOCTONEAR: NearToken = NearToken::from_yoctonear(1);
@@ -40,17 +76,116 @@ pub struct ArtEcho {
   //contract owner
   pub owner_id: AccountId,
 
-  //keeps track of all the token IDs for a given account
-  pub tokens_per_owner: LookupMap<AccountId, UnorderedSet<TokenId>>,
+  //keeps track of all the token IDs for a given account in order, so they can be paged through by index or by cursor
+  pub tokens_per_owner: LookupMap<AccountId, TreeMap<TokenId, ()>>,
 
   //keeps track of the token struct for a given token ID
   pub tokens_by_id: LookupMap<TokenId, Token>,
 
   //keeps track of the token metadata for a given token ID
-  pub token_metadata_by_id: UnorderedMap<TokenId, TokenMetadata>,
+  pub token_metadata_by_id: IterableMap<TokenId, TokenMetadata>,
+
+  //keeps track of all the token IDs in order, so they can be paged through by cursor
+  pub sorted_token_ids: TreeMap<TokenId, ()>,
 
   //keeps track of the metadata for the contract
   pub metadata: LazyOption<NFTContractMetadata>,
+
+  //accounts that are allowed to mint tokens and create series
+  pub minters: Vec<AccountId>,
+
+  //accounts that are allowed to freeze tokens, on top of the owner
+  pub moderators: Vec<AccountId>,
+
+  //whether transfers, approvals and mints are stopped
+  pub paused: bool,
+
+  //keeps track of the tokens a moderator stopped from being transferred, and the moderator that paid for the freeze
+  pub transfer_frozen_tokens: IterableMap<TokenId, AccountId>,
+
+  //the art-spot registrar that is asked whether a caller who isn't a minter has the Artist role
+  pub registrar_id: Option<AccountId>,
+
+  //keeps track of the series struct for a given series ID
+  pub series_by_id: IterableMap<SeriesId, Series>,
+
+  //keeps track of all the token IDs minted for a given series
+  pub tokens_per_series: LookupMap<SeriesId, IterableSet<TokenId>>,
+
+  //keeps track of the series whose metadata has been locked
+  pub locked_series: LookupSet<SeriesId>,
+
+  //keeps track of the tokens whose metadata has been frozen
+  pub frozen_tokens: LookupSet<TokenId>,
+
+  //keeps track of the issuer of the one-off tokens that can't be transferred
+  pub soulbound_tokens: LookupMap<TokenId, AccountId>,
+
+  //keeps track of the series whose editions can't be transferred
+  pub soulbound_series: LookupSet<SeriesId>,
+
+  //keeps track of the account that created a given token ID
+  pub token_creators: LookupMap<TokenId, AccountId>,
+
+  //keeps track of all the token IDs created by a given account
+  pub tokens_per_creator: LookupMap<AccountId, IterableSet<TokenId>>,
+
+  //the creators the registrar reported as verified the last time they were checked
+  pub verified_creators: LookupSet<AccountId>,
+
+  //keeps track of the collection struct for a given collection ID
+  pub collections_by_id: IterableMap<CollectionId, Collection>,
+
+  //keeps track of all the token IDs minted in a given collection
+  pub tokens_per_collection: LookupMap<CollectionId, IterableSet<TokenId>>,
+
+  //keeps track of the co-creators of the collaborative tokens
+  pub collab_tokens: LookupMap<TokenId, Collab>,
+
+  //keeps track of the collaborative tokens that aren't minted until every co-creator accepted
+  pub pending_collabs: LookupMap<TokenId, PendingCollab>,
+
+  //keeps track of how many tokens of a given series or collection an account holds
+  pub holdings: LookupMap<(AccountId, HoldsFilter), u64>,
+
+  //keeps track of the terms a given token ID is listed for rent on
+  pub rental_listings: LookupMap<TokenId, RentalListing>,
+
+  //keeps track of the account a given token ID is lent to
+  pub leases: LookupMap<TokenId, Lease>,
+
+  //keeps track of the storage an account prepaid, which mints, approvals and metadata growth are debited from
+  pub storage_balances: LookupMap<AccountId, NearToken>,
+
+  //the NEP-171 contracts whose tokens can be locked in this contract to mint wrapped tokens
+  pub wrap_contracts: Vec<AccountId>,
+
+  //keeps track of the original token locked for a given wrapped token ID
+  pub wrapped_tokens: LookupMap<TokenId, WrappedToken>,
+
+  //keeps track of the originals of burned wrapped tokens that couldn't be released yet, and who they go to
+  pub unreleased_tokens: LookupMap<TokenId, (WrappedToken, AccountId)>,
+
+  //the SPOT contract whose tokens can be used to pay for tokens
+  pub spot_token_id: Option<AccountId>,
+
+  //keeps track of the SPOT that couldn't be paid out to a given account, e.g. because it isn't registered with SPOT
+  pub unpaid_spot: LookupMap<AccountId, U128>,
+
+  //whether tokens can no longer be transferred once their metadata expired
+  pub enforce_validity: bool,
+
+  //keeps track of the upload struct for a given upload ID
+  pub uploads: LookupMap<UploadId, Upload>,
+
+  //keeps track of the uploaded bytes for a given upload ID and page index
+  pub upload_pages: LookupMap<(UploadId, u64), Vec<u8>>,
+
+  //the ID the next upload gets
+  pub next_upload_id: UploadId,
+
+  //the tokens of the previous state that still have to be moved over by migrate_tokens
+  pub legacy_tokens: Option<LegacyTokens>,
 }
 
 /// Helper structure for keys of the persistent collections.
@@ -65,6 +200,40 @@ pub enum StorageKey {
     TokensPerType,
     TokensPerTypeInner { token_type_hash: CryptoHash },
     TokenTypesLocked,
+    SeriesById,
+    FrozenTokens,
+    TokensPerOwnerIterable,
+    TokensPerOwnerIterableInner { account_id_hash: CryptoHash },
+    TokenMetadataByIdIterable,
+    SeriesByIdIterable,
+    TokensPerSeries,
+    TokensPerSeriesInner { series_id_hash: CryptoHash },
+    LockedSeries,
+    SoulboundTokens,
+    SoulboundSeries,
+    Uploads,
+    UploadPages,
+    CollabTokens,
+    TokenCreators,
+    TokensPerCreator,
+    TokensPerCreatorInner { account_id_hash: CryptoHash },
+    VerifiedCreators,
+    CollectionsById,
+    TokensPerCollection,
+    TokensPerCollectionInner { collection_id_hash: CryptoHash },
+    SortedTokenIds,
+    SortedTokensPerOwner,
+    SortedTokensPerOwnerInner { account_id_hash: CryptoHash },
+    RentalListings,
+    Leases,
+    TransferFrozenTokens,
+    StorageBalances,
+    WrappedTokens,
+    UnreleasedTokens,
+    Holdings,
+    TokensByIdWithSeries,
+    PendingCollabs,
+    UnpaidSpot,
 }
 
 #[near_bindgen]
@@ -101,15 +270,49 @@ impl ArtEcho {
       //create a variable of type Self with all the fields initialized. 
       let this = Self {
           //Storage keys are simply the prefixes used for the collections. This helps avoid data collision
-          tokens_per_owner: LookupMap::new(StorageKey::TokensPerOwner),
-          tokens_by_id: LookupMap::new(StorageKey::TokensById),
-          token_metadata_by_id: UnorderedMap::new(StorageKey::TokenMetadataById),
+          tokens_per_owner: LookupMap::new(StorageKey::SortedTokensPerOwner),
+          tokens_by_id: LookupMap::new(StorageKey::TokensByIdWithSeries),
+          token_metadata_by_id: IterableMap::new(StorageKey::TokenMetadataByIdIterable),
+          sorted_token_ids: TreeMap::new(StorageKey::SortedTokenIds),
+          //the owner is the first minter
+          minters: vec![owner_id.clone()],
+          moderators: Vec::new(),
+          paused: false,
+          transfer_frozen_tokens: IterableMap::new(StorageKey::TransferFrozenTokens),
+          registrar_id: None,
           //set the owner_id field equal to the passed in owner_id. 
           owner_id,
           metadata: LazyOption::new(
               StorageKey::NFTContractMetadata,
-              Some(&metadata),
+              Some(metadata),
           ),
+          series_by_id: IterableMap::new(StorageKey::SeriesByIdIterable),
+          tokens_per_series: LookupMap::new(StorageKey::TokensPerSeries),
+          locked_series: LookupSet::new(StorageKey::LockedSeries),
+          frozen_tokens: LookupSet::new(StorageKey::FrozenTokens),
+          soulbound_tokens: LookupMap::new(StorageKey::SoulboundTokens),
+          soulbound_series: LookupSet::new(StorageKey::SoulboundSeries),
+          token_creators: LookupMap::new(StorageKey::TokenCreators),
+          tokens_per_creator: LookupMap::new(StorageKey::TokensPerCreator),
+          verified_creators: LookupSet::new(StorageKey::VerifiedCreators),
+          collab_tokens: LookupMap::new(StorageKey::CollabTokens),
+          pending_collabs: LookupMap::new(StorageKey::PendingCollabs),
+          collections_by_id: IterableMap::new(StorageKey::CollectionsById),
+          tokens_per_collection: LookupMap::new(StorageKey::TokensPerCollection),
+          holdings: LookupMap::new(StorageKey::Holdings),
+          rental_listings: LookupMap::new(StorageKey::RentalListings),
+          leases: LookupMap::new(StorageKey::Leases),
+          storage_balances: LookupMap::new(StorageKey::StorageBalances),
+          wrap_contracts: Vec::new(),
+          wrapped_tokens: LookupMap::new(StorageKey::WrappedTokens),
+          unreleased_tokens: LookupMap::new(StorageKey::UnreleasedTokens),
+          spot_token_id: None,
+          unpaid_spot: LookupMap::new(StorageKey::UnpaidSpot),
+          enforce_validity: false,
+          uploads: LookupMap::new(StorageKey::Uploads),
+          upload_pages: LookupMap::new(StorageKey::UploadPages),
+          next_upload_id: 0,
+          legacy_tokens: None,
       };
 
       //return the Contract object
@@ -1,4 +1,4 @@
-use near_sdk::{near, NearToken};
+use near_sdk::near;
 
 use crate::*;
 pub type TokenId = String;
@@ -6,7 +6,7 @@ pub type TokenId = String;
 #[derive(Serialize, Deserialize, NearSchema)]
 #[serde(crate = "near_sdk::serde")]
 pub struct Payout {
-    pub payout: HashMap<AccountId, NearToken>,
+    pub payout: HashMap<AccountId, U128>,
 } 
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, NearSchema)]
@@ -22,7 +22,17 @@ pub struct NFTContractMetadata {
   pub reference_hash: Option<Base64VecU8>, // Base64-encoded sha256 hash of JSON from reference field. Required if `reference` is included.
 }
 
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, NearSchema)]
+//The contract metadata returned by nft_metadata, with whether the contract is paused
+#[derive(Serialize, Deserialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonContractMetadata {
+  #[serde(flatten)]
+  pub metadata: NFTContractMetadata,
+  //whether transfers, approvals and mints are stopped
+  pub paused: bool,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, NearSchema)]
 #[borsh(crate = "near_sdk::borsh")]
 #[serde(crate = "near_sdk::serde")]
 pub struct TokenMetadata {
@@ -40,7 +50,30 @@ pub struct TokenMetadata {
   pub reference_hash: Option<Base64VecU8>, // Base64-encoded sha256 hash of JSON from reference field. Required if `reference` is included.
 }
 
-#[derive(BorshDeserialize, BorshSerialize)]
+//the fields of the token metadata that can be changed after minting. Fields that are left out stay the same
+#[derive(Serialize, Deserialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenMetadataPatch {
+  pub title: Option<String>,
+  pub description: Option<String>,
+  pub media: Option<String>,
+  pub media_hash: Option<Base64VecU8>,
+  pub extra: Option<String>,
+  pub reference: Option<String>,
+  pub reference_hash: Option<Base64VecU8>,
+}
+
+//the fields of the contract metadata that can be changed by the owner. Fields that are left out stay the same
+#[derive(Serialize, Deserialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NFTContractMetadataPatch {
+  pub icon: Option<String>,
+  pub base_uri: Option<String>,
+  pub reference: Option<String>,
+  pub reference_hash: Option<Base64VecU8>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
 #[borsh(crate = "near_sdk::borsh")]
 pub struct Token {
   //owner of the token
@@ -51,6 +84,8 @@ pub struct Token {
   pub next_approval_id: u32,
   //keep track of the royalty percentages for the token in a hash map
   pub royalty: HashMap<AccountId, u32>,
+  //the series this token was minted from (if it's an edition of a series)
+  pub series_id: Option<SeriesId>,
 }
 
 //The Json token is what will be returned from view calls. 
@@ -67,16 +102,406 @@ pub struct JsonToken {
   pub approved_account_ids: HashMap<AccountId, u32>,
   //keep track of the royalty percentages for the token in a hash map
   pub royalty: HashMap<AccountId, u32>,
+  //the series this token was minted from (if it's an edition of a series)
+  pub series_id: Option<SeriesId>,
+  //whether the token can be transferred. Tokens that can't be transferred can only be burned by their issuer
+  pub transferable: bool,
+  //the account that created the token. Tokens minted before creators were recorded don't have one
+  pub creator_id: Option<AccountId>,
+  //whether the registrar reported the creator as a verified artist the last time they were checked
+  pub creator_verified: bool,
+  //the collection the token was minted in, if any
+  pub collection_id: Option<CollectionId>,
+  //the account the token is lent to and until when, if the lease didn't expire
+  pub lease: Option<Lease>,
 }
 
 pub trait NonFungibleTokenMetadata {
-    //view call for returning the contract metadata
-    fn nft_metadata(&self) -> NFTContractMetadata;
+    //view call for returning the contract metadata and whether the contract is paused
+    fn nft_metadata(&self) -> JsonContractMetadata;
 }
 
 #[near]
 impl NonFungibleTokenMetadata for ArtEcho {
-    fn nft_metadata(&self) -> NFTContractMetadata {
-      self.metadata.get().unwrap()
+    fn nft_metadata(&self) -> JsonContractMetadata {
+      JsonContractMetadata {
+          metadata: self.metadata.get().clone().unwrap(),
+          paused: self.paused,
+      }
+    }
+}
+
+//logs the tokens whose metadata was updated as per the events standard
+pub(crate) fn log_nft_metadata_update(token_ids: Vec<TokenId>) {
+    // Construct the metadata update log as per the events standard.
+    let nft_metadata_update_log: EventLog = EventLog {
+        // Standard name ("nep171").
+        standard: NFT_STANDARD_NAME.to_string(),
+        // Version of the standard that introduced the event ("1.1.0").
+        version: NFT_METADATA_UPDATE_VERSION.to_string(),
+        // The data related with the event stored in a vector.
+        event: EventLogVariant::NftMetadataUpdate(vec![NftMetadataUpdateLog {
+            // Vector of token IDs that were updated.
+            token_ids,
+            // An optional memo to include.
+            memo: None,
+        }]),
+    };
+
+    // Log the serialized json.
+    env::log_str(&nft_metadata_update_log.to_string());
+}
+
+#[near]
+impl ArtEcho {
+    //update the metadata of a token. Only the contract owner or the creator of the token or its series can update it,
+    //and only while neither the token nor its series are frozen
+    #[payable]
+    pub fn nft_update_metadata(&mut self, token_id: TokenId, patch: TokenMetadataPatch) {
+        //assert at least one yocto for security reasons and to pay for storage growth
+        assert_at_least_one_yocto();
+
+        //make sure the token isn't still being migrated
+        self.assert_migrated();
+
+        //get the token object and make sure the caller can update it
+        let token = self.tokens_by_id.get(&token_id).expect("No token");
+        self.assert_metadata_updater(&token_id, token);
+        assert!(!self.internal_is_metadata_frozen(&token_id, token), "Token metadata is frozen");
+
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        //apply the fields of the patch that were passed in
+        let metadata = self.token_metadata_by_id.get_mut(&token_id).unwrap();
+        if let Some(title) = patch.title {
+            metadata.title = Some(title);
+        }
+        if let Some(description) = patch.description {
+            metadata.description = Some(description);
+        }
+        if let Some(media) = patch.media {
+            metadata.media = Some(media);
+        }
+        if let Some(media_hash) = patch.media_hash {
+            metadata.media_hash = Some(media_hash);
+        }
+        if let Some(extra) = patch.extra {
+            metadata.extra = Some(extra);
+        }
+        if let Some(reference) = patch.reference {
+            metadata.reference = Some(reference);
+        }
+        if let Some(reference_hash) = patch.reference_hash {
+            metadata.reference_hash = Some(reference_hash);
+        }
+        //the media hash has to be the hash of the upload if the media points to one
+        link_upload(&mut self.uploads, metadata);
+        metadata.updated_at = Some(env::block_timestamp_ms());
+
+        //log the update as per the events standard
+        log_nft_metadata_update(vec![token_id]);
+
+        //charge the caller for the extra storage used or refund them the storage that was released
+        self.refund_storage_difference(initial_storage_usage);
+    }
+
+    //freeze the metadata of a token so it can never be updated again
+    #[payable]
+    pub fn nft_freeze_metadata(&mut self, token_id: TokenId) {
+        //assert at least one yocto for security reasons and to pay for storage
+        assert_at_least_one_yocto();
+
+        //get the token object and make sure the caller can update it
+        let token = self.tokens_by_id.get(&token_id).expect("No token");
+        self.assert_metadata_updater(&token_id, token);
+
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        assert!(self.frozen_tokens.insert(token_id), "Token metadata is already frozen");
+
+        //calculate the required storage which was the used - initial
+        let required_storage_in_bytes = self.internal_storage_usage() - initial_storage_usage;
+
+        //refund any excess storage if the user attached too much. Panic if they didn't attach enough to cover the required.
+        self.refund_deposit(required_storage_in_bytes.into());
+    }
+
+    //check if the metadata of a token can no longer be updated, either because the token or its series is frozen
+    pub fn nft_is_metadata_frozen(&self, token_id: TokenId) -> bool {
+        let token = self.tokens_by_id.get(&token_id).expect("No token");
+        self.internal_is_metadata_frozen(&token_id, token)
+    }
+
+    //update the icon, base URI and reference of the contract metadata. Only the contract owner can update it
+    #[payable]
+    pub fn nft_update_contract_metadata(&mut self, patch: NFTContractMetadataPatch) {
+        //assert at least one yocto for security reasons and to pay for storage growth
+        assert_at_least_one_yocto();
+        self.assert_owner();
+
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        //apply the fields of the patch that were passed in
+        let metadata = self.metadata.get_mut().as_mut().unwrap();
+        if let Some(icon) = patch.icon {
+            metadata.icon = Some(icon);
+        }
+        if let Some(base_uri) = patch.base_uri {
+            metadata.base_uri = Some(base_uri);
+        }
+        if let Some(reference) = patch.reference {
+            metadata.reference = Some(reference);
+        }
+        if let Some(reference_hash) = patch.reference_hash {
+            metadata.reference_hash = Some(reference_hash);
+        }
+
+        // Construct the contract metadata update log as per the events standard.
+        let contract_metadata_update_log: EventLog = EventLog {
+            // Standard name ("nep171").
+            standard: NFT_STANDARD_NAME.to_string(),
+            // Version of the standard that introduced the event ("1.2.0").
+            version: CONTRACT_METADATA_UPDATE_VERSION.to_string(),
+            // The data related with the event stored in a vector.
+            event: EventLogVariant::ContractMetadataUpdate(vec![ContractMetadataUpdateLog {
+                // An optional memo to include.
+                memo: None,
+            }]),
+        };
+
+        // Log the serialized json.
+        env::log_str(&contract_metadata_update_log.to_string());
+
+        //charge the caller for the extra storage used or refund them the storage that was released
+        self.refund_storage_difference(initial_storage_usage);
+    }
+}
+
+impl ArtEcho {
+    //make sure the caller is the contract owner or the creator of the token's series, or of the token if it's a one-off
+    pub(crate) fn assert_metadata_updater(&self, token_id: &TokenId, token: &Token) {
+        let predecessor_account_id = env::predecessor_account_id();
+        if predecessor_account_id == self.owner_id {
+            return;
+        }
+
+        let creator_id = match token.series_id.as_ref() {
+            Some(series_id) => self.series_by_id.get(series_id).map(|series| &series.creator_id),
+            None => self.token_creators.get(token_id),
+        };
+        let is_creator = creator_id.is_some_and(|creator_id| *creator_id == predecessor_account_id);
+        assert!(is_creator, "Predecessor must be the contract owner or the token creator.");
+    }
+
+    //check if the token or the series it belongs to is frozen
+    pub(crate) fn internal_is_metadata_frozen(&self, token_id: &TokenId, token: &Token) -> bool {
+        self.frozen_tokens.contains(token_id)
+            || token
+                .series_id
+                .as_ref()
+                .is_some_and(|series_id| self.locked_series.contains(series_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::get_logs;
+
+    const TOKEN_ID: &str = "token";
+
+    fn empty_patch() -> TokenMetadataPatch {
+        TokenMetadataPatch {
+            title: None,
+            description: None,
+            media: None,
+            media_hash: None,
+            extra: None,
+            reference: None,
+            reference_hash: None,
+        }
+    }
+
+    //a contract with a one-off token and the edition series:1 created by artist.near, both owned by buyer.near
+    fn setup_contract() -> ArtEcho {
+        let mut contract = crate::test_utils::setup_contract();
+        contract.internal_mint(TOKEN_ID.to_string(), sample_metadata(), account("buyer.near"), None, None, owner());
+        contract.minters.push(account("artist.near"));
+
+        set_context(account("artist.near"), NearToken::from_near(1));
+        let mut series_metadata = sample_metadata();
+        series_metadata.copies = Some(10);
+        contract.nft_create_series("series".to_string(), series_metadata, None, None, None);
+        contract.nft_mint_series("series".to_string(), account("buyer.near"));
+        contract
+    }
+
+    #[test]
+    fn owner_updates_metadata_and_logs_event() {
+        let mut contract = setup_contract();
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_update_metadata(
+            TOKEN_ID.to_string(),
+            TokenMetadataPatch {
+                description: Some("A much longer description than before".to_string()),
+                ..empty_patch()
+            },
+        );
+
+        let metadata = contract.token_metadata_by_id.get(TOKEN_ID).unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Token"));
+        assert_eq!(metadata.description.as_deref(), Some("A much longer description than before"));
+        assert!(metadata.updated_at.is_some());
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"nep171","version":"1.1.0","event":"nft_metadata_update","data":[{"token_ids":["token"]}]}"#]
+        );
+        //the storage growth was charged and the rest of the deposit refunded
+        assert!(refunded_to(&owner()) < NearToken::from_near(1));
+    }
+
+    #[test]
+    fn shrinking_metadata_refunds_released_storage() {
+        let mut contract = setup_contract();
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_update_metadata(
+            TOKEN_ID.to_string(),
+            TokenMetadataPatch { description: Some("A much longer description".to_string()), ..empty_patch() },
+        );
+
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.nft_update_metadata(
+            TOKEN_ID.to_string(),
+            TokenMetadataPatch { description: Some("Short".to_string()), ..empty_patch() },
+        );
+
+        assert!(refunded_to(&owner()) > ONE_YOCTONEAR);
+    }
+
+    #[test]
+    fn series_creator_updates_edition_metadata() {
+        let mut contract = setup_contract();
+
+        set_context(account("artist.near"), NearToken::from_near(1));
+        contract.nft_update_metadata(
+            "series:1".to_string(),
+            TokenMetadataPatch { media: Some("ipfs://media".to_string()), ..empty_patch() },
+        );
+
+        let metadata = contract.token_metadata_by_id.get("series:1").unwrap();
+        assert_eq!(metadata.media.as_deref(), Some("ipfs://media"));
+    }
+
+    #[test]
+    fn creator_updates_one_off_token_metadata() {
+        let mut contract = setup_contract();
+        set_context(account("artist.near"), NearToken::from_near(1));
+        contract.nft_mint("artwork".to_string(), sample_metadata(), account("buyer.near"), None, None);
+
+        set_context(account("artist.near"), NearToken::from_near(1));
+        contract.nft_update_metadata(
+            "artwork".to_string(),
+            TokenMetadataPatch { media: Some("ipfs://media".to_string()), ..empty_patch() },
+        );
+
+        let metadata = contract.token_metadata_by_id.get("artwork").unwrap();
+        assert_eq!(metadata.media.as_deref(), Some("ipfs://media"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Predecessor must be the contract owner or the token creator.")]
+    fn token_owner_cannot_update_metadata() {
+        let mut contract = setup_contract();
+
+        set_context(account("buyer.near"), NearToken::from_near(1));
+        contract.nft_update_metadata(
+            TOKEN_ID.to_string(),
+            TokenMetadataPatch { title: Some("Mine".to_string()), ..empty_patch() },
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Predecessor must be the contract owner or the token creator.")]
+    fn series_creator_cannot_update_other_tokens() {
+        let mut contract = setup_contract();
+
+        set_context(account("artist.near"), NearToken::from_near(1));
+        contract.nft_update_metadata(
+            TOKEN_ID.to_string(),
+            TokenMetadataPatch { title: Some("Mine".to_string()), ..empty_patch() },
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Token metadata is frozen")]
+    fn frozen_token_cannot_be_updated() {
+        let mut contract = setup_contract();
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_freeze_metadata(TOKEN_ID.to_string());
+        assert!(contract.nft_is_metadata_frozen(TOKEN_ID.to_string()));
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_update_metadata(
+            TOKEN_ID.to_string(),
+            TokenMetadataPatch { title: Some("Changed".to_string()), ..empty_patch() },
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Token metadata is frozen")]
+    fn editions_of_locked_series_cannot_be_updated() {
+        let mut contract = setup_contract();
+
+        set_context(account("artist.near"), NearToken::from_near(1));
+        contract.nft_lock_series("series".to_string());
+        assert!(contract.nft_is_metadata_frozen("series:1".to_string()));
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_update_metadata(
+            "series:1".to_string(),
+            TokenMetadataPatch { title: Some("Changed".to_string()), ..empty_patch() },
+        );
+    }
+
+    #[test]
+    fn owner_updates_contract_metadata_and_logs_event() {
+        let mut contract = setup_contract();
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_update_contract_metadata(NFTContractMetadataPatch {
+            icon: Some("data:image/svg+xml,<svg/>".to_string()),
+            base_uri: Some("https://ipfs.io/ipfs".to_string()),
+            reference: None,
+            reference_hash: None,
+        });
+
+        let metadata = contract.nft_metadata().metadata;
+        assert_eq!(metadata.icon.as_deref(), Some("data:image/svg+xml,<svg/>"));
+        assert_eq!(metadata.base_uri.as_deref(), Some("https://ipfs.io/ipfs"));
+        assert_eq!(metadata.name, "NFT Tutorial Contract");
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"nep171","version":"1.2.0","event":"contract_metadata_update","data":[{}]}"#]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Predecessor must be the contract owner.")]
+    fn only_owner_updates_contract_metadata() {
+        let mut contract = setup_contract();
+
+        set_context(account("artist.near"), NearToken::from_near(1));
+        contract.nft_update_contract_metadata(NFTContractMetadataPatch {
+            icon: Some("data:image/svg+xml,<svg/>".to_string()),
+            base_uri: None,
+            reference: None,
+            reference_hash: None,
+        });
     }
 }
@@ -0,0 +1,336 @@
+use crate::*;
+use near_sdk::collections;
+
+//a token the way the previous state stored it, before tokens could belong to a series
+#[derive(BorshDeserialize, BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct LegacyToken {
+    pub owner_id: AccountId,
+    pub approved_account_ids: HashMap<AccountId, u32>,
+    pub next_approval_id: u32,
+    pub royalty: HashMap<AccountId, u32>,
+}
+
+impl From<LegacyToken> for Token {
+    fn from(token: LegacyToken) -> Self {
+        Token {
+            owner_id: token.owner_id,
+            approved_account_ids: token.approved_account_ids,
+            next_approval_id: token.next_approval_id,
+            royalty: token.royalty,
+            //tokens of the previous state are all one-off tokens
+            series_id: None,
+        }
+    }
+}
+
+//the tokens of the previous state that haven't been moved over to the iterable collections yet
+#[derive(BorshDeserialize, BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct LegacyTokens {
+    //the tokens that still have to be converted. They're removed once they're moved over
+    pub tokens_by_id: collections::LookupMap<TokenId, LegacyToken>,
+    //the token metadata that still has to be moved over. The tokens are migrated in the order they're removed from this map
+    pub token_metadata_by_id: collections::UnorderedMap<TokenId, TokenMetadata>,
+    //the sets of tokens per owner that still have to be cleared
+    pub tokens_per_owner: collections::LookupMap<AccountId, collections::UnorderedSet<TokenId>>,
+}
+
+//the state of the contract before it moved to the iterable collections
+#[derive(BorshDeserialize, BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+pub(crate) struct OldArtEcho {
+    pub owner_id: AccountId,
+    pub tokens_per_owner: collections::LookupMap<AccountId, collections::UnorderedSet<TokenId>>,
+    pub tokens_by_id: collections::LookupMap<TokenId, LegacyToken>,
+    pub token_metadata_by_id: collections::UnorderedMap<TokenId, TokenMetadata>,
+    pub metadata: collections::LazyOption<NFTContractMetadata>,
+}
+
+#[near]
+impl ArtEcho {
+    /*
+        converts the previous state to the iterable collections. The contract metadata is stored the same way by
+        both lazy options, so it's written back under the same key. The tokens are moved over in batches by
+        migrate_tokens since there can be too many of them for a single call. Tokens can't be changed until all
+        of them have been moved over.
+    */
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let old_state: OldArtEcho = env::state_read().expect("Failed to read the previous state");
+        let OldArtEcho {
+            owner_id,
+            tokens_per_owner,
+            tokens_by_id,
+            token_metadata_by_id,
+            metadata,
+        } = old_state;
+
+        let mut this = Self {
+            owner_id: owner_id.clone(),
+            //the sorted indices are filled in by migrate_tokens
+            tokens_per_owner: LookupMap::new(StorageKey::SortedTokensPerOwner),
+            //the converted tokens are stored under a new prefix, the previous ones are converted by migrate_tokens
+            tokens_by_id: LookupMap::new(StorageKey::TokensByIdWithSeries),
+            token_metadata_by_id: IterableMap::new(StorageKey::TokenMetadataByIdIterable),
+            sorted_token_ids: TreeMap::new(StorageKey::SortedTokenIds),
+            //the contract metadata is stored under the same key by both lazy options
+            metadata: LazyOption::new(StorageKey::NFTContractMetadata, metadata.get()),
+            //the owner is the first minter
+            minters: vec![owner_id],
+            moderators: Vec::new(),
+            paused: false,
+            transfer_frozen_tokens: IterableMap::new(StorageKey::TransferFrozenTokens),
+            registrar_id: None,
+            series_by_id: IterableMap::new(StorageKey::SeriesByIdIterable),
+            tokens_per_series: LookupMap::new(StorageKey::TokensPerSeries),
+            locked_series: LookupSet::new(StorageKey::LockedSeries),
+            frozen_tokens: LookupSet::new(StorageKey::FrozenTokens),
+            //every token of the previous state can be transferred
+            soulbound_tokens: LookupMap::new(StorageKey::SoulboundTokens),
+            soulbound_series: LookupSet::new(StorageKey::SoulboundSeries),
+            token_creators: LookupMap::new(StorageKey::TokenCreators),
+            tokens_per_creator: LookupMap::new(StorageKey::TokensPerCreator),
+            verified_creators: LookupSet::new(StorageKey::VerifiedCreators),
+            collab_tokens: LookupMap::new(StorageKey::CollabTokens),
+            pending_collabs: LookupMap::new(StorageKey::PendingCollabs),
+            collections_by_id: IterableMap::new(StorageKey::CollectionsById),
+            tokens_per_collection: LookupMap::new(StorageKey::TokensPerCollection),
+            //the holdings are counted by migrate_tokens
+            holdings: LookupMap::new(StorageKey::Holdings),
+            rental_listings: LookupMap::new(StorageKey::RentalListings),
+            leases: LookupMap::new(StorageKey::Leases),
+            storage_balances: LookupMap::new(StorageKey::StorageBalances),
+            wrap_contracts: Vec::new(),
+            wrapped_tokens: LookupMap::new(StorageKey::WrappedTokens),
+            unreleased_tokens: LookupMap::new(StorageKey::UnreleasedTokens),
+            spot_token_id: None,
+            unpaid_spot: LookupMap::new(StorageKey::UnpaidSpot),
+            enforce_validity: false,
+            uploads: LookupMap::new(StorageKey::Uploads),
+            upload_pages: LookupMap::new(StorageKey::UploadPages),
+            next_upload_id: 0,
+            legacy_tokens: None,
+        };
+
+        //the tokens are moved over by migrate_tokens
+        if !token_metadata_by_id.is_empty() {
+            this.legacy_tokens = Some(LegacyTokens {
+                tokens_by_id,
+                token_metadata_by_id,
+                tokens_per_owner,
+            });
+        }
+
+        this
+    }
+
+    //move up to `limit` tokens over from the previous state. Only the contract owner can migrate the tokens.
+    //returns the number of tokens that still have to be moved over
+    pub fn migrate_tokens(&mut self, limit: u32) -> U64 {
+        self.assert_owner();
+        let mut legacy = self.legacy_tokens.take().expect("No tokens left to migrate");
+
+        for _ in 0..limit {
+            //take the last token so removing it from the legacy map doesn't have to move another one
+            let length = legacy.token_metadata_by_id.len();
+            if length == 0 {
+                break;
+            }
+            let token_id = legacy.token_metadata_by_id.keys_as_vector().get(length - 1).unwrap();
+            let metadata = legacy.token_metadata_by_id.remove(&token_id).unwrap();
+            let token: Token = legacy.tokens_by_id.remove(&token_id).expect("No token").into();
+
+            //move the token over to the iterable collections
+            let owner_id = token.owner_id.clone();
+            self.tokens_by_id.insert(token_id.clone(), token);
+            self.token_metadata_by_id.insert(token_id.clone(), metadata);
+            self.sorted_token_ids.insert(token_id.clone(), ());
+            self.internal_add_token_to_owner(&owner_id, &token_id);
+
+            //clear the token from the legacy set of the owner
+            if let Some(mut tokens_set) = legacy.tokens_per_owner.get(&owner_id) {
+                tokens_set.remove(&token_id);
+                if tokens_set.is_empty() {
+                    legacy.tokens_per_owner.remove(&owner_id);
+                } else {
+                    legacy.tokens_per_owner.insert(&owner_id, &tokens_set);
+                }
+            }
+        }
+
+        //keep the legacy tokens around until every token has been moved over
+        let tokens_left = legacy.token_metadata_by_id.len();
+        if tokens_left > 0 {
+            self.legacy_tokens = Some(legacy);
+        }
+
+        U64(tokens_left)
+    }
+}
+
+impl ArtEcho {
+    //get a token, falling back to the previous state for tokens that haven't been moved over yet
+    pub(crate) fn internal_token(&self, token_id: &TokenId) -> Option<Token> {
+        if let Some(token) = self.tokens_by_id.get(token_id) {
+            return Some(token.clone());
+        }
+
+        self.legacy_tokens
+            .as_ref()
+            .and_then(|legacy| legacy.tokens_by_id.get(token_id))
+            .map(Token::from)
+    }
+
+    //get the metadata of a token, falling back to the previous state for tokens that haven't been moved over yet
+    pub(crate) fn internal_token_metadata(&self, token_id: &TokenId) -> TokenMetadata {
+        if let Some(metadata) = self.token_metadata_by_id.get(token_id) {
+            return metadata.clone();
+        }
+
+        self.legacy_tokens
+            .as_ref()
+            .and_then(|legacy| legacy.token_metadata_by_id.get(token_id))
+            .expect("No token metadata")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    //adds a token to the previous state the way the legacy collections stored it
+    fn add_old_token(old_state: &mut OldArtEcho, token_id: &str, owner_id: AccountId) {
+        let token_id = token_id.to_string();
+        let token = LegacyToken {
+            owner_id: owner_id.clone(),
+            approved_account_ids: HashMap::from([(account("market.near"), 0)]),
+            next_approval_id: 1,
+            royalty: HashMap::from([(account("artist.near"), 1000)]),
+        };
+        old_state.tokens_by_id.insert(&token_id, &token);
+        old_state.token_metadata_by_id.insert(&token_id, &sample_metadata());
+
+        let mut tokens_set = old_state.tokens_per_owner.get(&owner_id).unwrap_or_else(|| {
+            collections::UnorderedSet::new(StorageKey::TokenPerOwnerInner { account_id_hash: hash_account_id(&owner_id) })
+        });
+        tokens_set.insert(&token_id);
+        old_state.tokens_per_owner.insert(&owner_id, &tokens_set);
+    }
+
+    //writes the state of the baseline contract with three tokens owned by alice and bob, then migrates it
+    fn setup_migrated_contract() -> ArtEcho {
+        set_context(account("art-echo.near"), NearToken::from_yoctonear(0));
+        let mut old_state = OldArtEcho {
+            owner_id: owner(),
+            tokens_per_owner: collections::LookupMap::new(StorageKey::TokensPerOwner),
+            tokens_by_id: collections::LookupMap::new(StorageKey::TokensById),
+            token_metadata_by_id: collections::UnorderedMap::new(StorageKey::TokenMetadataById),
+            metadata: collections::LazyOption::new(
+                StorageKey::NFTContractMetadata,
+                Some(&NFTContractMetadata {
+                    spec: "nft-1.0.0".to_string(),
+                    name: "Old Contract".to_string(),
+                    symbol: "OLD".to_string(),
+                    icon: None,
+                    base_uri: None,
+                    reference: None,
+                    reference_hash: None,
+                }),
+            ),
+        };
+
+        add_old_token(&mut old_state, "one", account("alice.near"));
+        add_old_token(&mut old_state, "two", account("alice.near"));
+        add_old_token(&mut old_state, "three", account("bob.near"));
+        env::state_write(&old_state);
+
+        ArtEcho::migrate()
+    }
+
+    #[test]
+    fn migrate_moves_contract_state_over() {
+        let contract = setup_migrated_contract();
+
+        assert_eq!(contract.nft_metadata().metadata.name, "Old Contract");
+        assert_eq!(contract.get_minters(), vec![owner()]);
+        assert_eq!(contract.get_registrar(), None);
+        assert_eq!(contract.nft_total_supply(), U64(3));
+        //the tokens can be viewed before they're moved over
+        let token = contract.nft_token("three".to_string()).unwrap();
+        assert_eq!(token.owner_id, account("bob.near"));
+        assert_eq!(token.series_id, None);
+        assert!(contract.legacy_tokens.is_some());
+    }
+
+    #[test]
+    fn migrate_tokens_moves_tokens_over_in_batches() {
+        let mut contract = setup_migrated_contract();
+
+        set_context(owner(), NearToken::from_yoctonear(0));
+        assert_eq!(contract.migrate_tokens(2), U64(1));
+        assert_eq!(contract.nft_total_supply(), U64(3));
+        assert_eq!(contract.migrate_tokens(2), U64(0));
+        assert!(contract.legacy_tokens.is_none());
+
+        assert_eq!(contract.nft_tokens(None, None).len(), 3);
+        assert_eq!(contract.nft_supply_for_owner(account("alice.near")), U64(2));
+        assert_eq!(contract.nft_tokens_for_owner(account("bob.near"), None, None)[0].token_id, "three");
+
+        //the approvals and royalties were converted with the tokens
+        let token = contract.nft_token("one".to_string()).unwrap();
+        assert_eq!(token.approved_account_ids, HashMap::from([(account("market.near"), 0)]));
+        assert_eq!(token.royalty, HashMap::from([(account("artist.near"), 1000)]));
+        assert_eq!(token.series_id, None);
+
+        //the legacy collections were cleared
+        let legacy_tokens_per_owner: collections::LookupMap<AccountId, collections::UnorderedSet<TokenId>> =
+            collections::LookupMap::new(StorageKey::TokensPerOwner);
+        assert!(!legacy_tokens_per_owner.contains_key(&account("alice.near")));
+        let legacy_tokens_by_id: collections::LookupMap<TokenId, LegacyToken> =
+            collections::LookupMap::new(StorageKey::TokensById);
+        assert!(!legacy_tokens_by_id.contains_key(&"one".to_string()));
+
+        //the tokens can be transferred again
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_transfer(account("bob.near"), "one".to_string(), None, None);
+        assert_eq!(contract.nft_supply_for_owner(account("bob.near")), U64(2));
+    }
+
+    #[test]
+    fn contract_metadata_can_be_updated_after_migrating() {
+        let mut contract = setup_migrated_contract();
+
+        set_context(owner(), NearToken::from_millinear(10));
+        contract.nft_update_contract_metadata(NFTContractMetadataPatch {
+            icon: Some("icon".to_string()),
+            base_uri: None,
+            reference: None,
+            reference_hash: None,
+        });
+
+        let metadata = contract.nft_metadata().metadata;
+        assert_eq!(metadata.name, "Old Contract");
+        assert_eq!(metadata.icon, Some("icon".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Tokens are still being migrated")]
+    fn tokens_cannot_be_transferred_while_migrating() {
+        let mut contract = setup_migrated_contract();
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_transfer(account("bob.near"), "one".to_string(), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Predecessor must be the contract owner.")]
+    fn only_owner_migrates_tokens() {
+        let mut contract = setup_migrated_contract();
+
+        set_context(account("alice.near"), NearToken::from_yoctonear(0));
+        contract.migrate_tokens(10);
+    }
+}
@@ -1,7 +1,70 @@
+//nft_resolve_mint takes every argument of nft_mint, so the methods generated for the callback have a lot of arguments
+#![allow(clippy::too_many_arguments)]
+
 use crate::*;
+use near_sdk::{ext_contract, log, Gas, PromiseResult};
+
+const GAS_FOR_HAS_ROLE: Gas = Gas::from_tgas(5);
+const GAS_FOR_RESOLVE_MINT: Gas = Gas::from_tgas(20);
+
+//the role an account needs in the art-spot registrar to mint without being a minter
+const ARTIST_ROLE: &str = "Artist";
+
+#[allow(dead_code)]
+#[ext_contract(ext_registrar)]
+pub(crate) trait Registrar {
+    //view call on the art-spot registrar to check if an account has a role
+    fn has_role(&self, id: AccountId, role: String) -> bool;
+    //view call on the art-spot registrar to check if an account is verified
+    fn is_verified(&self, id: AccountId) -> bool;
+}
+
+//logs the tokens minted to an owner as per the events standard
+pub(crate) fn log_nft_mint(owner_id: &AccountId, token_ids: Vec<TokenId>) {
+    // Construct the mint log as per the events standard.
+    let nft_mint_log: EventLog = EventLog {
+        // Standard name ("nep171").
+        standard: NFT_STANDARD_NAME.to_string(),
+        // Version of the standard ("nft-1.0.0").
+        version: NFT_METADATA_SPEC.to_string(),
+        // The data related with the event stored in a vector.
+        event: EventLogVariant::NftMint(vec![NftMintLog {
+            // Owner of the tokens.
+            owner_id: owner_id.to_string(),
+            // Vector of token IDs that were minted.
+            token_ids,
+            // An optional memo to include.
+            memo: None,
+        }]),
+    };
+
+    // Log the serialized json.
+    env::log_str(&nft_mint_log.to_string());
+}
+
+//make sure the token ID can't collide with the editions of a series, the tokens of a collection or the wrapped tokens
+pub(crate) fn assert_valid_token_id(token_id: &TokenId) {
+    assert!(
+        !token_id.contains(SERIES_DELIMITER),
+        "Token ID cannot contain '{}'", SERIES_DELIMITER
+    );
+    assert!(
+        !token_id.contains(COLLECTION_DELIMITER),
+        "Token ID cannot contain '{}'", COLLECTION_DELIMITER
+    );
+    assert!(
+        !token_id.contains(WRAP_DELIMITER),
+        "Token ID cannot contain '{}'", WRAP_DELIMITER
+    );
+}
 
 #[near]
 impl ArtEcho {
+    /*
+        mint a token. Minters mint right away. If a registrar is set, anyone else is
+        checked for the Artist role in the registrar and the mint is completed or
+        refunded in the callback.
+    */
     #[payable]
     pub fn nft_mint(
         &mut self,
@@ -10,70 +73,359 @@ impl ArtEcho {
         receiver_id: AccountId,
         //we add an optional parameter for perpetual royalties
         perpetual_royalties: Option<HashMap<AccountId, u32>>,
-    ) {
+        //tokens that aren't transferable stay with the receiver until the minter burns them. Defaults to true
+        transferable: Option<bool>,
+    ) -> PromiseOrValue<bool> {
+        //make sure mints aren't stopped and the tokens aren't being migrated before spending gas on the registrar
+        self.assert_not_paused();
+        self.assert_migrated();
+        //make sure the token ID can't collide with the editions of a series
+        assert_valid_token_id(&token_id);
+        //make sure the token doesn't exist and the royalties are valid before spending gas on the registrar
+        assert!(
+            self.tokens_by_id.get(&token_id).is_none() && !self.pending_collabs.contains_key(&token_id),
+            "Token already exists"
+        );
+        if let Some(perpetual_royalties) = perpetual_royalties.as_ref() {
+            assert_valid_royalties(perpetual_royalties);
+        }
+        //make sure the media can be linked if it points to an upload
+        let mut metadata = metadata;
+        link_upload(&mut self.uploads, &mut metadata);
+
+        //get the caller of the function
+        let minter_id = env::predecessor_account_id();
+
+        //minters don't need to be checked
+        if self.minters.contains(&minter_id) {
+            //measure the initial storage being used on the contract
+            let initial_storage_usage = self.internal_storage_usage();
+
+            //mint the token and log it
+            let soulbound_issuer_id = (!transferable.unwrap_or(true)).then(|| minter_id.clone());
+            let token = self.internal_mint(token_id.clone(), metadata, receiver_id, perpetual_royalties, soulbound_issuer_id, minter_id.clone());
+            log_nft_mint(&token.owner_id, vec![token_id]);
+
+            //calculate the required storage which was the used - initial
+            let required_storage_in_bytes = self.internal_storage_usage() - initial_storage_usage;
+
+            //refund any excess storage if the user attached too much. Panic if they didn't attach enough to cover the required.
+            self.refund_deposit(required_storage_in_bytes.into());
+
+            return PromiseOrValue::Value(true);
+        }
+
+        //anyone else needs the Artist role in the registrar
+        let registrar_id = self.registrar_id.clone().expect("Unauthorized minter");
+
+        // Ask the registrar for the role and resolve the mint on our own contract
+        ext_registrar::ext(registrar_id)
+            .with_static_gas(GAS_FOR_HAS_ROLE)
+            .has_role(minter_id.clone(), ARTIST_ROLE.to_string())
+        .then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_MINT)
+                .nft_resolve_mint(
+                    minter_id,
+                    env::attached_deposit(),
+                    token_id,
+                    metadata,
+                    receiver_id,
+                    perpetual_royalties,
+                    transferable,
+                )
+        ).into()
+    }
+
+    //resolves the registrar check of nft_mint. Completes the mint if the minter is an artist, otherwise refunds the deposit.
+    //returns true if the token was minted
+    #[private]
+    pub fn nft_resolve_mint(
+        &mut self,
+        minter_id: AccountId,
+        deposit: NearToken,
+        token_id: TokenId,
+        metadata: TokenMetadata,
+        receiver_id: AccountId,
+        perpetual_royalties: Option<HashMap<AccountId, u32>>,
+        transferable: Option<bool>,
+    ) -> bool {
+        //the registrar has to have answered that the minter is an artist
+        let is_artist = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice::<bool>(&value).unwrap_or(false)
+            }
+            _ => false,
+        };
+
+        //the token could have been minted or reserved by a collaboration while we were waiting on the registrar
+        let token_exists = self.tokens_by_id.get(&token_id).is_some() || self.pending_collabs.contains_key(&token_id);
+
+        //the mint can't go through if the contract was paused or a migration started while we were waiting either
+        let is_migrating = self.legacy_tokens.is_some();
+
+        if !is_artist || token_exists || self.paused || is_migrating {
+            if !is_artist {
+                log!("@{} is not an artist, refunding the mint", minter_id);
+            } else if self.paused {
+                log!("Contract was paused, refunding the mint");
+            } else if is_migrating {
+                log!("Tokens are being migrated, refunding the mint");
+            } else {
+                log!("Token {} already exists, refunding the mint", token_id);
+            }
+            Promise::new(minter_id).transfer(deposit);
+            return false;
+        }
+
         //measure the initial storage being used on the contract
-        let initial_storage_usage = env::storage_usage();
-    
+        let initial_storage_usage = self.internal_storage_usage();
+
+        //mint the token. The minter is the issuer if the token isn't transferable
+        let soulbound_issuer_id = (!transferable.unwrap_or(true)).then(|| minter_id.clone());
+        let token = self.internal_mint(token_id.clone(), metadata, receiver_id, perpetual_royalties, soulbound_issuer_id, minter_id.clone());
+
+        //calculate the cost of the storage which was the used - initial
+        let required_storage_in_bytes = self.internal_storage_usage() - initial_storage_usage;
+        let mut required_cost = env::storage_byte_cost().saturating_mul(required_storage_in_bytes.into());
+
+        //the storage is debited from the minter's storage balance if it covers it
+        if self.internal_debit_storage(&minter_id, required_cost) {
+            required_cost = NearToken::from_yoctonear(0);
+        }
+
+        //if the deposit doesn't cover the storage, we undo the mint and refund the deposit
+        if required_cost > deposit {
+            self.internal_undo_mint(&token, &token_id);
+
+            log!("Must attach {} yoctoNEAR to cover storage, refunding the mint", required_cost);
+            Promise::new(minter_id).transfer(deposit);
+            return false;
+        }
+
+        //the mint is final, so we log it
+        log_nft_mint(&token.owner_id, vec![token_id]);
+
+        //refund the excess deposit
+        let refund = deposit.saturating_sub(required_cost);
+        if refund.gt(&ONE_YOCTONEAR) {
+            Promise::new(minter_id).transfer(refund);
+        }
+
+        true
+    }
+}
+
+impl ArtEcho {
+    //mints a token without logging it, so the caller can still undo the mint (internal method and can't be called directly via CLI).
+    pub(crate) fn internal_mint(
+        &mut self,
+        token_id: TokenId,
+        mut metadata: TokenMetadata,
+        receiver_id: AccountId,
+        perpetual_royalties: Option<HashMap<AccountId, u32>>,
+        //the issuer of the token if it can't be transferred
+        soulbound_issuer_id: Option<AccountId>,
+        //the account that created the token, which stays attributed to it after it's sold
+        creator_id: AccountId,
+    ) -> Token {
+        //make sure the tokens aren't still being migrated and mints aren't stopped
+        self.assert_migrated();
+        self.assert_not_paused();
+
         // create a royalty map to store in the token
         let mut royalty = HashMap::new();
-    
-        // if perpetual royalties were passed into the function: 
+
+        // if perpetual royalties were passed into the function:
         if let Some(perpetual_royalties) = perpetual_royalties {
-            //make sure that the length of the perpetual royalties is below 7 since we won't have enough GAS to pay out that many people
-            assert!(perpetual_royalties.len() < 7, "Cannot add more than 6 perpetual royalty amounts");
-    
+            //make sure the perpetual royalties can be payed out
+            assert_valid_royalties(&perpetual_royalties);
+
             //iterate through the perpetual royalties and insert the account and amount in the royalty map
             for (account, amount) in perpetual_royalties {
                 royalty.insert(account, amount);
             }
         }
-    
-        //specify the token struct that contains the owner ID 
+
+        //specify the token struct that contains the owner ID
         let token = Token {
             owner_id: receiver_id,
             //we set the approved account IDs to the default value (an empty map)
             approved_account_ids: Default::default(),
             //the next approval ID is set to 0
             next_approval_id: 0,
-            royalty
+            royalty,
+            //one-off tokens don't belong to a series
+            series_id: None,
         };
-    
-        //insert the token ID and token struct and make sure that the token doesn't exist
+
+        //insert the token ID and token struct and make sure that the token doesn't exist, not even as a pending collaboration
+        assert!(!self.pending_collabs.contains_key(&token_id), "Token already exists");
         assert!(
-            self.tokens_by_id.insert(&token_id, &token).is_none(),
+            self.tokens_by_id.insert(token_id.clone(), token.clone()).is_none(),
             "Token already exists"
         );
-    
-        //insert the token ID and metadata
-        self.token_metadata_by_id.insert(&token_id, &metadata);
-    
-        //call the internal method for adding the token to the owner
+
+        //insert the token ID and metadata, with the hash of the upload its media points to
+        link_upload(&mut self.uploads, &mut metadata);
+        self.token_metadata_by_id.insert(token_id.clone(), metadata);
+        self.sorted_token_ids.insert(token_id.clone(), ());
+
+        //keep track of the issuer if the token can't be transferred
+        if let Some(soulbound_issuer_id) = soulbound_issuer_id {
+            self.soulbound_tokens.insert(token_id.clone(), soulbound_issuer_id);
+        }
+
+        //call the internal methods for adding the token to the owner and the creator
         self.internal_add_token_to_owner(&token.owner_id, &token_id);
-    
-        // Construct the mint log as per the events standard.
-        let nft_mint_log: EventLog = EventLog {
-            // Standard name ("nep171").
-            standard: NFT_STANDARD_NAME.to_string(),
-            // Version of the standard ("nft-1.0.0").
-            version: NFT_METADATA_SPEC.to_string(),
-            // The data related with the event stored in a vector.
-            event: EventLogVariant::NftMint(vec![NftMintLog {
-                // Owner of the token.
-                owner_id: token.owner_id.to_string(),
-                // Vector of token IDs that were minted.
-                token_ids: vec![token_id.to_string()],
-                // An optional memo to include.
-                memo: None,
-            }]),
-        };
-    
-        // Log the serialized json.
-        env::log_str(&nft_mint_log.to_string());
-    
-        //calculate the required storage which was the used - initial
-        let required_storage_in_bytes = env::storage_usage() - initial_storage_usage;
-    
-        //refund any excess storage if the user attached too much. Panic if they didn't attach enough to cover the required.
-        refund_deposit(required_storage_in_bytes.into());
+        self.internal_add_token_to_creator(&creator_id, &token_id);
+
+        token
+    }
+
+    //removes a token that was minted by internal_mint but not logged yet (internal method and can't be called directly via CLI).
+    pub(crate) fn internal_undo_mint(&mut self, token: &Token, token_id: &TokenId) {
+        self.internal_remove_token_from_owner(&token.owner_id, token_id);
+        self.tokens_by_id.remove(token_id);
+        self.token_metadata_by_id.remove(token_id);
+        self.sorted_token_ids.remove(token_id);
+        self.soulbound_tokens.remove(token_id);
+        self.internal_remove_token_from_creator(token_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::collections;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::{testing_env, RuntimeFeesConfig};
+
+    const TOKEN_ID: &str = "piece";
+
+    //resolves a registrar check that answered `is_artist` for an nft_mint of alice with the given deposit
+    fn resolve_mint(contract: &mut ArtEcho, is_artist: bool, deposit: NearToken) -> bool {
+        testing_env!(
+            VMContextBuilder::new().current_account_id(account("art-echo.near")).build(),
+            near_sdk::test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(is_artist.to_string().into_bytes())],
+        );
+        contract.nft_resolve_mint(
+            account("alice.near"),
+            deposit,
+            TOKEN_ID.to_string(),
+            sample_metadata(),
+            account("alice.near"),
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn minters_mint_right_away() {
+        let mut contract = setup_contract();
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.add_minter(account("alice.near"));
+
+        set_context(account("alice.near"), NearToken::from_near(1));
+        contract.nft_mint(TOKEN_ID.to_string(), sample_metadata(), account("bob.near"), None, None);
+
+        assert_eq!(contract.nft_token(TOKEN_ID.to_string()).unwrap().owner_id, account("bob.near"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unauthorized minter")]
+    fn only_minters_mint_without_a_registrar() {
+        let mut contract = setup_contract();
+
+        set_context(account("alice.near"), NearToken::from_near(1));
+        contract.nft_mint(TOKEN_ID.to_string(), sample_metadata(), account("alice.near"), None, None);
+    }
+
+    #[test]
+    fn artist_mint_is_completed() {
+        let mut contract = setup_contract();
+
+        assert!(resolve_mint(&mut contract, true, NearToken::from_near(1)));
+        assert_eq!(contract.nft_token(TOKEN_ID.to_string()).unwrap().owner_id, account("alice.near"));
+        let refund = refunded_to(&account("alice.near"));
+        assert!(refund > NearToken::from_yoctonear(0) && refund < NearToken::from_near(1));
+    }
+
+    #[test]
+    fn non_artist_mint_is_refunded() {
+        let mut contract = setup_contract();
+
+        assert!(!resolve_mint(&mut contract, false, NearToken::from_near(1)));
+        assert!(contract.nft_token(TOKEN_ID.to_string()).is_none());
+        assert_eq!(refunded_to(&account("alice.near")), NearToken::from_near(1));
+    }
+
+    #[test]
+    fn mint_of_existing_token_is_refunded() {
+        let mut contract = setup_contract();
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint(TOKEN_ID.to_string(), sample_metadata(), account("bob.near"), None, None);
+
+        assert!(!resolve_mint(&mut contract, true, NearToken::from_near(1)));
+        assert_eq!(contract.nft_token(TOKEN_ID.to_string()).unwrap().owner_id, account("bob.near"));
+        assert_eq!(refunded_to(&account("alice.near")), NearToken::from_near(1));
+    }
+
+    #[test]
+    fn mint_of_token_pending_a_collaboration_is_refunded() {
+        let mut contract = setup_contract();
+        let creators = HashMap::from([(owner(), 500), (account("bob.near"), 500)]);
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint_collab(TOKEN_ID.to_string(), sample_metadata(), account("bob.near"), creators, 1000, None);
+
+        assert!(!resolve_mint(&mut contract, true, NearToken::from_near(1)));
+        assert!(contract.nft_token(TOKEN_ID.to_string()).is_none());
+        assert!(contract.nft_collab(TOKEN_ID.to_string()).is_some());
+        assert_eq!(refunded_to(&account("alice.near")), NearToken::from_near(1));
+    }
+
+    #[test]
+    fn mint_without_enough_deposit_is_undone() {
+        let mut contract = setup_contract();
+
+        assert!(!resolve_mint(&mut contract, true, ONE_YOCTONEAR));
+        assert!(contract.nft_token(TOKEN_ID.to_string()).is_none());
+        assert_eq!(contract.nft_total_supply(), U64(0));
+        assert!(contract.nft_tokens_for_owner(account("alice.near"), None, None).is_empty());
+        assert_eq!(refunded_to(&account("alice.near")), ONE_YOCTONEAR);
+    }
+
+    //marks the contract as still migrating tokens from the previous state
+    fn start_migration(contract: &mut ArtEcho) {
+        contract.legacy_tokens = Some(LegacyTokens {
+            tokens_by_id: collections::LookupMap::new(b"lt".to_vec()),
+            token_metadata_by_id: collections::UnorderedMap::new(b"lm".to_vec()),
+            tokens_per_owner: collections::LookupMap::new(b"lo".to_vec()),
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Tokens are still being migrated")]
+    fn registrar_isnt_asked_during_migration() {
+        let mut contract = setup_contract();
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.set_registrar(Some(account("registrar.near")));
+        start_migration(&mut contract);
+
+        set_context(account("alice.near"), NearToken::from_near(1));
+        contract.nft_mint(TOKEN_ID.to_string(), sample_metadata(), account("alice.near"), None, None);
+    }
+
+    #[test]
+    fn mint_during_migration_is_refunded() {
+        let mut contract = setup_contract();
+        start_migration(&mut contract);
+
+        assert!(!resolve_mint(&mut contract, true, NearToken::from_near(1)));
+        assert_eq!(refunded_to(&account("alice.near")), NearToken::from_near(1));
     }
 }
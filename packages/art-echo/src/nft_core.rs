@@ -31,6 +31,7 @@ pub trait NonFungibleTokenCore {
     fn nft_token(&self, token_id: TokenId) -> Option<JsonToken>;
 }
 
+#[allow(dead_code)]
 #[ext_contract(ext_non_fungible_token_receiver)]
 trait NonFungibleTokenReceiver {
     //Method stored on the receiver contract that is called via cross contract call when nft_transfer_call is called
@@ -44,8 +45,9 @@ trait NonFungibleTokenReceiver {
     ) -> Promise;
 }
 
+#[allow(dead_code)]
 #[ext_contract(ext_self)]
-trait NonFungibleTokenResolver {
+pub(crate) trait NonFungibleTokenResolver {
     /*
         resolves the promise of the cross contract call to the receiver contract
         this is stored on THIS contract and is meant to analyze what happened in the cross contract call when nft_on_transfer was called
@@ -159,10 +161,19 @@ impl NonFungibleTokenCore for ArtEcho {
 
     //get the information for a specific token ID
     fn nft_token(&self, token_id: TokenId) -> Option<JsonToken> {
-        //if there is some token ID in the tokens_by_id collection
-        if let Some(token) = self.tokens_by_id.get(&token_id) {
+        //if there is some token ID in the tokens_by_id collection, or in the previous state if it wasn't moved over yet
+        if let Some(token) = self.internal_token(&token_id) {
             //we'll get the metadata for that token
-            let metadata = self.token_metadata_by_id.get(&token_id).unwrap();
+            let metadata = self.internal_token_metadata(&token_id);
+            //check if the token can be transferred
+            let transferable = self.internal_soulbound_issuer(&token_id, &token).is_none();
+            //get the creator and whether the registrar verified them
+            let creator_id = self.token_creators.get(&token_id).cloned();
+            let creator_verified = creator_id.as_ref().is_some_and(|creator_id| self.verified_creators.contains(creator_id));
+            //get the collection the token was minted in
+            let collection_id = collection_of(&token_id);
+            //get the account the token is lent to
+            let lease = self.internal_active_lease(&token_id);
             //we return the JsonToken (wrapped by Some since we return an option)
             Some(JsonToken {
                 token_id,
@@ -170,6 +181,12 @@ impl NonFungibleTokenCore for ArtEcho {
                 metadata,
                 approved_account_ids: token.approved_account_ids,
                 royalty: token.royalty,
+                series_id: token.series_id,
+                transferable,
+                creator_id,
+                creator_verified,
+                collection_id,
+                lease,
             })
         } else { //if there wasn't a token ID in the tokens_by_id collection, we return None
             None
@@ -213,7 +230,7 @@ impl NonFungibleTokenResolver for ArtEcho {
         }
 
         //get the token object if there is some token object
-        let mut token = if let Some(token) = self.tokens_by_id.get(&token_id) {
+        let mut token = if let Some(token) = self.tokens_by_id.get(&token_id).cloned() {
             if token.owner_id != receiver_id {
                 //we refund the owner for releasing the storage used up by the approved account IDs
                 refund_approved_account_ids(owner_id, &approved_account_ids);
@@ -245,7 +262,7 @@ impl NonFungibleTokenResolver for ArtEcho {
         token.approved_account_ids = approved_account_ids;
 
         //we inset the token back into the tokens_by_id collection
-        self.tokens_by_id.insert(&token_id, &token);
+        self.tokens_by_id.insert(token_id.clone(), token);
 
         /*
             We need to log that the NFT was reverted back to the original owner.
@@ -279,3 +296,102 @@ impl NonFungibleTokenResolver for ArtEcho {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::get_logs;
+
+    const TOKEN_ID: &str = "badge";
+
+    //a contract with a badge minted by the owner to alice that can't be transferred
+    fn setup_soulbound_token() -> ArtEcho {
+        let mut contract = setup_contract();
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint(TOKEN_ID.to_string(), sample_metadata(), account("alice.near"), None, Some(false));
+        contract
+    }
+
+    #[test]
+    fn tokens_are_transferable_by_default() {
+        let mut contract = setup_contract();
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint(TOKEN_ID.to_string(), sample_metadata(), account("alice.near"), None, None);
+
+        assert!(contract.nft_token(TOKEN_ID.to_string()).unwrap().transferable);
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_transfer(account("bob.near"), TOKEN_ID.to_string(), None, None);
+        assert_eq!(contract.nft_token(TOKEN_ID.to_string()).unwrap().owner_id, account("bob.near"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Token is not transferable")]
+    fn soulbound_token_cannot_be_transferred() {
+        let mut contract = setup_soulbound_token();
+
+        assert!(!contract.nft_token(TOKEN_ID.to_string()).unwrap().transferable);
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_transfer(account("bob.near"), TOKEN_ID.to_string(), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Token is not transferable")]
+    fn soulbound_token_cannot_be_approved() {
+        let mut contract = setup_soulbound_token();
+
+        set_context(account("alice.near"), NearToken::from_millinear(10));
+        contract.nft_approve(TOKEN_ID.to_string(), account("market.near"), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Token is not transferable")]
+    fn soulbound_token_cannot_be_sold() {
+        let mut contract = setup_soulbound_token();
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_transfer_payout(account("bob.near"), TOKEN_ID.to_string(), None, None, U128(100), None);
+    }
+
+    #[test]
+    fn issuer_revokes_soulbound_token_by_burning() {
+        let mut contract = setup_soulbound_token();
+
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.nft_burn(TOKEN_ID.to_string(), None);
+
+        assert!(contract.nft_token(TOKEN_ID.to_string()).is_none());
+        assert!(contract.soulbound_tokens.get(TOKEN_ID).is_none());
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"nep171","version":"1.0.0","event":"nft_burn","data":[{"owner_id":"alice.near","authorized_id":"owner.near","token_ids":["badge"]}]}"#]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the issuer can burn a non-transferable token")]
+    fn owner_cannot_burn_soulbound_token() {
+        let mut contract = setup_soulbound_token();
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_burn(TOKEN_ID.to_string(), None);
+    }
+
+    #[test]
+    fn editions_of_soulbound_series_are_revoked_by_creator() {
+        let mut contract = setup_contract();
+        let mut series_metadata = sample_metadata();
+        series_metadata.copies = Some(10);
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_create_series("series".to_string(), series_metadata, None, None, Some(false));
+        let token_id = contract.nft_mint_series("series".to_string(), account("alice.near"));
+
+        assert!(!contract.nft_get_series("series".to_string()).unwrap().transferable);
+        assert!(!contract.nft_token(token_id.clone()).unwrap().transferable);
+
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.nft_burn(token_id.clone(), None);
+        assert!(contract.nft_token(token_id).is_none());
+        assert_eq!(contract.nft_series_supply("series".to_string()), U64(0));
+    }
+}
@@ -0,0 +1,153 @@
+use crate::*;
+
+#[near]
+impl ArtEcho {
+    //get the owner of the contract
+    pub fn get_owner_id(&self) -> AccountId {
+        self.owner_id.clone()
+    }
+
+    //allow an account to mint tokens and create series. Only the owner can add minters
+    #[payable]
+    pub fn add_minter(&mut self, account_id: AccountId) {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+        self.assert_owner();
+
+        //make sure the account isn't a minter already
+        assert!(!self.minters.contains(&account_id), "Already a minter");
+        self.minters.push(account_id);
+    }
+
+    //stop an account from minting tokens and creating series. Only the owner can remove minters
+    #[payable]
+    pub fn remove_minter(&mut self, account_id: AccountId) {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+        self.assert_owner();
+
+        //find the account in the list of minters and remove it
+        if let Some(index) = self.minters.iter().position(|minter_id| minter_id == &account_id) {
+            self.minters.swap_remove(index);
+        } else {
+            env::panic_str("Not a minter");
+        }
+    }
+
+    //get the accounts that are allowed to mint
+    pub fn get_minters(&self) -> Vec<AccountId> {
+        self.minters.clone()
+    }
+
+    //allow an account to freeze tokens. Only the owner can add moderators
+    #[payable]
+    pub fn add_moderator(&mut self, account_id: AccountId) {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+        self.assert_owner();
+
+        //make sure the account isn't a moderator already
+        assert!(!self.moderators.contains(&account_id), "Already a moderator");
+        self.moderators.push(account_id);
+    }
+
+    //stop an account from freezing tokens. Only the owner can remove moderators
+    #[payable]
+    pub fn remove_moderator(&mut self, account_id: AccountId) {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+        self.assert_owner();
+
+        //find the account in the list of moderators and remove it
+        if let Some(index) = self.moderators.iter().position(|moderator_id| moderator_id == &account_id) {
+            self.moderators.swap_remove(index);
+        } else {
+            env::panic_str("Not a moderator");
+        }
+    }
+
+    //get the accounts that are allowed to freeze tokens, on top of the owner
+    pub fn get_moderators(&self) -> Vec<AccountId> {
+        self.moderators.clone()
+    }
+
+    //allow the tokens of a NEP-171 contract to be wrapped by sending them to this contract with nft_transfer_call.
+    //Only the owner can add wrap contracts
+    #[payable]
+    pub fn add_wrap_contract(&mut self, contract_id: AccountId) {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+        self.assert_owner();
+
+        //make sure the contract can't be wrapped already
+        assert!(!self.wrap_contracts.contains(&contract_id), "Already a wrap contract");
+        self.wrap_contracts.push(contract_id);
+    }
+
+    //stop the tokens of a contract from being wrapped. Tokens that are already wrapped can still be released.
+    //Only the owner can remove wrap contracts
+    #[payable]
+    pub fn remove_wrap_contract(&mut self, contract_id: AccountId) {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+        self.assert_owner();
+
+        //find the contract in the list of wrap contracts and remove it
+        if let Some(index) = self.wrap_contracts.iter().position(|wrap_contract_id| wrap_contract_id == &contract_id) {
+            self.wrap_contracts.swap_remove(index);
+        } else {
+            env::panic_str("Not a wrap contract");
+        }
+    }
+
+    //get the NEP-171 contracts whose tokens can be wrapped
+    pub fn get_wrap_contracts(&self) -> Vec<AccountId> {
+        self.wrap_contracts.clone()
+    }
+
+    //set the art-spot registrar that is asked whether a caller who isn't a minter has the Artist role.
+    //Passing no registrar means only minters can mint
+    #[payable]
+    pub fn set_registrar(&mut self, registrar_id: Option<AccountId>) {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+        self.assert_owner();
+
+        self.registrar_id = registrar_id;
+    }
+
+    //get the art-spot registrar that is used to check the Artist role
+    pub fn get_registrar(&self) -> Option<AccountId> {
+        self.registrar_id.clone()
+    }
+
+    //set the SPOT contract whose tokens can be used to pay for tokens. Passing no contract means only NEAR is accepted
+    #[payable]
+    pub fn set_spot_token(&mut self, spot_token_id: Option<AccountId>) {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+        self.assert_owner();
+
+        self.spot_token_id = spot_token_id;
+    }
+
+    //get the SPOT contract whose tokens can be used to pay for tokens
+    pub fn get_spot_token(&self) -> Option<AccountId> {
+        self.spot_token_id.clone()
+    }
+
+    //set whether tokens whose metadata expired can still be transferred. Only the owner can change it
+    #[payable]
+    pub fn set_enforce_validity(&mut self, enforce_validity: bool) {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+        self.assert_owner();
+
+        self.enforce_validity = enforce_validity;
+    }
+
+    //get whether expired tokens are kept from being transferred
+    pub fn get_enforce_validity(&self) -> bool {
+        self.enforce_validity
+    }
+}
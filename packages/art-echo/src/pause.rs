@@ -0,0 +1,302 @@
+use crate::*;
+
+//logs the contract being paused or unpaused under the art-echo standard
+fn log_contract_pause(paused: bool, memo: Option<String>) {
+    let log = ContractPauseLog {
+        // The owner that paused or unpaused the contract.
+        owner_id: env::predecessor_account_id().to_string(),
+        // An optional memo to include.
+        memo,
+    };
+
+    // Construct the pause log.
+    let contract_pause_log: EventLog = EventLog {
+        // Standard name ("art_echo").
+        standard: ART_ECHO_STANDARD_NAME.to_string(),
+        // Version of the events ("1.0.0").
+        version: ART_ECHO_EVENTS_VERSION.to_string(),
+        // The data related with the event stored in a vector.
+        event: if paused {
+            EventLogVariant::ContractPause(vec![log])
+        } else {
+            EventLogVariant::ContractUnpause(vec![log])
+        },
+    };
+
+    // Log the serialized json.
+    env::log_str(&contract_pause_log.to_string());
+}
+
+//logs tokens being frozen or unfrozen under the art-echo standard
+fn log_nft_freeze(frozen: bool, token_ids: Vec<TokenId>, memo: Option<String>) {
+    let log = NftFreezeLog {
+        // The moderator that froze or unfroze the tokens.
+        moderator_id: env::predecessor_account_id().to_string(),
+        // Vector of token IDs that were frozen or unfrozen.
+        token_ids,
+        // An optional memo to include.
+        memo,
+    };
+
+    // Construct the freeze log.
+    let nft_freeze_log: EventLog = EventLog {
+        // Standard name ("art_echo").
+        standard: ART_ECHO_STANDARD_NAME.to_string(),
+        // Version of the events ("1.0.0").
+        version: ART_ECHO_EVENTS_VERSION.to_string(),
+        // The data related with the event stored in a vector.
+        event: if frozen {
+            EventLogVariant::NftFreeze(vec![log])
+        } else {
+            EventLogVariant::NftUnfreeze(vec![log])
+        },
+    };
+
+    // Log the serialized json.
+    env::log_str(&nft_freeze_log.to_string());
+}
+
+#[near]
+impl ArtEcho {
+    //stop all transfers, approvals and mints until the contract is unpaused. Only the owner can pause the contract
+    #[payable]
+    pub fn nft_pause(&mut self, memo: Option<String>) {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+        self.assert_owner();
+        assert!(!self.paused, "Contract is already paused");
+
+        self.paused = true;
+        log_contract_pause(true, memo);
+    }
+
+    //allow transfers, approvals and mints again. Only the owner can unpause the contract
+    #[payable]
+    pub fn nft_unpause(&mut self, memo: Option<String>) {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+        self.assert_owner();
+        assert!(self.paused, "Contract is not paused");
+
+        self.paused = false;
+        log_contract_pause(false, memo);
+    }
+
+    //check if transfers, approvals and mints are stopped
+    pub fn nft_is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /*
+        stop tokens from being transferred, approved or burned, for example while a work is reported stolen or disputed.
+        The moderator pays for the storage of the freeze and is refunded it once the token is unfrozen. Only moderators can freeze tokens
+    */
+    #[payable]
+    pub fn nft_freeze_tokens(&mut self, token_ids: Vec<TokenId>, memo: Option<String>) {
+        //assert at least one yocto for security reasons and to pay for storage
+        assert_at_least_one_yocto();
+        self.assert_moderator();
+
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        //keep track of the moderator so they get the storage back when the token is unfrozen
+        let moderator_id = env::predecessor_account_id();
+        for token_id in token_ids.iter() {
+            assert!(self.tokens_by_id.contains_key(token_id), "No token");
+            assert!(
+                self.transfer_frozen_tokens.insert(token_id.clone(), moderator_id.clone()).is_none(),
+                "Token is already frozen"
+            );
+        }
+        log_nft_freeze(true, token_ids, memo);
+
+        //charge the caller for the storage of the freeze
+        self.refund_storage_difference(initial_storage_usage);
+    }
+
+    //allow frozen tokens to be transferred again. The moderators that froze the tokens are refunded the released storage.
+    //Only moderators can unfreeze tokens
+    #[payable]
+    pub fn nft_unfreeze_tokens(&mut self, token_ids: Vec<TokenId>, memo: Option<String>) {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+        self.assert_moderator();
+
+        //add up the storage released by every token for the moderator that paid for its freeze
+        let mut refunds: HashMap<AccountId, NearToken> = HashMap::new();
+        for token_id in token_ids.iter() {
+            //measure the initial storage being used on the contract
+            let initial_storage_usage = self.internal_storage_usage();
+
+            let moderator_id = self.transfer_frozen_tokens.remove(token_id).expect("Token is not frozen");
+
+            //calculate the storage that was released by unfreezing the token
+            let released_storage_in_bytes = initial_storage_usage.saturating_sub(self.internal_storage_usage());
+            let released = env::storage_byte_cost().saturating_mul(released_storage_in_bytes.into());
+            let refund = refunds.entry(moderator_id).or_insert(NearToken::from_yoctonear(0));
+            *refund = refund.saturating_add(released);
+        }
+        log_nft_freeze(false, token_ids, memo);
+
+        //refund the moderators that paid for the freezes
+        for (moderator_id, refund) in refunds {
+            if refund.gt(&NearToken::from_yoctonear(0)) {
+                Promise::new(moderator_id).transfer(refund);
+            }
+        }
+    }
+
+    //check if a token was frozen by a moderator
+    pub fn nft_is_token_frozen(&self, token_id: TokenId) -> bool {
+        self.transfer_frozen_tokens.contains_key(&token_id)
+    }
+
+    //Query for the tokens that are frozen by a moderator
+    pub fn nft_frozen_tokens(&self, from_index: Option<U128>, limit: Option<u32>) -> Vec<TokenId> {
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        //iterate through the frozen tokens
+        self.transfer_frozen_tokens.keys()
+            //skip to the index we specified in the start variable
+            .skip(start as usize)
+            //take the first "limit" elements in the vector, capped so the view can't run out of gas
+            .take(page_limit(limit))
+            .cloned()
+            .collect()
+    }
+}
+
+impl ArtEcho {
+    //make sure transfers, approvals and mints aren't stopped
+    pub(crate) fn assert_not_paused(&self) {
+        assert!(!self.paused, "Contract is paused");
+    }
+
+    //make sure the caller is the owner or a moderator
+    pub(crate) fn assert_moderator(&self) {
+        let predecessor_account_id = env::predecessor_account_id();
+        assert!(
+            predecessor_account_id == self.owner_id || self.moderators.contains(&predecessor_account_id),
+            "Unauthorized moderator"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::get_logs;
+
+    const TOKEN_ID: &str = "piece";
+
+    //a contract with a token owned by alice and moderated by mod.near
+    fn setup_moderated_contract() -> ArtEcho {
+        let mut contract = setup_contract();
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint(TOKEN_ID.to_string(), sample_metadata(), account("alice.near"), None, None);
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.add_moderator(account("mod.near"));
+        contract
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn pause_stops_transfers() {
+        let mut contract = setup_moderated_contract();
+
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.nft_pause(Some("incident".to_string()));
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"art_echo","version":"1.0.0","event":"contract_pause","data":[{"owner_id":"owner.near","memo":"incident"}]}"#]
+        );
+        assert!(contract.nft_metadata().paused);
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_transfer(account("bob.near"), TOKEN_ID.to_string(), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn pause_stops_mints() {
+        let mut contract = setup_moderated_contract();
+
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.nft_pause(None);
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint("another".to_string(), sample_metadata(), account("alice.near"), None, None);
+    }
+
+    #[test]
+    fn unpause_allows_transfers_again() {
+        let mut contract = setup_moderated_contract();
+
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.nft_pause(None);
+        contract.nft_unpause(None);
+        assert!(!contract.nft_metadata().paused);
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_transfer(account("bob.near"), TOKEN_ID.to_string(), None, None);
+        assert_eq!(contract.nft_token(TOKEN_ID.to_string()).unwrap().owner_id, account("bob.near"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Token is frozen")]
+    fn frozen_token_cannot_be_approved() {
+        let mut contract = setup_moderated_contract();
+
+        set_context(account("mod.near"), NearToken::from_millinear(10));
+        contract.nft_freeze_tokens(vec![TOKEN_ID.to_string()], Some("reported stolen".to_string()));
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"art_echo","version":"1.0.0","event":"nft_freeze","data":[{"moderator_id":"mod.near","token_ids":["piece"],"memo":"reported stolen"}]}"#]
+        );
+        assert_eq!(contract.nft_frozen_tokens(None, None), vec![TOKEN_ID.to_string()]);
+
+        set_context(account("alice.near"), NearToken::from_millinear(10));
+        contract.nft_approve(TOKEN_ID.to_string(), account("market.near"), None);
+    }
+
+    #[test]
+    fn unfrozen_token_can_be_transferred() {
+        let mut contract = setup_moderated_contract();
+
+        set_context(account("mod.near"), NearToken::from_millinear(10));
+        contract.nft_freeze_tokens(vec![TOKEN_ID.to_string()], None);
+        set_context(account("mod.near"), ONE_YOCTONEAR);
+        contract.nft_unfreeze_tokens(vec![TOKEN_ID.to_string()], None);
+        assert!(!contract.nft_is_token_frozen(TOKEN_ID.to_string()));
+        assert!(refunded_to(&account("mod.near")) > NearToken::from_yoctonear(0));
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_transfer(account("bob.near"), TOKEN_ID.to_string(), None, None);
+    }
+
+    #[test]
+    fn unfreeze_refunds_the_moderator_that_froze() {
+        let mut contract = setup_moderated_contract();
+
+        set_context(account("mod.near"), NearToken::from_millinear(10));
+        contract.nft_freeze_tokens(vec![TOKEN_ID.to_string()], None);
+        let freeze_cost = NearToken::from_millinear(10).saturating_sub(refunded_to(&account("mod.near")));
+
+        //another moderator unfreezes the token but the storage goes back to mod.near
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.nft_unfreeze_tokens(vec![TOKEN_ID.to_string()], None);
+        assert_eq!(refunded_to(&account("mod.near")), freeze_cost);
+        assert_eq!(refunded_to(&owner()), NearToken::from_yoctonear(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unauthorized moderator")]
+    fn only_moderators_can_freeze() {
+        let mut contract = setup_moderated_contract();
+
+        set_context(account("alice.near"), NearToken::from_millinear(10));
+        contract.nft_freeze_tokens(vec![TOKEN_ID.to_string()], None);
+    }
+}
@@ -0,0 +1,467 @@
+use crate::*;
+use near_sdk::log;
+use std::mem::size_of;
+
+//the longest an account ID can be
+pub(crate) const MAX_ACCOUNT_ID_LEN: u128 = 64;
+
+//the fee a renter pays the owner for a lease
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug, NearSchema,
+)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum RentalPrice {
+    //paid by attaching it to nft_rent
+    Near(NearToken),
+    //paid through ft_transfer_call on the SPOT contract
+    Spot(U128),
+}
+
+//the terms an owner lists a token for rent on
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, NearSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct RentalListing {
+    //the fee for a lease
+    pub price: RentalPrice,
+    //how long a lease lasts, in milliseconds
+    pub duration_ms: u64,
+}
+
+//the account a token is lent to and until when
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, NearSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct Lease {
+    //the account that has the display rights of the token
+    pub user_id: AccountId,
+    //when the lease ends, in milliseconds since the Unix epoch
+    pub expires_at: u64,
+}
+
+//calculate how many bytes the rental listing and the lease of a token can take up at most.
+//the owner pays for both up front, so renters paying in SPOT don't need to attach NEAR for the lease
+pub(crate) fn bytes_for_rental(token_id: &TokenId) -> u128 {
+    //both records are keyed by the storage prefix and the token ID with its length, and every record takes 40 bytes more
+    let record = 40 + 1 + 4 + token_id.len() as u128;
+    //the listing is the price with its variant and the duration
+    let listing = 1 + size_of::<u128>() as u128 + size_of::<u64>() as u128;
+    //the lease is the user ID with its length and the expiry, sized for the longest account ID
+    let lease = 4 + MAX_ACCOUNT_ID_LEN + size_of::<u64>() as u128;
+    2 * record + listing + lease
+}
+
+#[near]
+impl ArtEcho {
+    /*
+        list a token for rent. Anyone can then rent it for `duration_ms` by paying the price, which gives them
+        the display rights until the lease expires. The owner attaches the storage of the listing and the lease,
+        which is refunded once neither is left. Only the owner can list a token
+    */
+    #[payable]
+    pub fn nft_list_rental(&mut self, token_id: TokenId, price: RentalPrice, duration_ms: u64) {
+        //assert at least one yocto for security reasons and to pay for storage
+        assert_at_least_one_yocto();
+        self.assert_token_owner(&token_id);
+        self.assert_rentable(&token_id);
+        assert!(duration_ms > 0, "Duration must be greater than 0");
+
+        //the owner only pays for storage if the token isn't listed or lent yet
+        let storage_used = if self.internal_has_rental(&token_id) {
+            0
+        } else {
+            bytes_for_rental(&token_id)
+        };
+        self.rental_listings
+            .insert(token_id, RentalListing { price, duration_ms });
+
+        //refund any excess storage attached by the owner. If they didn't attach enough, panic.
+        self.refund_deposit(storage_used);
+    }
+
+    //stop renting out a token. A running lease lasts until it expires. Only the owner can remove the listing
+    #[payable]
+    pub fn nft_unlist_rental(&mut self, token_id: TokenId) {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+        let owner_id = self.assert_token_owner(&token_id);
+
+        assert!(
+            self.rental_listings.remove(&token_id).is_some(),
+            "Token is not listed for rent"
+        );
+        self.internal_refund_rental(&owner_id, &token_id);
+    }
+
+    /*
+        lend a token to `user_id` until `expires_at` without a fee, which gives them the display rights.
+        A lease can't be replaced until it expired. Only the owner can lend a token
+    */
+    #[payable]
+    pub fn nft_set_user(&mut self, token_id: TokenId, user_id: AccountId, expires_at: u64) {
+        //assert at least one yocto for security reasons and to pay for storage
+        assert_at_least_one_yocto();
+        self.assert_token_owner(&token_id);
+        self.assert_rentable(&token_id);
+        assert!(
+            expires_at > env::block_timestamp_ms(),
+            "Lease must expire in the future"
+        );
+        assert!(
+            self.nft_user_of(token_id.clone()).is_none(),
+            "Token is already lent"
+        );
+
+        //the owner only pays for storage if the token isn't listed or lent yet
+        let storage_used = if self.internal_has_rental(&token_id) {
+            0
+        } else {
+            bytes_for_rental(&token_id)
+        };
+        log!(
+            "Token {} is lent to @{} until {}",
+            token_id,
+            user_id,
+            expires_at
+        );
+        self.leases.insert(
+            token_id,
+            Lease {
+                user_id,
+                expires_at,
+            },
+        );
+
+        //refund any excess storage attached by the owner. If they didn't attach enough, panic.
+        self.refund_deposit(storage_used);
+    }
+
+    //end the lease of a token. The user can end it at any time and anyone can clear it once it expired
+    pub fn nft_clear_user(&mut self, token_id: TokenId) {
+        let lease = self.leases.get(&token_id).expect("Token is not lent");
+        assert!(
+            lease.user_id == env::predecessor_account_id()
+                || lease.expires_at <= env::block_timestamp_ms(),
+            "Lease hasn't expired"
+        );
+
+        //the owner gets the storage back if the token isn't listed anymore
+        self.leases.remove(&token_id);
+        let owner_id = self
+            .tokens_by_id
+            .get(&token_id)
+            .expect("No token")
+            .owner_id
+            .clone();
+        self.internal_refund_rental(&owner_id, &token_id);
+    }
+
+    //rent a token that is listed for rent in NEAR by attaching the price. The excess deposit is refunded
+    #[payable]
+    pub fn nft_rent(&mut self, token_id: TokenId) {
+        let (owner_id, price) = self.internal_rent(&token_id, env::predecessor_account_id());
+        let price = match price {
+            RentalPrice::Near(price) => Some(price),
+            RentalPrice::Spot(_) => None,
+        }
+        .expect("Rental is priced in SPOT, use ft_transfer_call");
+
+        //the owner already paid for the storage of the lease, so the renter only pays the price
+        self.refund_deposit_with_price(0, price);
+        if price.gt(&NearToken::from_yoctonear(0)) {
+            Promise::new(owner_id).transfer(price);
+        }
+    }
+
+    //get the account that has the display rights of a token, if it's lent and the lease didn't expire
+    pub fn nft_user_of(&self, token_id: TokenId) -> Option<AccountId> {
+        self.internal_active_lease(&token_id)
+            .map(|lease| lease.user_id)
+    }
+
+    //get the terms a token is listed for rent on
+    pub fn nft_rental_listing(&self, token_id: TokenId) -> Option<RentalListing> {
+        self.rental_listings.get(&token_id).cloned()
+    }
+}
+
+impl ArtEcho {
+    //make sure the caller owns the token and get them back
+    fn assert_token_owner(&self, token_id: &TokenId) -> AccountId {
+        let owner_id = self
+            .tokens_by_id
+            .get(token_id)
+            .expect("No token")
+            .owner_id
+            .clone();
+        assert_eq!(
+            env::predecessor_account_id(),
+            owner_id,
+            "Predecessor must be the token owner."
+        );
+        owner_id
+    }
+
+    //make sure the contract isn't paused and the token isn't frozen, since a lease hands out the display rights
+    fn assert_rentable(&self, token_id: &TokenId) {
+        self.assert_not_paused();
+        assert!(
+            !self.transfer_frozen_tokens.contains_key(token_id),
+            "Token is frozen"
+        );
+    }
+
+    //check if a token has a rental listing or a lease the owner paid the storage for
+    fn internal_has_rental(&self, token_id: &TokenId) -> bool {
+        self.rental_listings.contains_key(token_id) || self.leases.contains_key(token_id)
+    }
+
+    //get the lease of a token if it didn't expire
+    pub(crate) fn internal_active_lease(&self, token_id: &TokenId) -> Option<Lease> {
+        self.leases
+            .get(token_id)
+            .filter(|lease| lease.expires_at > env::block_timestamp_ms())
+            .cloned()
+    }
+
+    //lend a listed token to the renter for the duration of the listing and get the owner and the price back
+    pub(crate) fn internal_rent(
+        &mut self,
+        token_id: &TokenId,
+        renter_id: AccountId,
+    ) -> (AccountId, RentalPrice) {
+        self.assert_rentable(token_id);
+        let listing = self
+            .rental_listings
+            .get(token_id)
+            .cloned()
+            .expect("Token is not listed for rent");
+        let owner_id = self
+            .tokens_by_id
+            .get(token_id)
+            .expect("No token")
+            .owner_id
+            .clone();
+        assert_ne!(renter_id, owner_id, "Cannot rent your own token");
+        assert!(
+            self.internal_active_lease(token_id).is_none(),
+            "Token is already lent"
+        );
+
+        let expires_at = env::block_timestamp_ms() + listing.duration_ms;
+        log!(
+            "Token {} is rented to @{} until {}",
+            token_id,
+            renter_id,
+            expires_at
+        );
+        self.leases.insert(
+            token_id.clone(),
+            Lease {
+                user_id: renter_id,
+                expires_at,
+            },
+        );
+
+        (owner_id, listing.price)
+    }
+
+    //remove the listing and the lease of a token that changes hands or is burned.
+    //returns the storage the owner paid for them, which is released
+    pub(crate) fn internal_remove_rental(&mut self, token_id: &TokenId) -> u128 {
+        let had_listing = self.rental_listings.remove(token_id).is_some();
+        let had_lease = self.leases.remove(token_id).is_some();
+        if had_listing || had_lease {
+            bytes_for_rental(token_id)
+        } else {
+            0
+        }
+    }
+
+    //refund the owner the storage of the listing and the lease once the token has neither.
+    //an expired lease is cleared so the storage can be refunded
+    fn internal_refund_rental(&mut self, owner_id: &AccountId, token_id: &TokenId) {
+        if self.internal_active_lease(token_id).is_none() {
+            self.leases.remove(token_id);
+        }
+        if !self.internal_has_rental(token_id) {
+            Promise::new(owner_id.clone())
+                .transfer(env::storage_byte_cost().saturating_mul(bytes_for_rental(token_id)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    const TOKEN_ID: &str = "piece";
+
+    //a contract with a token owned by alice that is listed for rent for 1 NEAR a day
+    fn setup_rental() -> ArtEcho {
+        let mut contract = setup_contract();
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint(
+            TOKEN_ID.to_string(),
+            sample_metadata(),
+            account("alice.near"),
+            None,
+            None,
+        );
+
+        set_context(account("alice.near"), NearToken::from_millinear(10));
+        contract.nft_list_rental(
+            TOKEN_ID.to_string(),
+            RentalPrice::Near(NearToken::from_near(1)),
+            86_400_000,
+        );
+        contract
+    }
+
+    #[test]
+    fn renter_gets_display_rights_until_the_lease_expires() {
+        let mut contract = setup_rental();
+
+        set_context_at(account("gallery.near"), NearToken::from_near(2), 1000);
+        contract.nft_rent(TOKEN_ID.to_string());
+        assert_eq!(
+            contract.nft_user_of(TOKEN_ID.to_string()),
+            Some(account("gallery.near"))
+        );
+        assert_eq!(refunded_to(&account("alice.near")), NearToken::from_near(1));
+        assert_eq!(
+            refunded_to(&account("gallery.near")),
+            NearToken::from_near(1)
+        );
+
+        let lease = contract
+            .nft_token(TOKEN_ID.to_string())
+            .unwrap()
+            .lease
+            .unwrap();
+        assert_eq!(lease.expires_at, 86_401_000);
+
+        //the owner keeps the token
+        assert_eq!(
+            contract.nft_token(TOKEN_ID.to_string()).unwrap().owner_id,
+            account("alice.near")
+        );
+
+        set_context_at(
+            account("dave.near"),
+            NearToken::from_yoctonear(0),
+            86_401_000,
+        );
+        assert!(contract.nft_user_of(TOKEN_ID.to_string()).is_none());
+        assert!(contract
+            .nft_token(TOKEN_ID.to_string())
+            .unwrap()
+            .lease
+            .is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Token is already lent")]
+    fn cannot_rent_a_lent_token() {
+        let mut contract = setup_rental();
+
+        set_context(account("gallery.near"), NearToken::from_near(1));
+        contract.nft_rent(TOKEN_ID.to_string());
+        set_context(account("dave.near"), NearToken::from_near(1));
+        contract.nft_rent(TOKEN_ID.to_string());
+    }
+
+    #[test]
+    fn transfer_ends_the_lease_and_the_listing() {
+        let mut contract = setup_rental();
+        set_context(account("gallery.near"), NearToken::from_near(1));
+        contract.nft_rent(TOKEN_ID.to_string());
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_transfer(account("bob.near"), TOKEN_ID.to_string(), None, None);
+
+        assert!(contract.nft_user_of(TOKEN_ID.to_string()).is_none());
+        assert!(contract.nft_rental_listing(TOKEN_ID.to_string()).is_none());
+        assert_eq!(
+            refunded_to(&account("alice.near")),
+            env::storage_byte_cost().saturating_mul(bytes_for_rental(&TOKEN_ID.to_string()))
+        );
+    }
+
+    #[test]
+    fn storage_is_refunded_once_the_listing_and_the_lease_are_gone() {
+        let mut contract = setup_rental();
+        set_context_at(account("gallery.near"), NearToken::from_near(1), 0);
+        contract.nft_rent(TOKEN_ID.to_string());
+
+        //the lease is still running, so the owner doesn't get the storage back yet
+        set_context_at(account("alice.near"), ONE_YOCTONEAR, 1000);
+        contract.nft_unlist_rental(TOKEN_ID.to_string());
+        assert_eq!(
+            refunded_to(&account("alice.near")),
+            NearToken::from_yoctonear(0)
+        );
+
+        set_context_at(
+            account("dave.near"),
+            NearToken::from_yoctonear(0),
+            86_400_000,
+        );
+        contract.nft_clear_user(TOKEN_ID.to_string());
+        assert_eq!(
+            refunded_to(&account("alice.near")),
+            env::storage_byte_cost().saturating_mul(bytes_for_rental(&TOKEN_ID.to_string()))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Rental is priced in SPOT, use ft_transfer_call")]
+    fn spot_rental_cannot_be_paid_in_near() {
+        let mut contract = setup_rental();
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_list_rental(TOKEN_ID.to_string(), RentalPrice::Spot(U128(100)), 1000);
+
+        set_context(account("gallery.near"), NearToken::from_near(1));
+        contract.nft_rent(TOKEN_ID.to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn paused_contract_blocks_rentals() {
+        let mut contract = setup_rental();
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.nft_pause(None);
+
+        set_context(account("gallery.near"), NearToken::from_near(1));
+        contract.nft_rent(TOKEN_ID.to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Token is frozen")]
+    fn frozen_token_cannot_be_rented() {
+        let mut contract = setup_rental();
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.add_moderator(account("mod.near"));
+        set_context(account("mod.near"), NearToken::from_millinear(10));
+        contract.nft_freeze_tokens(vec![TOKEN_ID.to_string()], None);
+
+        set_context(account("gallery.near"), NearToken::from_near(1));
+        contract.nft_rent(TOKEN_ID.to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Token is frozen")]
+    fn frozen_token_cannot_be_lent() {
+        let mut contract = setup_rental();
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.add_moderator(account("mod.near"));
+        set_context(account("mod.near"), NearToken::from_millinear(10));
+        contract.nft_freeze_tokens(vec![TOKEN_ID.to_string()], None);
+
+        set_context(account("alice.near"), NearToken::from_millinear(10));
+        contract.nft_set_user(TOKEN_ID.to_string(), account("gallery.near"), 1000);
+    }
+}
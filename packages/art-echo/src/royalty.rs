@@ -1,67 +1,91 @@
 use crate::*;
 
-pub trait NonFungibleTokenCore {
+//the royalties are expressed in basis points, so all the royalties together can't be more than 100%
+pub const MAX_TOTAL_ROYALTY: u32 = 10000;
+
+pub trait NonFungibleTokenRoyalty {
     //calculates the payout for a token given the passed in balance. This is a view method
-  	fn nft_payout(&self, token_id: TokenId, balance: NearToken, max_len_payout: u32) -> Payout;
-    
-    //transfers the token to the receiver ID and returns the payout object that should be payed given the passed in balance. 
+  	fn nft_payout(&self, token_id: TokenId, balance: U128, max_len_payout: Option<u32>) -> Payout;
+
+    //transfers the token to the receiver ID and returns the payout object that should be payed given the passed in balance.
     fn nft_transfer_payout(
         &mut self,
         receiver_id: AccountId,
         token_id: TokenId,
-        approval_id: u32,
+        approval_id: Option<u32>,
         memo: Option<String>,
-        balance: NearToken,
-        max_len_payout: u32,
+        balance: U128,
+        max_len_payout: Option<u32>,
     ) -> Payout;
 }
 
+//make sure the perpetual royalties can be payed out. Panics if there are too many or they add up to more than 100%
+pub(crate) fn assert_valid_royalties(perpetual_royalties: &HashMap<AccountId, u32>) {
+    //make sure that the length of the perpetual royalties is below 7 since we won't have enough GAS to pay out that many people
+    assert!(perpetual_royalties.len() < 7, "Cannot add more than 6 perpetual royalty amounts");
+
+    //make sure the royalties don't add up to more than 100%
+    let total = perpetual_royalties
+        .values()
+        .try_fold(0u32, |total, amount| total.checked_add(*amount))
+        .filter(|total| *total <= MAX_TOTAL_ROYALTY);
+    assert!(
+        total.is_some(),
+        "Perpetual royalties cannot add up to more than {} basis points", MAX_TOTAL_ROYALTY
+    );
+}
+
+//calculates the payout of a balance between the royalties and the owner.
+//every royalty is rounded down and the owner gets the rest, so the payout always adds up to exactly the balance
+pub(crate) fn compute_payout(
+    owner_id: &AccountId,
+    royalty: &HashMap<AccountId, u32>,
+    balance: u128,
+    max_len_payout: Option<u32>,
+) -> Payout {
+    //keep track of the payout object to send back
+    let mut payout_object = Payout {
+        payout: HashMap::new(),
+    };
+    //keep track of how much of the balance is left for the owner
+    let mut owner_payout = balance;
+
+    //go through each key and value in the royalty object
+    for (account_id, royalty_percentage) in royalty.iter() {
+        //only insert into the payout if the key isn't the token owner (we add their payout at the end)
+        if account_id != owner_id {
+            let amount = royalty_to_payout(*royalty_percentage, balance);
+            owner_payout = owner_payout
+                .checked_sub(amount.0)
+                .unwrap_or_else(|| env::panic_str("Royalties add up to more than the balance"));
+            payout_object.payout.insert(account_id.clone(), amount);
+        }
+    }
+
+    // payout to previous owner who gets the rest of the balance
+    payout_object.payout.insert(owner_id.clone(), U128(owner_payout));
+
+    //make sure we're not paying out to too many people (GAS limits this)
+    if let Some(max_len_payout) = max_len_payout {
+        assert!(
+            payout_object.payout.len() as u32 <= max_len_payout,
+            "Market cannot payout to that many receivers"
+        );
+    }
+
+    //return the payout object
+    payout_object
+}
+
 #[near_bindgen]
-impl NonFungibleTokenCore for ArtEcho {
+impl NonFungibleTokenRoyalty for ArtEcho {
   //calculates the payout for a token given the passed in balance. This is a view method
-  fn nft_payout(&self, token_id: TokenId, balance: NearToken, max_len_payout: u32) -> Payout {
+  fn nft_payout(&self, token_id: TokenId, balance: U128, max_len_payout: Option<u32>) -> Payout {
       //get the token object
       let token = self.tokens_by_id.get(&token_id).expect("No token");
 
-      //get the owner of the token
-      let owner_id = token.owner_id;
-      //keep track of the total perpetual royalties
-      let mut total_perpetual = 0;
-      //keep track of the payout object to send back
-      let mut payout_object = Payout {
-          payout: HashMap::new(),
-      };
-      //get the royalty object from token
-      let royalty = token.royalty;
-
-      //make sure we're not paying out to too many people (GAS limits this)
-      assert!(
-          royalty.len() as u32 <= max_len_payout,
-          "Market cannot payout to that many receivers"
-      );
-
-      //go through each key and value in the royalty object
-      for (k, v) in royalty.iter() {
-          //get the key
-          let key = k.clone();
-
-          //only insert into the payout if the key isn't the token owner (we add their payout at the end)
-          if key != owner_id {
-              payout_object
-                  .payout
-                  .insert(key, royalty_to_payout(*v as u128, balance));
-              total_perpetual += *v;
-          }
-      }
-
-      // payout to previous owner who gets 100% - total perpetual royalties
-      payout_object.payout.insert(
-          owner_id,
-          royalty_to_payout((10000 - total_perpetual).into(), balance),
-      );
-
-      //return the payout object
-      payout_object
+      //calculate the payout between the royalties and the owner
+      compute_payout(&token.owner_id, &token.royalty, balance.0, max_len_payout)
   }
 
   //transfers the token to the receiver ID and returns the payout object that should be payed given the passed in balance.
@@ -70,10 +94,10 @@ impl NonFungibleTokenCore for ArtEcho {
       &mut self,
       receiver_id: AccountId,
       token_id: TokenId,
-      approval_id: u32,
+      approval_id: Option<u32>,
       memo: Option<String>,
-      balance: NearToken,
-      max_len_payout: u32,
+      balance: U128,
+      max_len_payout: Option<u32>,
   ) -> Payout {
       //assert that the user attached 1 yocto NEAR for security reasons
       assert_one_yocto();
@@ -81,7 +105,7 @@ impl NonFungibleTokenCore for ArtEcho {
       let sender_id = env::predecessor_account_id();
       //transfer the token to the passed in receiver and get the previous token object back
       let previous_token =
-          self.internal_transfer(&sender_id, &receiver_id, &token_id, Some(approval_id), memo);
+          self.internal_transfer(&sender_id, &receiver_id, &token_id, approval_id, memo);
 
       //refund the previous token owner for the storage used up by the previous approved account IDs
       refund_approved_account_ids(
@@ -89,44 +113,111 @@ impl NonFungibleTokenCore for ArtEcho {
           &previous_token.approved_account_ids,
       );
 
-      //get the owner of the token
-      let owner_id = previous_token.owner_id;
-      //keep track of the total perpetual royalties
-      let mut total_perpetual = 0;
-      //keep track of the payout object to send back
-      let mut payout_object = Payout {
-          payout: HashMap::new(),
-      };
-      //get the royalty object from token
-      let royalty = previous_token.royalty;
-
-      //make sure we're not paying out to too many people (GAS limits this)
-      assert!(
-          royalty.len() as u32 <= max_len_payout,
-          "Market cannot payout to that many receivers"
-      );
-
-      //go through each key and value in the royalty object
-      for (k, v) in royalty.iter() {
-          //get the key
-          let key = k.clone();
-
-          //only insert into the payout if the key isn't the token owner (we add their payout at the end)
-          if key != owner_id {
-              payout_object
-                  .payout
-                  .insert(key, royalty_to_payout(*v as u128, balance));
-              total_perpetual += *v;
-          }
-      }
-
-      // payout to previous owner who gets 100% - total perpetual royalties
-      payout_object.payout.insert(
-          owner_id,
-          royalty_to_payout((10000 - total_perpetual).into(), balance),
-      );
-
-      //return the payout object
-      payout_object
+      //calculate the payout between the royalties and the previous owner
+      compute_payout(&previous_token.owner_id, &previous_token.royalty, balance.0, max_len_payout)
   }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::account;
+
+    fn total(payout: &Payout) -> u128 {
+        payout.payout.values().map(|amount| amount.0).sum()
+    }
+
+    #[test]
+    fn payout_without_royalties_goes_to_owner() {
+        let payout = compute_payout(&account("owner.near"), &HashMap::new(), 1_000, None);
+
+        assert_eq!(payout.payout.len(), 1);
+        assert_eq!(payout.payout[&account("owner.near")], U128(1_000));
+    }
+
+    #[test]
+    fn payout_rounds_royalties_down_and_adds_up_to_balance() {
+        let royalty = HashMap::from([
+            (account("artist.near"), 3333),
+            (account("gallery.near"), 3333),
+            (account("curator.near"), 1),
+        ]);
+
+        for balance in [0, 1, 7, 9_999, 10_001, 1_234_567_891, u128::MAX] {
+            let payout = compute_payout(&account("owner.near"), &royalty, balance, None);
+
+            assert_eq!(total(&payout), balance);
+            assert_eq!(payout.payout[&account("artist.near")], U128(balance / 10000 * 3333 + balance % 10000 * 3333 / 10000));
+            assert_eq!(payout.payout[&account("curator.near")], U128(balance / 10000 + balance % 10000 / 10000));
+        }
+    }
+
+    #[test]
+    fn payout_merges_owner_royalty_into_owner_share() {
+        let royalty = HashMap::from([
+            (account("artist.near"), 1000),
+            (account("owner.near"), 500),
+        ]);
+
+        let payout = compute_payout(&account("owner.near"), &royalty, 10_000, None);
+
+        assert_eq!(payout.payout.len(), 2);
+        assert_eq!(payout.payout[&account("artist.near")], U128(1_000));
+        assert_eq!(payout.payout[&account("owner.near")], U128(9_000));
+    }
+
+    #[test]
+    fn payout_with_full_royalties_leaves_owner_nothing() {
+        let royalty = HashMap::from([
+            (account("artist.near"), 5000),
+            (account("gallery.near"), 5000),
+        ]);
+
+        let payout = compute_payout(&account("owner.near"), &royalty, 999, None);
+
+        assert_eq!(total(&payout), 999);
+        assert_eq!(payout.payout[&account("owner.near")], U128(1));
+    }
+
+    #[test]
+    fn payout_counts_owner_towards_max_len_payout() {
+        let royalty = HashMap::from([(account("artist.near"), 1000)]);
+
+        let payout = compute_payout(&account("owner.near"), &royalty, 100, Some(2));
+        assert_eq!(payout.payout.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Market cannot payout to that many receivers")]
+    fn payout_panics_above_max_len_payout() {
+        let royalty = HashMap::from([(account("artist.near"), 1000)]);
+
+        compute_payout(&account("owner.near"), &royalty, 100, Some(1));
+    }
+
+    #[test]
+    fn royalties_up_to_100_percent_are_valid() {
+        assert_valid_royalties(&HashMap::from([
+            (account("artist.near"), 6000),
+            (account("gallery.near"), 4000),
+        ]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Perpetual royalties cannot add up to more than 10000 basis points")]
+    fn royalties_above_100_percent_are_invalid() {
+        assert_valid_royalties(&HashMap::from([
+            (account("artist.near"), 6000),
+            (account("gallery.near"), 4001),
+        ]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Perpetual royalties cannot add up to more than 10000 basis points")]
+    fn overflowing_royalties_are_invalid() {
+        assert_valid_royalties(&HashMap::from([
+            (account("artist.near"), u32::MAX),
+            (account("gallery.near"), 2),
+        ]));
+    }
+}
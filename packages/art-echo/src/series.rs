@@ -0,0 +1,523 @@
+use crate::*;
+
+pub type SeriesId = String;
+
+//the delimiter between the series ID and the edition number in the token IDs of a series
+pub const SERIES_DELIMITER: char = ':';
+
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct Series {
+  //the account that created the series and gets paid for every edition
+  pub creator_id: AccountId,
+  //the metadata shared by every edition. `copies` is the maximum number of editions
+  pub metadata: TokenMetadata,
+  //the perpetual royalties every edition is minted with
+  pub royalty: HashMap<AccountId, u32>,
+  //the price of an edition. If there is no price, only the creator can mint editions
+  pub price: Option<NearToken>,
+  //the number of editions that have been minted so far
+  pub minted: u64,
+}
+
+//The Json series is what will be returned from view calls.
+#[derive(Serialize, Deserialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonSeries {
+  //series ID
+  pub series_id: SeriesId,
+  //creator of the series
+  pub creator_id: AccountId,
+  //metadata shared by every edition
+  pub metadata: TokenMetadata,
+  //perpetual royalties of every edition
+  pub royalty: HashMap<AccountId, u32>,
+  //price of an edition
+  pub price: Option<NearToken>,
+  //number of editions minted so far
+  pub minted: U64,
+  //whether the metadata of the series has been locked
+  pub is_locked: bool,
+  //whether the editions of the series can be transferred
+  pub transferable: bool,
+}
+
+#[near]
+impl ArtEcho {
+    /*
+        create a new series of editions that can be minted on demand. Only minters can create series: unlike nft_mint,
+        artists of the registrar aren't let through, so the owner has to add an artist with add_minter first
+    */
+    #[payable]
+    pub fn nft_create_series(
+        &mut self,
+        series_id: SeriesId,
+        metadata: TokenMetadata,
+        price: Option<NearToken>,
+        //we add an optional parameter for perpetual royalties
+        perpetual_royalties: Option<HashMap<AccountId, u32>>,
+        //editions of a series that isn't transferable stay with their owner until the creator burns them. Defaults to true
+        transferable: Option<bool>,
+    ) {
+        //make sure the caller is allowed to create series
+        self.assert_minter();
+
+        //make sure the series ID can be used as the prefix of the edition token IDs
+        assert!(!series_id.is_empty(), "Series ID cannot be empty");
+        assert!(
+            !series_id.contains(SERIES_DELIMITER),
+            "Series ID cannot contain '{}'", SERIES_DELIMITER
+        );
+        assert!(
+            !series_id.contains(COLLECTION_DELIMITER),
+            "Series ID cannot contain '{}'", COLLECTION_DELIMITER
+        );
+        assert!(
+            !series_id.contains(WRAP_DELIMITER),
+            "Series ID cannot contain '{}'", WRAP_DELIMITER
+        );
+        //a series needs to be capped, otherwise it wouldn't be a series of editions
+        assert!(
+            metadata.copies.unwrap_or(0) > 0,
+            "Series must have a positive number of copies"
+        );
+        //every edition gets the hash of the upload the media points to
+        let mut metadata = metadata;
+        link_upload(&mut self.uploads, &mut metadata);
+
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        // create a royalty map to store in the series
+        let mut royalty = HashMap::new();
+
+        // if perpetual royalties were passed into the function:
+        if let Some(perpetual_royalties) = perpetual_royalties {
+            //make sure the perpetual royalties can be payed out
+            assert_valid_royalties(&perpetual_royalties);
+
+            //iterate through the perpetual royalties and insert the account and amount in the royalty map
+            for (account, amount) in perpetual_royalties {
+                royalty.insert(account, amount);
+            }
+        }
+
+        //specify the series struct with the caller as the creator
+        let series = Series {
+            creator_id: env::predecessor_account_id(),
+            metadata,
+            royalty,
+            price,
+            minted: 0,
+        };
+
+        //insert the series ID and series struct and make sure that the series doesn't exist
+        assert!(
+            self.series_by_id.insert(series_id.clone(), series).is_none(),
+            "Series already exists"
+        );
+
+        //keep track of the series whose editions can't be transferred
+        if !transferable.unwrap_or(true) {
+            self.soulbound_series.insert(series_id);
+        }
+
+        //calculate the required storage which was the used - initial
+        let required_storage_in_bytes = self.internal_storage_usage() - initial_storage_usage;
+
+        //refund any excess storage if the user attached too much. Panic if they didn't attach enough to cover the required.
+        self.refund_deposit(required_storage_in_bytes.into());
+    }
+
+    //mint the next edition of a series. Anyone can mint if the series has a price, otherwise only the creator can
+    #[payable]
+    pub fn nft_mint_series(&mut self, series_id: SeriesId, receiver_id: AccountId) -> TokenId {
+        //make sure mints aren't stopped and the tokens aren't still being migrated
+        self.assert_not_paused();
+        self.assert_migrated();
+
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        //get the series object from the series ID
+        let mut series = self.series_by_id.get(&series_id).cloned().expect("No series");
+
+        //if the series has no price, only the creator can mint editions
+        let price = match series.price {
+            Some(price) => price,
+            None => {
+                assert_eq!(
+                    env::predecessor_account_id(),
+                    series.creator_id,
+                    "Only the series creator can mint editions of a series without a price"
+                );
+                NearToken::from_yoctonear(0)
+            }
+        };
+
+        //make sure the series isn't sold out
+        let copies = series.metadata.copies.unwrap_or(0);
+        assert!(series.minted < copies, "Series is sold out");
+
+        //editions are numbered starting from 1
+        series.minted += 1;
+        let token_id = format!("{}{}{}", series_id, SERIES_DELIMITER, series.minted);
+
+        //every edition gets a copy of the series metadata issued now
+        let mut metadata = series.metadata.clone();
+        metadata.issued_at = Some(env::block_timestamp_ms());
+
+        //specify the token struct that contains the owner ID
+        let token = Token {
+            owner_id: receiver_id,
+            //we set the approved account IDs to the default value (an empty map)
+            approved_account_ids: Default::default(),
+            //the next approval ID is set to 0
+            next_approval_id: 0,
+            //every edition has the royalties of the series
+            royalty: series.royalty.clone(),
+            series_id: Some(series_id.clone()),
+        };
+
+        //insert the token ID and token struct and make sure that the token doesn't exist
+        assert!(
+            self.tokens_by_id.insert(token_id.clone(), token.clone()).is_none(),
+            "Token already exists"
+        );
+
+        //insert the token ID and metadata
+        self.token_metadata_by_id.insert(token_id.clone(), metadata);
+        self.sorted_token_ids.insert(token_id.clone(), ());
+
+        //insert the series back with the new number of minted editions
+        let creator_id = series.creator_id.clone();
+        self.series_by_id.insert(series_id.clone(), series);
+
+        //call the internal methods for adding the token to the owner, the series and the series creator
+        self.internal_add_token_to_owner(&token.owner_id, &token_id);
+        self.internal_add_token_to_series(&series_id, &token_id);
+        self.internal_add_token_to_creator(&creator_id, &token_id);
+
+        // Construct the mint log as per the events standard.
+        let nft_mint_log: EventLog = EventLog {
+            // Standard name ("nep171").
+            standard: NFT_STANDARD_NAME.to_string(),
+            // Version of the standard ("nft-1.0.0").
+            version: NFT_METADATA_SPEC.to_string(),
+            // The data related with the event stored in a vector.
+            event: EventLogVariant::NftMint(vec![NftMintLog {
+                // Owner of the token.
+                owner_id: token.owner_id.to_string(),
+                // Vector of token IDs that were minted.
+                token_ids: vec![token_id.to_string()],
+                // An optional memo to include.
+                memo: None,
+            }]),
+        };
+
+        // Log the serialized json.
+        env::log_str(&nft_mint_log.to_string());
+
+        //calculate the required storage which was the used - initial
+        let required_storage_in_bytes = self.internal_storage_usage() - initial_storage_usage;
+
+        //refund any excess deposit. Panic if the user didn't attach enough to cover the price and the storage.
+        self.refund_deposit_with_price(required_storage_in_bytes.into(), price);
+
+        //pay the creator for the edition
+        if price.gt(&NearToken::from_yoctonear(0)) {
+            Promise::new(creator_id).transfer(price);
+        }
+
+        token_id
+    }
+
+    //update the metadata shared by the editions that haven't been minted yet. Only works while the series isn't locked
+    #[payable]
+    pub fn nft_update_series_metadata(&mut self, series_id: SeriesId, metadata: TokenMetadata) {
+        //assert at least one yocto for security reasons and to pay for storage growth
+        assert_at_least_one_yocto();
+
+        //get the series object and make sure the caller is the creator
+        let series = self.series_by_id.get(&series_id).expect("No series");
+        assert_eq!(
+            env::predecessor_account_id(),
+            series.creator_id,
+            "Predecessor must be the series creator."
+        );
+        assert!(!self.locked_series.contains(&series_id), "Series is locked");
+
+        //the cap can't go below the editions that are already out there
+        assert!(
+            metadata.copies.unwrap_or(0) >= series.minted.max(1),
+            "Copies cannot be less than the minted editions"
+        );
+        //the media hash has to be the hash of the upload if the media points to one
+        let mut metadata = metadata;
+        link_upload(&mut self.uploads, &mut metadata);
+
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        //replace the metadata of the series
+        self.series_by_id.get_mut(&series_id).unwrap().metadata = metadata;
+
+        //charge the caller for the extra storage used or refund them the storage that was released
+        self.refund_storage_difference(initial_storage_usage);
+    }
+
+    //change the price of an edition. Passing no price means only the creator can mint
+    #[payable]
+    pub fn nft_set_series_price(&mut self, series_id: SeriesId, price: Option<NearToken>) {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+
+        //get the series object and make sure the caller is the creator
+        let series = self.series_by_id.get_mut(&series_id).expect("No series");
+        assert_eq!(
+            env::predecessor_account_id(),
+            series.creator_id,
+            "Predecessor must be the series creator."
+        );
+
+        //set the new price
+        series.price = price;
+    }
+
+    //freeze the metadata of a series. This can't be undone
+    #[payable]
+    pub fn nft_lock_series(&mut self, series_id: SeriesId) {
+        //assert at least one yocto for security reasons and to pay for storage
+        assert_at_least_one_yocto();
+
+        //get the series object and make sure the caller is the creator
+        let series = self.series_by_id.get(&series_id).expect("No series");
+        assert_eq!(
+            env::predecessor_account_id(),
+            series.creator_id,
+            "Predecessor must be the series creator."
+        );
+
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        assert!(self.locked_series.insert(series_id), "Series is already locked");
+
+        //calculate the required storage which was the used - initial
+        let required_storage_in_bytes = self.internal_storage_usage() - initial_storage_usage;
+
+        //refund any excess storage if the user attached too much. Panic if they didn't attach enough to cover the required.
+        self.refund_deposit(required_storage_in_bytes.into());
+    }
+
+    //get the information for a specific series ID
+    pub fn nft_get_series(&self, series_id: SeriesId) -> Option<JsonSeries> {
+        self.series_by_id
+            .get(&series_id)
+            .map(|series| self.internal_series_to_json(series_id.clone(), series.clone()))
+    }
+
+    //Query for the series on the contract using pagination
+    pub fn nft_series(&self, from_index: Option<U128>, limit: Option<u32>) -> Vec<JsonSeries> {
+        //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        //iterate through each series using an iterator
+        self.series_by_id.iter()
+            //skip to the index we specified in the start variable
+            .skip(start as usize)
+            //take the first "limit" elements in the vector, capped so the view can't run out of gas
+            .take(page_limit(limit))
+            //we'll map the series into Json Series
+            .map(|(series_id, series)| self.internal_series_to_json(series_id.clone(), series.clone()))
+            //since we turned the series into an iterator, we need to turn it back into a vector to return
+            .collect()
+    }
+
+    //get the number of editions minted for a given series
+    pub fn nft_series_supply(&self, series_id: SeriesId) -> U64 {
+        //get the set of tokens for the passed in series
+        if let Some(tokens_for_series_set) = self.tokens_per_series.get(&series_id) {
+            U64(tokens_for_series_set.len().into())
+        } else {
+            //if there isn't a set of tokens for the passed in series ID, we'll return 0
+            U64(0)
+        }
+    }
+}
+
+impl ArtEcho {
+    //add a token to the set of tokens minted for a series
+    pub(crate) fn internal_add_token_to_series(
+        &mut self,
+        series_id: &SeriesId,
+        token_id: &TokenId,
+    ) {
+        //get the set of tokens for the given series
+        let tokens_set = self.tokens_per_series.entry(series_id.clone()).or_insert_with(|| {
+            //if the series doesn't have any tokens, we create a new iterable set
+            IterableSet::new(
+                StorageKey::TokensPerSeriesInner {
+                    //we get a new unique prefix for the collection
+                    series_id_hash: hash_series_id(series_id),
+                },
+            )
+        });
+
+        //we insert the token ID into the set and write it to storage
+        tokens_set.insert(token_id.clone());
+        tokens_set.flush();
+    }
+
+    //remove a token from the set of tokens minted for a series
+    pub(crate) fn internal_remove_token_from_series(
+        &mut self,
+        series_id: &SeriesId,
+        token_id: &TokenId,
+    ) {
+        //we get the set of tokens minted for the series
+        if let Some(tokens_set) = self.tokens_per_series.get_mut(series_id) {
+            //we remove the the token_id from the set of tokens and write it to storage
+            tokens_set.remove(token_id);
+            tokens_set.flush();
+
+            //if the token set is now empty, we remove the series from the tokens_per_series collection
+            if tokens_set.is_empty() {
+                self.tokens_per_series.remove(series_id);
+            }
+        }
+    }
+
+    //convert a series into the struct returned from view calls
+    pub(crate) fn internal_series_to_json(&self, series_id: SeriesId, series: Series) -> JsonSeries {
+        JsonSeries {
+            is_locked: self.locked_series.contains(&series_id),
+            transferable: !self.soulbound_series.contains(&series_id),
+            series_id,
+            creator_id: series.creator_id,
+            metadata: series.metadata,
+            royalty: series.royalty,
+            price: series.price,
+            minted: U64(series.minted),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    const SERIES_ID: &str = "sunsets";
+
+    //a contract with a series of 2 editions created by the owner
+    fn setup_series(price: Option<NearToken>) -> ArtEcho {
+        let mut contract = setup_contract();
+        let mut metadata = sample_metadata();
+        metadata.copies = Some(2);
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_create_series(SERIES_ID.to_string(), metadata, price, None, None);
+        contract
+    }
+
+    #[test]
+    fn create_series() {
+        let contract = setup_series(None);
+
+        let series = contract.nft_get_series(SERIES_ID.to_string()).unwrap();
+        assert_eq!(series.creator_id, owner());
+        assert_eq!(series.metadata.copies, Some(2));
+        assert_eq!(series.minted, U64(0));
+        assert!(!series.is_locked);
+        assert!(series.transferable);
+        assert_eq!(contract.nft_series(None, None).len(), 1);
+        assert_eq!(contract.nft_series_supply(SERIES_ID.to_string()), U64(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Series already exists")]
+    fn series_ids_are_unique() {
+        let mut contract = setup_series(None);
+
+        let mut metadata = sample_metadata();
+        metadata.copies = Some(5);
+        contract.nft_create_series(SERIES_ID.to_string(), metadata, None, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Series must have a positive number of copies")]
+    fn series_must_be_capped() {
+        let mut contract = setup_contract();
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_create_series(SERIES_ID.to_string(), sample_metadata(), None, None, None);
+    }
+
+    #[test]
+    fn editions_are_numbered_up_to_the_supply_cap() {
+        let mut contract = setup_series(None);
+
+        set_context(owner(), NearToken::from_near(1));
+        assert_eq!(contract.nft_mint_series(SERIES_ID.to_string(), account("alice.near")), "sunsets:1");
+        assert_eq!(contract.nft_mint_series(SERIES_ID.to_string(), account("bob.near")), "sunsets:2");
+
+        let token = contract.nft_token("sunsets:2".to_string()).unwrap();
+        assert_eq!(token.owner_id, account("bob.near"));
+        assert_eq!(token.metadata.copies, Some(2));
+        assert_eq!(contract.nft_get_series(SERIES_ID.to_string()).unwrap().minted, U64(2));
+        assert_eq!(contract.nft_series_supply(SERIES_ID.to_string()), U64(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn paused_contract_blocks_series_mints() {
+        let mut contract = setup_series(None);
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.nft_pause(None);
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint_series(SERIES_ID.to_string(), account("alice.near"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Series is sold out")]
+    fn sold_out_series_cannot_be_minted() {
+        let mut contract = setup_series(None);
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint_series(SERIES_ID.to_string(), account("alice.near"));
+        contract.nft_mint_series(SERIES_ID.to_string(), account("alice.near"));
+        contract.nft_mint_series(SERIES_ID.to_string(), account("alice.near"));
+    }
+
+    #[test]
+    fn paid_mint_pays_the_creator_and_refunds_the_rest() {
+        let mut contract = setup_series(Some(NearToken::from_near(1)));
+
+        set_context(account("bob.near"), NearToken::from_near(2));
+        contract.nft_mint_series(SERIES_ID.to_string(), account("bob.near"));
+
+        assert_eq!(refunded_to(&owner()), NearToken::from_near(1));
+        let refund = refunded_to(&account("bob.near"));
+        assert!(refund > NearToken::from_millinear(900) && refund < NearToken::from_near(1));
+        assert_eq!(contract.nft_token("sunsets:1".to_string()).unwrap().owner_id, account("bob.near"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Must attach")]
+    fn paid_mint_requires_the_price() {
+        let mut contract = setup_series(Some(NearToken::from_near(1)));
+
+        set_context(account("bob.near"), NearToken::from_millinear(500));
+        contract.nft_mint_series(SERIES_ID.to_string(), account("bob.near"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the series creator can mint editions of a series without a price")]
+    fn only_the_creator_mints_without_a_price() {
+        let mut contract = setup_series(None);
+
+        set_context(account("bob.near"), NearToken::from_near(1));
+        contract.nft_mint_series(SERIES_ID.to_string(), account("bob.near"));
+    }
+}
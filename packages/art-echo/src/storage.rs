@@ -0,0 +1,203 @@
+use near_contract_standards::storage_management::{
+    StorageBalance, StorageBalanceBounds, StorageManagement,
+};
+use near_sdk::{log, StorageUsage};
+
+use crate::*;
+
+//the bytes the storage balance of an account takes up: the record, its key with the longest account ID and the balance
+const STORAGE_BALANCE_BYTES: StorageUsage = 40 + 1 + 4 + 64 + 16;
+
+impl ArtEcho {
+    //get the storage balance of an account. The minimum balance pays for the balance itself, the rest is available
+    //to be debited for mints, approvals and metadata growth
+    pub(crate) fn internal_storage_balance_of(&self, account_id: &AccountId) -> Option<StorageBalance> {
+        self.storage_balances.get(account_id).map(|total| StorageBalance {
+            total: *total,
+            available: total.saturating_sub(self.storage_balance_bounds().min),
+        })
+    }
+
+    //debit the cost of storage from the available storage balance of an account.
+    //returns false if the account isn't registered or doesn't have enough available, so the caller has to attach a deposit
+    pub(crate) fn internal_debit_storage(&mut self, account_id: &AccountId, cost: NearToken) -> bool {
+        if cost.is_zero() {
+            return false;
+        }
+        let Some(storage_balance) = self.internal_storage_balance_of(account_id) else {
+            return false;
+        };
+        if storage_balance.available < cost {
+            return false;
+        }
+
+        self.storage_balances.insert(account_id.clone(), storage_balance.total.saturating_sub(cost));
+        true
+    }
+}
+
+#[near]
+impl StorageManagement for ArtEcho {
+    //deposit storage for `account_id` (defaults to the caller), registering the account if it isn't yet.
+    //with `registration_only`, only the minimum balance is kept and the rest of the deposit is refunded
+    #[payable]
+    fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let amount = env::attached_deposit();
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let registration_only = registration_only.unwrap_or(false);
+
+        if let Some(total) = self.storage_balances.get(&account_id).cloned() {
+            if registration_only {
+                log!("The account is already registered, refunding the deposit");
+                if amount.gt(&NearToken::from_yoctonear(0)) {
+                    Promise::new(env::predecessor_account_id()).transfer(amount);
+                }
+            } else {
+                self.storage_balances.insert(account_id.clone(), total.saturating_add(amount));
+            }
+        } else {
+            let min_balance = self.storage_balance_bounds().min;
+            assert!(amount >= min_balance, "The attached deposit is less than the minimum storage balance");
+
+            let total = if registration_only {
+                //refund the deposit above the minimum balance
+                let refund = amount.saturating_sub(min_balance);
+                if refund.gt(&NearToken::from_yoctonear(0)) {
+                    Promise::new(env::predecessor_account_id()).transfer(refund);
+                }
+                min_balance
+            } else {
+                amount
+            };
+            self.storage_balances.insert(account_id.clone(), total);
+        }
+
+        self.internal_storage_balance_of(&account_id).unwrap()
+    }
+
+    //withdraw `amount` (defaults to all) of the available storage balance of the caller
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<NearToken>) -> StorageBalance {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+
+        let storage_balance = self.internal_storage_balance_of(&account_id).expect("The account is not registered");
+        let amount = amount.unwrap_or(storage_balance.available);
+        assert!(
+            amount <= storage_balance.available,
+            "The amount is greater than the available storage balance"
+        );
+
+        if amount.gt(&NearToken::from_yoctonear(0)) {
+            self.storage_balances.insert(account_id.clone(), storage_balance.total.saturating_sub(amount));
+            Promise::new(account_id.clone()).transfer(amount);
+        }
+
+        self.internal_storage_balance_of(&account_id).unwrap()
+    }
+
+    //unregister the caller and refund their whole storage balance. The storage that was already debited
+    //stays with the tokens it paid for, so there is nothing to force
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+        assert!(!force.unwrap_or(false), "Force unregistering is not supported");
+
+        match self.storage_balances.remove(&env::predecessor_account_id()) {
+            Some(total) => {
+                Promise::new(env::predecessor_account_id()).transfer(total);
+                true
+            }
+            None => {
+                log!("The account is not registered");
+                false
+            }
+        }
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: env::storage_byte_cost().saturating_mul(STORAGE_BALANCE_BYTES.into()),
+            max: None,
+        }
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.internal_storage_balance_of(&account_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    //a contract where alice deposited 1 NEAR of storage
+    fn setup_storage() -> ArtEcho {
+        let mut contract = setup_contract();
+        set_context(account("alice.near"), NearToken::from_near(1));
+        contract.storage_deposit(None, None);
+        contract
+    }
+
+    #[test]
+    fn approvals_are_debited_from_the_storage_balance() {
+        let mut contract = setup_storage();
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint("piece".to_string(), sample_metadata(), account("alice.near"), None, None);
+        let available = contract.storage_balance_of(account("alice.near")).unwrap().available;
+
+        //only the yoctoNEAR for security is attached
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_approve("piece".to_string(), account("market.near"), None);
+
+        let cost = env::storage_byte_cost().saturating_mul(bytes_for_approved_account_id(&account("market.near")));
+        assert_eq!(contract.storage_balance_of(account("alice.near")).unwrap().available, available.saturating_sub(cost));
+    }
+
+    #[test]
+    fn mint_is_debited_from_the_storage_balance() {
+        let mut contract = setup_contract();
+        set_context(owner(), NearToken::from_near(1));
+        contract.storage_deposit(None, None);
+
+        set_context(owner(), NearToken::from_yoctonear(0));
+        contract.nft_mint("piece".to_string(), sample_metadata(), account("alice.near"), None, None);
+
+        assert!(contract.storage_balance_of(owner()).unwrap().available < NearToken::from_near(1));
+        assert_eq!(contract.nft_token("piece".to_string()).unwrap().owner_id, account("alice.near"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Must attach")]
+    fn attached_deposit_is_the_fallback() {
+        let mut contract = setup_contract();
+        set_context(owner(), NearToken::from_millinear(10));
+        contract.storage_deposit(None, Some(true));
+
+        //the minimum balance isn't available, so the attached deposit has to cover the mint
+        set_context(owner(), NearToken::from_yoctonear(0));
+        contract.nft_mint("piece".to_string(), sample_metadata(), account("alice.near"), None, None);
+    }
+
+    #[test]
+    fn withdraw_leaves_the_minimum_balance() {
+        let mut contract = setup_storage();
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        let storage_balance = contract.storage_withdraw(None);
+
+        assert_eq!(storage_balance.total, contract.storage_balance_bounds().min);
+        assert_eq!(storage_balance.available, NearToken::from_yoctonear(0));
+        assert_eq!(
+            refunded_to(&account("alice.near")),
+            NearToken::from_near(1).saturating_sub(contract.storage_balance_bounds().min)
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use crate::*;
+use near_sdk::mock::MockAction;
+use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+use near_sdk::testing_env;
+
+pub(crate) fn account(account_id: &str) -> AccountId {
+    account_id.parse().unwrap()
+}
+
+pub(crate) fn owner() -> AccountId {
+    account("owner.near")
+}
+
+//sets up the context for the next call on the contract
+pub(crate) fn set_context(predecessor_id: AccountId, deposit: NearToken) {
+    set_context_at(predecessor_id, deposit, 0);
+}
+
+//sets up the context for the next call on the contract in a block with the given timestamp
+pub(crate) fn set_context_at(predecessor_id: AccountId, deposit: NearToken, timestamp_ms: u64) {
+    testing_env!(VMContextBuilder::new()
+        .current_account_id(account("art-echo.near"))
+        .predecessor_account_id(predecessor_id)
+        .attached_deposit(deposit)
+        .block_timestamp(timestamp_ms * 1_000_000)
+        .build());
+}
+
+//a contract owned by owner.near
+pub(crate) fn setup_contract() -> ArtEcho {
+    set_context(owner(), NearToken::from_yoctonear(0));
+    ArtEcho::new_default_meta(owner())
+}
+
+pub(crate) fn sample_metadata() -> TokenMetadata {
+    TokenMetadata {
+        title: Some("Token".to_string()),
+        description: None,
+        media: None,
+        media_hash: None,
+        copies: None,
+        issued_at: None,
+        expires_at: None,
+        starts_at: None,
+        updated_at: None,
+        extra: None,
+        reference: None,
+        reference_hash: None,
+    }
+}
+
+//the total that was transferred to an account in the receipts created by the last call
+pub(crate) fn refunded_to(account_id: &AccountId) -> NearToken {
+    get_created_receipts()
+        .into_iter()
+        .filter(|receipt| &receipt.receiver_id == account_id)
+        .flat_map(|receipt| receipt.actions)
+        .fold(NearToken::from_yoctonear(0), |total, action| match action {
+            MockAction::Transfer { deposit, .. } => total.saturating_add(deposit),
+            _ => total,
+        })
+}
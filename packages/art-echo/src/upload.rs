@@ -0,0 +1,464 @@
+use crate::*;
+
+pub type UploadId = u64;
+
+//the media of a token points to an upload with this prefix followed by the upload ID, e.g. "upload://7"
+pub const UPLOAD_URI_PREFIX: &str = "upload://";
+//the most bytes an upload can have
+pub const MAX_UPLOAD_LENGTH: u64 = 1_000_000;
+//the uploaded bytes are stored in pages of this many bytes, so ranges can be read without reading the whole upload
+pub const UPLOAD_PAGE_LENGTH: u64 = 16_384;
+//the most bytes that are returned by a single view call
+pub const MAX_UPLOAD_RANGE: u32 = 65_536;
+
+#[derive(BorshDeserialize, BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct Upload {
+    //the account that uploads the bytes and pays for their storage
+    pub uploader_id: AccountId,
+    //the number of bytes that have been uploaded so far
+    pub length: u64,
+    //the sha256 hash of the bytes. Only set once the upload is finalized, after which no bytes can be added
+    pub hash: Option<Base64VecU8>,
+    //whether the media of a token, series or pending collaboration ever pointed to the upload. Linked uploads can't be deleted
+    pub linked: bool,
+}
+
+//The Json upload is what will be returned from view calls.
+#[derive(Serialize, Deserialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonUpload {
+    //upload ID
+    pub upload_id: U64,
+    //account that uploaded the bytes
+    pub uploader_id: AccountId,
+    //number of bytes uploaded so far
+    pub length: U64,
+    //sha256 hash of the bytes if the upload is finalized
+    pub hash: Option<Base64VecU8>,
+    //whether a token links to the upload, in which case it can't be deleted
+    pub linked: bool,
+    //the URI to use as the media of a token
+    pub uri: String,
+}
+
+//get the URI that tokens use to point to an upload
+pub(crate) fn upload_uri(upload_id: UploadId) -> String {
+    format!("{}{}", UPLOAD_URI_PREFIX, upload_id)
+}
+
+//if the media of the metadata points to an upload, make sure the upload is finalized, set the media hash to the
+//hash of the uploaded bytes and keep the upload from being deleted. Panics if the metadata declares a different media hash
+pub(crate) fn link_upload(uploads: &mut LookupMap<UploadId, Upload>, metadata: &mut TokenMetadata) {
+    let Some(upload_id) = metadata
+        .media
+        .as_ref()
+        .and_then(|media| media.strip_prefix(UPLOAD_URI_PREFIX))
+    else {
+        return;
+    };
+    let upload_id: UploadId = upload_id
+        .parse()
+        .unwrap_or_else(|_| env::panic_str("Invalid upload URI"));
+    let upload = uploads.get_mut(&upload_id).expect("No upload");
+    let hash = upload.hash.clone().expect("Upload is not finalized");
+    upload.linked = true;
+
+    if let Some(media_hash) = metadata.media_hash.as_ref() {
+        assert!(
+            media_hash.0 == hash.0,
+            "media_hash doesn't match the sha256 hash of the media"
+        );
+    }
+    metadata.media_hash = Some(hash);
+}
+
+#[near]
+impl ArtEcho {
+    //start uploading bytes to the contract. The caller pays for the storage of everything they upload
+    #[payable]
+    pub fn begin_upload(&mut self) -> U64 {
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        //create an empty upload owned by the caller
+        let upload_id = self.next_upload_id;
+        self.next_upload_id += 1;
+        self.uploads.insert(
+            upload_id,
+            Upload {
+                uploader_id: env::predecessor_account_id(),
+                length: 0,
+                hash: None,
+                linked: false,
+            },
+        );
+
+        //calculate the required storage which was the used - initial
+        let required_storage_in_bytes = self.internal_storage_usage() - initial_storage_usage;
+
+        //refund any excess storage if the user attached too much. Panic if they didn't attach enough to cover the required.
+        self.refund_deposit(required_storage_in_bytes.into());
+
+        U64(upload_id)
+    }
+
+    //add bytes to the end of an upload. Only the uploader can add bytes, and only until the upload is finalized
+    #[payable]
+    pub fn append_chunk(&mut self, upload_id: U64, chunk: Base64VecU8) {
+        //assert at least one yocto for security reasons and to pay for storage
+        assert_at_least_one_yocto();
+
+        //get the upload and make sure bytes can still be added by the caller
+        let upload = self.uploads.get(&upload_id.0).expect("No upload");
+        assert_eq!(
+            env::predecessor_account_id(),
+            upload.uploader_id,
+            "Predecessor must be the uploader."
+        );
+        assert!(upload.hash.is_none(), "Upload is already finalized");
+        assert!(!chunk.0.is_empty(), "Chunk cannot be empty");
+        let mut offset = upload.length;
+        let length = offset + chunk.0.len() as u64;
+        assert!(
+            length <= MAX_UPLOAD_LENGTH,
+            "Upload cannot be more than {} bytes",
+            MAX_UPLOAD_LENGTH
+        );
+
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        //fill up the last page and add new pages for the rest of the chunk
+        let mut remaining: &[u8] = &chunk.0;
+        while !remaining.is_empty() {
+            let page_index = offset / UPLOAD_PAGE_LENGTH;
+            let page_space = (UPLOAD_PAGE_LENGTH - offset % UPLOAD_PAGE_LENGTH) as usize;
+            let (bytes, rest) = remaining.split_at(page_space.min(remaining.len()));
+
+            self.upload_pages
+                .entry((upload_id.0, page_index))
+                .or_default()
+                .extend_from_slice(bytes);
+            offset += bytes.len() as u64;
+            remaining = rest;
+        }
+        self.uploads.get_mut(&upload_id.0).unwrap().length = length;
+
+        //calculate the required storage which was the used - initial
+        let required_storage_in_bytes = self.internal_storage_usage() - initial_storage_usage;
+
+        //refund any excess storage if the user attached too much. Panic if they didn't attach enough to cover the required.
+        self.refund_deposit(required_storage_in_bytes.into());
+    }
+
+    //finish an upload so it can be used as the media of a token. The sha256 hash of the bytes is stored with the upload
+    //and returned, and no bytes can be added anymore
+    #[payable]
+    pub fn finalize_upload(&mut self, upload_id: U64) -> Base64VecU8 {
+        //assert at least one yocto for security reasons and to pay for storage
+        assert_at_least_one_yocto();
+
+        //get the upload and make sure the caller can finalize it
+        let upload = self.uploads.get(&upload_id.0).expect("No upload");
+        assert_eq!(
+            env::predecessor_account_id(),
+            upload.uploader_id,
+            "Predecessor must be the uploader."
+        );
+        assert!(upload.hash.is_none(), "Upload is already finalized");
+        assert!(upload.length > 0, "Upload is empty");
+        let length = upload.length;
+
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        //hash all the pages together
+        let page_count = length.div_ceil(UPLOAD_PAGE_LENGTH);
+        let mut bytes = Vec::with_capacity(length as usize);
+        for page_index in 0..page_count {
+            bytes.extend_from_slice(self.upload_pages.get(&(upload_id.0, page_index)).unwrap());
+        }
+        let hash = Base64VecU8(env::sha256(&bytes));
+        self.uploads.get_mut(&upload_id.0).unwrap().hash = Some(hash.clone());
+
+        //calculate the required storage which was the used - initial
+        let required_storage_in_bytes = self.internal_storage_usage() - initial_storage_usage;
+
+        //refund any excess storage if the user attached too much. Panic if they didn't attach enough to cover the required.
+        self.refund_deposit(required_storage_in_bytes.into());
+
+        hash
+    }
+
+    //delete an upload no token links to and refund the uploader the storage that was released.
+    //Only the uploader can delete an upload, finalized or not
+    #[payable]
+    pub fn delete_upload(&mut self, upload_id: U64) {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+
+        //get the upload and make sure the caller can delete it
+        let upload = self.uploads.get(&upload_id.0).expect("No upload");
+        assert_eq!(
+            env::predecessor_account_id(),
+            upload.uploader_id,
+            "Predecessor must be the uploader."
+        );
+        assert!(!upload.linked, "Upload is linked to a token");
+        let page_count = upload.length.div_ceil(UPLOAD_PAGE_LENGTH);
+
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        //remove the pages and the upload itself
+        for page_index in 0..page_count {
+            self.upload_pages.remove(&(upload_id.0, page_index));
+        }
+        let upload = self.uploads.remove(&upload_id.0).unwrap();
+
+        //refund the uploader the storage that was released
+        let released_storage_in_bytes =
+            initial_storage_usage.saturating_sub(self.internal_storage_usage());
+        let refund = env::storage_byte_cost().saturating_mul(released_storage_in_bytes.into());
+        if refund.gt(&NearToken::from_yoctonear(0)) {
+            Promise::new(upload.uploader_id).transfer(refund);
+        }
+    }
+
+    //get the information for a specific upload ID
+    pub fn get_upload(&self, upload_id: U64) -> Option<JsonUpload> {
+        self.uploads.get(&upload_id.0).map(|upload| JsonUpload {
+            upload_id,
+            uploader_id: upload.uploader_id.clone(),
+            length: U64(upload.length),
+            hash: upload.hash.clone(),
+            linked: upload.linked,
+            uri: upload_uri(upload_id.0),
+        })
+    }
+
+    //get a range of the uploaded bytes starting at from_index. If we didn't specify a limit, use the maximum range
+    pub fn get_upload_bytes(
+        &self,
+        upload_id: U64,
+        from_index: Option<U64>,
+        limit: Option<u32>,
+    ) -> Base64VecU8 {
+        let upload = self.uploads.get(&upload_id.0).expect("No upload");
+
+        //the range can't go past the uploaded bytes or be more than the maximum range
+        let start = from_index
+            .map_or(0, |from_index| from_index.0)
+            .min(upload.length);
+        let end = start
+            .saturating_add(
+                limit
+                    .unwrap_or(MAX_UPLOAD_RANGE)
+                    .min(MAX_UPLOAD_RANGE)
+                    .into(),
+            )
+            .min(upload.length);
+
+        //read the pages the range is on and take the bytes that are part of the range
+        let mut bytes = Vec::with_capacity((end - start) as usize);
+        let mut offset = start;
+        while offset < end {
+            let page_index = offset / UPLOAD_PAGE_LENGTH;
+            let page = self.upload_pages.get(&(upload_id.0, page_index)).unwrap();
+            let page_start = (offset % UPLOAD_PAGE_LENGTH) as usize;
+            let page_end = page.len().min(page_start + (end - offset) as usize);
+            bytes.extend_from_slice(&page[page_start..page_end]);
+            offset += (page_end - page_start) as u64;
+        }
+
+        Base64VecU8(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    //bytes that are spread out over more than two pages
+    fn artwork() -> Vec<u8> {
+        (0..40_000u32).map(|index| (index % 251) as u8).collect()
+    }
+
+    //uploads the artwork as alice in two chunks and finalizes it
+    fn upload_artwork(contract: &mut ArtEcho) -> U64 {
+        let artwork = artwork();
+        set_context(account("alice.near"), NearToken::from_near(1));
+        let upload_id = contract.begin_upload();
+        contract.append_chunk(upload_id, Base64VecU8(artwork[..10_000].to_vec()));
+        contract.append_chunk(upload_id, Base64VecU8(artwork[10_000..].to_vec()));
+        contract.finalize_upload(upload_id);
+        upload_id
+    }
+
+    #[test]
+    fn upload_is_stored_in_pages_and_read_in_ranges() {
+        let mut contract = setup_contract();
+        let upload_id = upload_artwork(&mut contract);
+        let artwork = artwork();
+
+        let upload = contract.get_upload(upload_id).unwrap();
+        assert_eq!(upload.uploader_id, account("alice.near"));
+        assert_eq!(upload.length, U64(40_000));
+        assert_eq!(upload.hash.unwrap().0, env::sha256(&artwork));
+        assert_eq!(upload.uri, "upload://0");
+
+        //a range across the first two pages
+        let range = contract.get_upload_bytes(upload_id, Some(U64(16_000)), Some(1_000));
+        assert_eq!(range.0, artwork[16_000..17_000]);
+        //the whole upload in ranges of the maximum size
+        let mut bytes = contract.get_upload_bytes(upload_id, None, None).0;
+        assert_eq!(bytes.len(), 40_000);
+        bytes.extend(
+            contract
+                .get_upload_bytes(upload_id, Some(U64(40_000)), None)
+                .0,
+        );
+        assert_eq!(bytes, artwork);
+    }
+
+    #[test]
+    #[should_panic(expected = "Must attach")]
+    fn uploader_pays_for_the_storage() {
+        let mut contract = setup_contract();
+
+        set_context(account("alice.near"), NearToken::from_near(1));
+        let upload_id = contract.begin_upload();
+        set_context(account("alice.near"), NearToken::from_millinear(10));
+        contract.append_chunk(upload_id, Base64VecU8(artwork()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Predecessor must be the uploader.")]
+    fn only_uploader_appends() {
+        let mut contract = setup_contract();
+
+        set_context(account("alice.near"), NearToken::from_near(1));
+        let upload_id = contract.begin_upload();
+        set_context(account("bob.near"), NearToken::from_near(1));
+        contract.append_chunk(upload_id, Base64VecU8(vec![1, 2, 3]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Upload is already finalized")]
+    fn finalized_upload_cannot_be_changed() {
+        let mut contract = setup_contract();
+        let upload_id = upload_artwork(&mut contract);
+
+        set_context(account("alice.near"), NearToken::from_near(1));
+        contract.append_chunk(upload_id, Base64VecU8(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn mint_links_media_to_upload() {
+        let mut contract = setup_contract();
+        let upload_id = upload_artwork(&mut contract);
+        let mut metadata = sample_metadata();
+        metadata.media = Some(upload_uri(upload_id.0));
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint(
+            "art".to_string(),
+            metadata,
+            account("alice.near"),
+            None,
+            None,
+        );
+
+        let metadata = contract.nft_token("art".to_string()).unwrap().metadata;
+        assert_eq!(metadata.media.unwrap(), "upload://0");
+        assert_eq!(metadata.media_hash.unwrap().0, env::sha256(&artwork()));
+    }
+
+    #[test]
+    #[should_panic(expected = "media_hash doesn't match the sha256 hash of the media")]
+    fn mint_with_other_media_hash_is_rejected() {
+        let mut contract = setup_contract();
+        let upload_id = upload_artwork(&mut contract);
+        let mut metadata = sample_metadata();
+        metadata.media = Some(upload_uri(upload_id.0));
+        metadata.media_hash = Some(Base64VecU8(env::sha256(b"something else")));
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint(
+            "art".to_string(),
+            metadata,
+            account("alice.near"),
+            None,
+            None,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Upload is not finalized")]
+    fn mint_with_unfinished_upload_is_rejected() {
+        let mut contract = setup_contract();
+        set_context(account("alice.near"), NearToken::from_near(1));
+        let upload_id = contract.begin_upload();
+        contract.append_chunk(upload_id, Base64VecU8(vec![1, 2, 3]));
+        let mut metadata = sample_metadata();
+        metadata.media = Some(upload_uri(upload_id.0));
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint(
+            "art".to_string(),
+            metadata,
+            account("alice.near"),
+            None,
+            None,
+        );
+    }
+
+    #[test]
+    fn unlinked_upload_is_deleted_and_refunded() {
+        let mut contract = setup_contract();
+        let upload_id = upload_artwork(&mut contract);
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.delete_upload(upload_id);
+
+        assert!(contract.get_upload(upload_id).is_none());
+        //the pages of the artwork take up at least as many bytes as the artwork
+        assert!(
+            refunded_to(&account("alice.near")) >= env::storage_byte_cost().saturating_mul(40_000)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Upload is linked to a token")]
+    fn linked_upload_cannot_be_deleted() {
+        let mut contract = setup_contract();
+        let upload_id = upload_artwork(&mut contract);
+        let mut metadata = sample_metadata();
+        metadata.media = Some(upload_uri(upload_id.0));
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint(
+            "art".to_string(),
+            metadata,
+            account("alice.near"),
+            None,
+            None,
+        );
+        assert!(contract.get_upload(upload_id).unwrap().linked);
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.delete_upload(upload_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Predecessor must be the uploader.")]
+    fn only_uploader_deletes() {
+        let mut contract = setup_contract();
+        let upload_id = upload_artwork(&mut contract);
+
+        set_context(account("bob.near"), ONE_YOCTONEAR);
+        contract.delete_upload(upload_id);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::*;
+
+//check if the token metadata expired at the current block
+pub(crate) fn is_expired(metadata: &TokenMetadata) -> bool {
+    metadata
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= env::block_timestamp_ms())
+}
+
+//check if the token metadata started being valid at the current block
+pub(crate) fn has_started(metadata: &TokenMetadata) -> bool {
+    metadata
+        .starts_at
+        .is_none_or(|starts_at| starts_at <= env::block_timestamp_ms())
+}
+
+#[near]
+impl ArtEcho {
+    //check if a token is valid at the current block: it started being valid and it hasn't expired yet
+    pub fn nft_is_valid(&self, token_id: TokenId) -> bool {
+        assert!(self.tokens_by_id.contains_key(&token_id), "No token");
+        let metadata = self.internal_token_metadata(&token_id);
+        has_started(&metadata) && !is_expired(&metadata)
+    }
+
+    //extend when a token expires. Only the contract owner or the creator of the token or its series can renew it,
+    //and only while neither the token nor its series are frozen
+    #[payable]
+    pub fn nft_renew(&mut self, token_id: TokenId, expires_at: u64) {
+        //assert at least one yocto for security reasons and to pay for storage growth
+        assert_at_least_one_yocto();
+
+        //make sure the token isn't still being migrated
+        self.assert_migrated();
+
+        //get the token object and make sure the caller can renew it
+        let token = self.tokens_by_id.get(&token_id).expect("No token");
+        self.assert_metadata_updater(&token_id, token);
+        assert!(!self.internal_is_metadata_frozen(&token_id, token), "Token metadata is frozen");
+
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        //a renewal can only push the expiry further into the future
+        let metadata = self.token_metadata_by_id.get_mut(&token_id).unwrap();
+        assert!(expires_at > env::block_timestamp_ms(), "Token must expire in the future");
+        assert!(
+            metadata.expires_at.is_none_or(|current_expires_at| expires_at > current_expires_at),
+            "Token must expire later than it currently does"
+        );
+        metadata.expires_at = Some(expires_at);
+        metadata.updated_at = Some(env::block_timestamp_ms());
+
+        //log the update as per the events standard
+        log_nft_metadata_update(vec![token_id]);
+
+        //charge the caller for the extra storage used or refund them the storage that was released
+        self.refund_storage_difference(initial_storage_usage);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::get_logs;
+
+    const TOKEN_ID: &str = "pass";
+
+    //a contract with a pass owned by alice that is valid from 1000 until 2000
+    fn setup_pass() -> ArtEcho {
+        let mut contract = setup_contract();
+        let mut metadata = sample_metadata();
+        metadata.starts_at = Some(1000);
+        metadata.expires_at = Some(2000);
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint(TOKEN_ID.to_string(), metadata, account("alice.near"), None, None);
+        contract
+    }
+
+    #[test]
+    fn token_is_valid_between_starts_at_and_expires_at() {
+        let contract = setup_pass();
+
+        for (timestamp_ms, is_valid) in [(999, false), (1000, true), (1999, true), (2000, false)] {
+            set_context_at(account("alice.near"), NearToken::from_yoctonear(0), timestamp_ms);
+            assert_eq!(contract.nft_is_valid(TOKEN_ID.to_string()), is_valid);
+        }
+    }
+
+    #[test]
+    fn token_without_validity_is_always_valid() {
+        let mut contract = setup_contract();
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint(TOKEN_ID.to_string(), sample_metadata(), account("alice.near"), None, None);
+
+        set_context_at(account("alice.near"), NearToken::from_yoctonear(0), 4_000_000_000_000);
+        assert!(contract.nft_is_valid(TOKEN_ID.to_string()));
+    }
+
+    #[test]
+    fn expired_token_can_be_transferred_without_enforcement() {
+        let mut contract = setup_pass();
+
+        set_context_at(account("alice.near"), ONE_YOCTONEAR, 3000);
+        contract.nft_transfer(account("bob.near"), TOKEN_ID.to_string(), None, None);
+        assert_eq!(contract.nft_token(TOKEN_ID.to_string()).unwrap().owner_id, account("bob.near"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Token has expired")]
+    fn expired_token_cannot_be_transferred_with_enforcement() {
+        let mut contract = setup_pass();
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.set_enforce_validity(true);
+
+        //the pass can be transferred before the exhibition starts
+        set_context_at(account("alice.near"), ONE_YOCTONEAR, 500);
+        contract.nft_transfer(account("bob.near"), TOKEN_ID.to_string(), None, None);
+
+        set_context_at(account("bob.near"), ONE_YOCTONEAR, 2000);
+        contract.nft_transfer(account("alice.near"), TOKEN_ID.to_string(), None, None);
+    }
+
+    #[test]
+    fn issuer_renews_expired_token() {
+        let mut contract = setup_pass();
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.set_enforce_validity(true);
+
+        set_context_at(owner(), NearToken::from_millinear(10), 2500);
+        contract.nft_renew(TOKEN_ID.to_string(), 5000);
+
+        assert!(contract.nft_is_valid(TOKEN_ID.to_string()));
+        let metadata = contract.nft_token(TOKEN_ID.to_string()).unwrap().metadata;
+        assert_eq!(metadata.expires_at, Some(5000));
+        assert_eq!(metadata.updated_at, Some(2500));
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"nep171","version":"1.1.0","event":"nft_metadata_update","data":[{"token_ids":["pass"]}]}"#]
+        );
+
+        set_context_at(account("alice.near"), ONE_YOCTONEAR, 2500);
+        contract.nft_transfer(account("bob.near"), TOKEN_ID.to_string(), None, None);
+    }
+
+    #[test]
+    fn creator_renews_one_off_token() {
+        let mut contract = setup_contract();
+        contract.minters.push(account("artist.near"));
+        let mut metadata = sample_metadata();
+        metadata.expires_at = Some(2000);
+        set_context(account("artist.near"), NearToken::from_near(1));
+        contract.nft_mint(TOKEN_ID.to_string(), metadata, account("alice.near"), None, None);
+
+        set_context_at(account("artist.near"), NearToken::from_millinear(10), 2500);
+        contract.nft_renew(TOKEN_ID.to_string(), 5000);
+
+        assert_eq!(contract.nft_token(TOKEN_ID.to_string()).unwrap().metadata.expires_at, Some(5000));
+        assert!(contract.nft_is_valid(TOKEN_ID.to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Token must expire later than it currently does")]
+    fn renewal_cannot_shorten_expiry() {
+        let mut contract = setup_pass();
+
+        set_context_at(owner(), ONE_YOCTONEAR, 1500);
+        contract.nft_renew(TOKEN_ID.to_string(), 1800);
+    }
+
+    #[test]
+    #[should_panic(expected = "Predecessor must be the contract owner or the token creator.")]
+    fn holder_cannot_renew_token() {
+        let mut contract = setup_pass();
+
+        set_context_at(account("alice.near"), ONE_YOCTONEAR, 2500);
+        contract.nft_renew(TOKEN_ID.to_string(), 5000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Predecessor must be the contract owner.")]
+    fn only_owner_enforces_validity() {
+        let mut contract = setup_pass();
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.set_enforce_validity(true);
+    }
+}
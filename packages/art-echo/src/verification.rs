@@ -0,0 +1,155 @@
+//nft_mint_verified takes every argument of nft_mint and the content to verify
+#![allow(clippy::too_many_arguments)]
+
+use crate::*;
+
+//make sure the sha256 hash of the content is the hash that was declared for it in the metadata
+pub(crate) fn assert_content_hash(content: &[u8], declared_hash: Option<&Base64VecU8>, field: &str) {
+    let declared_hash = declared_hash
+        .unwrap_or_else(|| env::panic_str(&format!("{}_hash is required to verify the {}", field, field)));
+    assert!(
+        env::sha256(content) == declared_hash.0,
+        "{}_hash doesn't match the sha256 hash of the {}", field, field
+    );
+}
+
+#[near]
+impl ArtEcho {
+    /*
+        mint a token after checking that the media and reference bytes that are passed in hash to the
+        media_hash and reference_hash of the metadata. The bytes are only hashed, they aren't stored.
+        Apart from the check, this is the same as nft_mint
+    */
+    #[payable]
+    pub fn nft_mint_verified(
+        &mut self,
+        token_id: TokenId,
+        metadata: TokenMetadata,
+        receiver_id: AccountId,
+        perpetual_royalties: Option<HashMap<AccountId, u32>>,
+        transferable: Option<bool>,
+        //the raw bytes of the media
+        media_bytes: Option<Base64VecU8>,
+        //the raw bytes of the JSON file the reference points to
+        reference_bytes: Option<Base64VecU8>,
+    ) -> PromiseOrValue<bool> {
+        //make sure mints aren't stopped before hashing the bytes
+        self.assert_not_paused();
+
+        //there has to be something to verify
+        assert!(
+            media_bytes.is_some() || reference_bytes.is_some(),
+            "Must pass the media or reference bytes to verify"
+        );
+
+        //reject the mint if any of the hashes don't match
+        if let Some(media_bytes) = media_bytes {
+            assert_content_hash(&media_bytes.0, metadata.media_hash.as_ref(), "media");
+        }
+        if let Some(reference_bytes) = reference_bytes {
+            assert_content_hash(&reference_bytes.0, metadata.reference_hash.as_ref(), "reference");
+        }
+
+        //the content is verified so we can mint the token
+        self.nft_mint(token_id, metadata, receiver_id, perpetual_royalties, transferable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    const TOKEN_ID: &str = "svg";
+    const MEDIA: &[u8] = b"<svg xmlns=\"http://www.w3.org/2000/svg\"><circle r=\"4\"/></svg>";
+    const REFERENCE: &[u8] = b"{\"artist\":\"alice.near\"}";
+
+    fn verified_metadata() -> TokenMetadata {
+        let mut metadata = sample_metadata();
+        metadata.media_hash = Some(Base64VecU8(env::sha256(MEDIA)));
+        metadata.reference_hash = Some(Base64VecU8(env::sha256(REFERENCE)));
+        metadata
+    }
+
+    #[test]
+    fn mint_with_matching_hashes() {
+        let mut contract = setup_contract();
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint_verified(
+            TOKEN_ID.to_string(),
+            verified_metadata(),
+            account("alice.near"),
+            None,
+            None,
+            Some(Base64VecU8(MEDIA.to_vec())),
+            Some(Base64VecU8(REFERENCE.to_vec())),
+        );
+
+        let token = contract.nft_token(TOKEN_ID.to_string()).unwrap();
+        assert_eq!(token.owner_id, account("alice.near"));
+        assert_eq!(token.metadata.media_hash.unwrap().0, env::sha256(MEDIA));
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn paused_contract_blocks_verified_mints() {
+        let mut contract = setup_contract();
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.nft_pause(None);
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint_verified(
+            TOKEN_ID.to_string(),
+            verified_metadata(),
+            account("alice.near"),
+            None,
+            None,
+            Some(Base64VecU8(MEDIA.to_vec())),
+            None,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "media_hash doesn't match the sha256 hash of the media")]
+    fn mint_with_tampered_media_is_rejected() {
+        let mut contract = setup_contract();
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint_verified(
+            TOKEN_ID.to_string(),
+            verified_metadata(),
+            account("alice.near"),
+            None,
+            None,
+            Some(Base64VecU8(b"<svg></svg>".to_vec())),
+            None,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "reference_hash doesn't match the sha256 hash of the reference")]
+    fn mint_with_tampered_reference_is_rejected() {
+        let mut contract = setup_contract();
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint_verified(
+            TOKEN_ID.to_string(),
+            verified_metadata(),
+            account("alice.near"),
+            None,
+            None,
+            Some(Base64VecU8(MEDIA.to_vec())),
+            Some(Base64VecU8(b"{}".to_vec())),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Must pass the media or reference bytes to verify")]
+    fn mint_without_content_is_rejected() {
+        let mut contract = setup_contract();
+
+        set_context(owner(), NearToken::from_near(1));
+        contract.nft_mint_verified(TOKEN_ID.to_string(), verified_metadata(), account("alice.near"), None, None, None, None);
+    }
+}
@@ -0,0 +1,379 @@
+use crate::*;
+use near_sdk::{ext_contract, log, Gas, PromiseResult};
+
+const GAS_FOR_NFT_TOKEN: Gas = Gas::from_tgas(5);
+const GAS_FOR_RESOLVE_WRAP: Gas = Gas::from_tgas(12);
+const GAS_FOR_NFT_TRANSFER: Gas = Gas::from_tgas(15);
+const GAS_FOR_RESOLVE_RELEASE: Gas = Gas::from_tgas(5);
+
+//the delimiter between the contract ID and the token ID of the original in the token IDs of wrapped tokens,
+//e.g. "gallery.near@42". Account IDs can't contain it, so the contract ID always ends at the first one
+pub const WRAP_DELIMITER: char = '@';
+
+#[allow(dead_code)]
+#[ext_contract(ext_nft_contract)]
+pub(crate) trait NonFungibleToken {
+    //view call on the contract of the original to get the token
+    fn nft_token(&self, token_id: TokenId);
+    //transfers the original from the contract to the receiver
+    fn nft_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u32>,
+        memo: Option<String>,
+    );
+}
+
+//the original token that is locked in the contract for a wrapped token
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug, NearSchema,
+)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct WrappedToken {
+    //the NEP-171 contract of the original
+    pub contract_id: AccountId,
+    //the token ID of the original on its contract
+    pub token_id: TokenId,
+}
+
+//the part of a token returned by nft_token on the contract of the original that is needed to wrap it
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+struct OriginalToken {
+    owner_id: AccountId,
+    metadata: Option<TokenMetadata>,
+}
+
+//get the token ID of the wrapped token for an original
+pub(crate) fn wrapped_token_id(wrapped: &WrappedToken) -> TokenId {
+    format!(
+        "{}{}{}",
+        wrapped.contract_id, WRAP_DELIMITER, wrapped.token_id
+    )
+}
+
+#[near]
+impl ArtEcho {
+    /*
+        called by a wrap contract during nft_transfer_call to lock the token that was sent to this contract.
+        A wrapped token whose metadata points back to the original is minted to the previous owner, who pays
+        for its storage from their storage balance. Returns true if the original should be returned instead
+    */
+    pub fn nft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        previous_owner_id: AccountId,
+        token_id: TokenId,
+        msg: String,
+    ) -> PromiseOrValue<bool> {
+        //make sure the token was sent by a contract whose tokens can be wrapped
+        let contract_id = env::predecessor_account_id();
+        assert!(
+            self.wrap_contracts.contains(&contract_id),
+            "Tokens of @{} can't be wrapped",
+            contract_id
+        );
+        self.assert_not_paused();
+        log!(
+            "@{} sent {} to be wrapped for @{}: {}",
+            sender_id,
+            token_id,
+            previous_owner_id,
+            msg
+        );
+
+        // Ask the contract for the original and resolve the wrap on our own contract
+        ext_nft_contract::ext(contract_id.clone())
+            .with_static_gas(GAS_FOR_NFT_TOKEN)
+            .nft_token(token_id.clone())
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_WRAP)
+                    .nft_resolve_wrap(
+                        WrappedToken {
+                            contract_id,
+                            token_id,
+                        },
+                        previous_owner_id,
+                    ),
+            )
+            .into()
+    }
+
+    //resolves the lookup of the original in nft_on_transfer. Mints the wrapped token if the original is locked
+    //in this contract and the owner's storage balance covers it. Returns true if the original should be returned
+    #[private]
+    pub fn nft_resolve_wrap(&mut self, wrapped: WrappedToken, owner_id: AccountId) -> bool {
+        //the contract of the original has to have answered with the token
+        let original = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice::<Option<OriginalToken>>(&value)
+                    .ok()
+                    .flatten()
+            }
+            _ => None,
+        };
+        let Some(original) = original else {
+            log!(
+                "{} was not found on @{}, returning it",
+                wrapped.token_id,
+                wrapped.contract_id
+            );
+            return true;
+        };
+
+        //the original has to be locked in this contract and not be wrapped already
+        let token_id = wrapped_token_id(&wrapped);
+        if original.owner_id != env::current_account_id()
+            || self.tokens_by_id.contains_key(&token_id)
+            || self.paused
+        {
+            log!("{} can't be wrapped, returning it", token_id);
+            return true;
+        }
+
+        //the metadata of the original is copied, with extra pointing back to the original
+        let mut metadata = original.metadata.unwrap_or(TokenMetadata {
+            title: None,
+            description: None,
+            media: None,
+            media_hash: None,
+            copies: None,
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            updated_at: None,
+            extra: None,
+            reference: None,
+            reference_hash: None,
+        });
+        metadata.extra =
+            Some(near_sdk::serde_json::json!({ "wrapped_from": &wrapped }).to_string());
+
+        //measure the initial storage being used on the contract
+        let initial_storage_usage = self.internal_storage_usage();
+
+        //mint the wrapped token to the owner of the original. The contract of the original is its creator
+        let token = self.internal_mint(
+            token_id.clone(),
+            metadata,
+            owner_id.clone(),
+            None,
+            None,
+            wrapped.contract_id.clone(),
+        );
+        self.wrapped_tokens.insert(token_id.clone(), wrapped);
+
+        //calculate the cost of the storage which was the used - initial
+        let required_storage_in_bytes = self.internal_storage_usage() - initial_storage_usage;
+        let required_cost =
+            env::storage_byte_cost().saturating_mul(required_storage_in_bytes.into());
+
+        //no deposit can be attached to nft_transfer_call, so the storage has to be covered by the owner's storage balance
+        if !self.internal_debit_storage(&owner_id, required_cost) {
+            self.wrapped_tokens.remove(&token_id);
+            self.internal_undo_mint(&token, &token_id);
+
+            log!(
+                "@{} must have {} yoctoNEAR of storage available to wrap a token, returning it",
+                owner_id,
+                required_cost
+            );
+            return true;
+        }
+
+        //the wrap is final, so we log it
+        log_nft_mint(&owner_id, vec![token_id]);
+        false
+    }
+
+    //retry releasing the original of a burned wrapped token whose release failed, e.g. because its contract was paused
+    #[payable]
+    pub fn nft_release(&mut self, token_id: TokenId) {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+
+        let (wrapped, receiver_id) = self
+            .unreleased_tokens
+            .remove(&token_id)
+            .expect("No unreleased token");
+        self.internal_release_wrapped(token_id, wrapped, receiver_id);
+    }
+
+    //resolves the transfer of the original of a burned wrapped token. If it failed, the original is kept
+    //track of so the release can be retried with nft_release. Returns true if the original was released
+    #[private]
+    pub fn nft_resolve_release(
+        &mut self,
+        token_id: TokenId,
+        wrapped: WrappedToken,
+        receiver_id: AccountId,
+    ) -> bool {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return true;
+        }
+
+        log!(
+            "{} could not be released to @{}, it can be retried with nft_release",
+            token_id,
+            receiver_id
+        );
+        self.unreleased_tokens
+            .insert(token_id, (wrapped, receiver_id));
+        false
+    }
+
+    //get the original that is locked for a wrapped token
+    pub fn nft_wrapped_token(&self, token_id: TokenId) -> Option<WrappedToken> {
+        self.wrapped_tokens.get(&token_id).cloned()
+    }
+}
+
+impl ArtEcho {
+    //transfer the original of a burned wrapped token out of the contract to the receiver
+    pub(crate) fn internal_release_wrapped(
+        &self,
+        token_id: TokenId,
+        wrapped: WrappedToken,
+        receiver_id: AccountId,
+    ) -> Promise {
+        ext_nft_contract::ext(wrapped.contract_id.clone())
+            .with_attached_deposit(ONE_YOCTONEAR)
+            .with_static_gas(GAS_FOR_NFT_TRANSFER)
+            .nft_transfer(
+                receiver_id.clone(),
+                wrapped.token_id.clone(),
+                None,
+                Some("unwrapped".to_string()),
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_RELEASE)
+                    .nft_resolve_release(token_id, wrapped, receiver_id),
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+    use near_sdk::{testing_env, RuntimeFeesConfig};
+
+    const ORIGINAL_ID: &str = "42";
+    const WRAPPED_ID: &str = "gallery.near@42";
+
+    fn original() -> WrappedToken {
+        WrappedToken {
+            contract_id: account("gallery.near"),
+            token_id: ORIGINAL_ID.to_string(),
+        }
+    }
+
+    //a contract that wraps the tokens of gallery.near, where alice deposited 1 NEAR of storage
+    fn setup_wrapper() -> ArtEcho {
+        let mut contract = setup_contract();
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.add_wrap_contract(account("gallery.near"));
+        set_context(account("alice.near"), NearToken::from_near(1));
+        contract.storage_deposit(None, None);
+        contract
+    }
+
+    //resolves a lookup of the original that answered with the token owned by `owner_id`
+    fn resolve_wrap(contract: &mut ArtEcho, owner_id: &str) -> bool {
+        let token = near_sdk::serde_json::json!({
+            "token_id": ORIGINAL_ID,
+            "owner_id": owner_id,
+            "metadata": sample_metadata(),
+        });
+        testing_env!(
+            VMContextBuilder::new()
+                .current_account_id(account("art-echo.near"))
+                .build(),
+            near_sdk::test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(token.to_string().into_bytes())],
+        );
+        contract.nft_resolve_wrap(original(), account("alice.near"))
+    }
+
+    #[test]
+    fn locked_token_is_wrapped() {
+        let mut contract = setup_wrapper();
+
+        assert!(!resolve_wrap(&mut contract, "art-echo.near"));
+
+        let token = contract.nft_token(WRAPPED_ID.to_string()).unwrap();
+        assert_eq!(token.owner_id, account("alice.near"));
+        assert_eq!(token.metadata.title, Some("Token".to_string()));
+        assert_eq!(
+            token.metadata.extra,
+            Some(r#"{"wrapped_from":{"contract_id":"gallery.near","token_id":"42"}}"#.to_string())
+        );
+        assert_eq!(token.collection_id, None);
+        assert_eq!(
+            contract.nft_wrapped_token(WRAPPED_ID.to_string()),
+            Some(original())
+        );
+        assert!(
+            contract
+                .storage_balance_of(account("alice.near"))
+                .unwrap()
+                .available
+                < NearToken::from_near(1)
+        );
+    }
+
+    #[test]
+    fn token_that_is_not_locked_is_returned() {
+        let mut contract = setup_wrapper();
+
+        assert!(resolve_wrap(&mut contract, "alice.near"));
+        assert!(contract.nft_token(WRAPPED_ID.to_string()).is_none());
+    }
+
+    #[test]
+    fn token_is_returned_without_storage_balance() {
+        let mut contract = setup_contract();
+        set_context(owner(), ONE_YOCTONEAR);
+        contract.add_wrap_contract(account("gallery.near"));
+
+        assert!(resolve_wrap(&mut contract, "art-echo.near"));
+        assert!(contract.nft_token(WRAPPED_ID.to_string()).is_none());
+        assert!(contract.nft_wrapped_token(WRAPPED_ID.to_string()).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Tokens of @other.near can't be wrapped")]
+    fn only_wrap_contracts_can_send_tokens() {
+        let mut contract = setup_wrapper();
+
+        set_context(account("other.near"), NearToken::from_yoctonear(0));
+        contract.nft_on_transfer(
+            account("alice.near"),
+            account("alice.near"),
+            ORIGINAL_ID.to_string(),
+            String::new(),
+        );
+    }
+
+    #[test]
+    fn burn_releases_the_original() {
+        let mut contract = setup_wrapper();
+        resolve_wrap(&mut contract, "art-echo.near");
+
+        set_context(account("alice.near"), ONE_YOCTONEAR);
+        contract.nft_burn(WRAPPED_ID.to_string(), None);
+
+        assert!(contract.nft_wrapped_token(WRAPPED_ID.to_string()).is_none());
+        assert!(get_created_receipts()
+            .iter()
+            .any(|receipt| receipt.receiver_id == account("gallery.near")));
+    }
+}
@@ -0,0 +1,335 @@
+use near_sdk::serde_json::json;
+use near_workspaces::{types::NearToken, Account, Contract, DevNetwork, Worker};
+use tokio::{fs::File, io::{self, AsyncReadExt}};
+
+const ONE_YOCTO: NearToken = NearToken::from_yoctonear(1);
+const TOKEN_ID: &str = "artwork";
+
+async fn read_file(path: &str) -> io::Result<Vec<u8>> {
+  let mut f = File::open(path).await?;
+  let mut buffer: Vec<u8> = Vec::new();
+
+  // read the whole file
+  f.read_to_end(&mut buffer).await?;
+  Ok(buffer)
+}
+
+struct Env {
+    //the external NEP-171 contract the original is minted on
+    gallery_contract: Contract,
+    //the art-echo contract that wraps the tokens of the gallery
+    wrapper_contract: Contract,
+    collector: Account,
+}
+
+async fn init(worker: &Worker<impl DevNetwork>) -> anyhow::Result<Env> {
+    let wasm = read_file("../../compiled/art_echo.wasm").await?;
+    let gallery_contract = worker.dev_deploy(&wasm).await?;
+    let wrapper_contract = worker.dev_deploy(&wasm).await?;
+
+    for contract in [&gallery_contract, &wrapper_contract] {
+        let res = contract
+            .call("new_default_meta")
+            .args_json((contract.id(),))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(res.is_success());
+    }
+
+    let res = wrapper_contract
+        .call("add_wrap_contract")
+        .args_json((gallery_contract.id(),))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let collector = wrapper_contract
+        .as_account()
+        .create_subaccount("collector")
+        .initial_balance(NearToken::from_near(30))
+        .transact()
+        .await?
+        .into_result()?;
+
+    // The collector owns the original on the gallery and has storage on the wrapper to pay for the wrapped token.
+    let res = gallery_contract
+        .call("nft_mint")
+        .args_json(json!({
+            "token_id": TOKEN_ID,
+            "metadata": { "title": "Artwork" },
+            "receiver_id": collector.id(),
+        }))
+        .max_gas()
+        .deposit(NearToken::from_millinear(100))
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let res = collector
+        .call(wrapper_contract.id(), "storage_deposit")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_millinear(100))
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    Ok(Env { gallery_contract, wrapper_contract, collector })
+}
+
+async fn wrap(env: &Env) -> anyhow::Result<()> {
+    let res = env.collector
+        .call(env.gallery_contract.id(), "nft_transfer_call")
+        .args_json(json!({
+            "receiver_id": env.wrapper_contract.id(),
+            "token_id": TOKEN_ID,
+            "msg": "",
+        }))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    Ok(())
+}
+
+async fn nft_token(contract: &Contract, token_id: &str) -> anyhow::Result<near_sdk::serde_json::Value> {
+    let token: near_sdk::serde_json::Value = contract
+        .call("nft_token")
+        .args_json((token_id,))
+        .view()
+        .await?
+        .json()?;
+
+    Ok(token)
+}
+
+fn wrapped_token_id(env: &Env) -> String {
+    format!("{}@{}", env.gallery_contract.id(), TOKEN_ID)
+}
+
+#[tokio::test]
+async fn test_nft_transfer_call_locks_and_mints() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let env = init(&worker).await?;
+
+    wrap(&env).await?;
+
+    let original = nft_token(&env.gallery_contract, TOKEN_ID).await?;
+    assert_eq!(original["owner_id"], env.wrapper_contract.id().to_string());
+
+    let wrapped = nft_token(&env.wrapper_contract, &wrapped_token_id(&env)).await?;
+    assert_eq!(wrapped["owner_id"], env.collector.id().to_string());
+    assert_eq!(wrapped["metadata"]["title"], "Artwork");
+    assert_eq!(
+        wrapped["metadata"]["extra"],
+        json!({ "wrapped_from": { "contract_id": env.gallery_contract.id(), "token_id": TOKEN_ID } }).to_string()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_burn_releases_the_original() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let env = init(&worker).await?;
+
+    wrap(&env).await?;
+
+    let res = env.collector
+        .call(env.wrapper_contract.id(), "nft_burn")
+        .args_json(json!({ "token_id": wrapped_token_id(&env) }))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let original = nft_token(&env.gallery_contract, TOKEN_ID).await?;
+    assert_eq!(original["owner_id"], env.collector.id().to_string());
+    assert!(nft_token(&env.wrapper_contract, &wrapped_token_id(&env)).await?.is_null());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tokens_of_other_contracts_are_returned() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let env = init(&worker).await?;
+
+    let res = env.wrapper_contract
+        .call("remove_wrap_contract")
+        .args_json((env.gallery_contract.id(),))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    wrap(&env).await?;
+
+    let original = nft_token(&env.gallery_contract, TOKEN_ID).await?;
+    assert_eq!(original["owner_id"], env.collector.id().to_string());
+
+    Ok(())
+}
+
+struct RegistrarEnv {
+    //the art-spot registrar that assigns the Artist role
+    registrar_contract: Contract,
+    //the art-echo contract that asks the registrar before minting
+    art_echo_contract: Contract,
+    //a user of the registrar, registered as `artist` and owning `artist.<registrar>`
+    artist: Account,
+}
+
+async fn init_registrar(worker: &Worker<impl DevNetwork>) -> anyhow::Result<RegistrarEnv> {
+    let registrar_contract = worker.dev_deploy(&read_file("../../compiled/registrar.wasm").await?).await?;
+    let art_echo_contract = worker.dev_deploy(&read_file("../../compiled/art_echo.wasm").await?).await?;
+
+    let res = registrar_contract
+        .call("new")
+        .args_json(json!({ "owner_id": "owner", "staging_duration": 0 }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    // Users are kept under their name on the registrar, the account they sign with is a subaccount of it.
+    let res = registrar_contract
+        .call("storage_deposit")
+        .args_json(json!({ "account_id": "artist" }))
+        .max_gas()
+        .deposit(NearToken::from_millinear(100))
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let artist = registrar_contract
+        .as_account()
+        .create_subaccount("artist")
+        .initial_balance(NearToken::from_near(30))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let res = art_echo_contract
+        .call("new_default_meta")
+        .args_json((art_echo_contract.id(),))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let res = art_echo_contract
+        .call("set_registrar")
+        .args_json((registrar_contract.id(),))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    Ok(RegistrarEnv { registrar_contract, art_echo_contract, artist })
+}
+
+async fn set_roles(env: &RegistrarEnv, roles: &[&str]) -> anyhow::Result<()> {
+    let res = env.registrar_contract
+        .call("update_account")
+        .args_json(json!({ "id": "artist", "roles": roles }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    Ok(())
+}
+
+async fn mint_as_artist(env: &RegistrarEnv) -> anyhow::Result<bool> {
+    let minted: bool = env.artist
+        .call(env.art_echo_contract.id(), "nft_mint")
+        .args_json(json!({
+            "token_id": TOKEN_ID,
+            "metadata": { "title": "Artwork" },
+            "receiver_id": env.artist.id(),
+        }))
+        .max_gas()
+        .deposit(NearToken::from_millinear(100))
+        .transact()
+        .await?
+        .json()?;
+
+    Ok(minted)
+}
+
+#[tokio::test]
+async fn test_artist_mints_through_the_registrar() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let env = init_registrar(&worker).await?;
+
+    set_roles(&env, &["Artist"]).await?;
+
+    let has_role: bool = env.registrar_contract
+        .view("has_role")
+        .args_json(json!({ "id": env.artist.id(), "role": "Artist" }))
+        .await?
+        .json()?;
+    assert!(has_role);
+
+    assert!(mint_as_artist(&env).await?);
+
+    let token = nft_token(&env.art_echo_contract, TOKEN_ID).await?;
+    assert_eq!(token["owner_id"], env.artist.id().to_string());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_non_artist_mint_is_refunded() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let env = init_registrar(&worker).await?;
+
+    // Accounts outside the registrar are answered `false` instead of failing the call.
+    let has_role: bool = env.registrar_contract
+        .view("has_role")
+        .args_json(json!({ "id": env.art_echo_contract.id(), "role": "Artist" }))
+        .await?
+        .json()?;
+    assert!(!has_role);
+
+    assert!(!mint_as_artist(&env).await?);
+    assert!(nft_token(&env.art_echo_contract, TOKEN_ID).await?.is_null());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_creator_verification_asks_the_registrar() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let env = init_registrar(&worker).await?;
+
+    // Both a registered user and an account outside the registrar get an answer instead of a failed check.
+    for account_id in [env.artist.id(), env.art_echo_contract.id()] {
+        let res = env.artist
+            .call(env.art_echo_contract.id(), "nft_verify_creator")
+            .args_json(json!({ "account_id": account_id }))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(res.is_success());
+        assert!(!res.json::<bool>()?);
+
+        let is_verified: bool = env.registrar_contract
+            .view("is_verified")
+            .args_json(json!({ "id": account_id }))
+            .await?
+            .json()?;
+        assert!(!is_verified);
+    }
+
+    Ok(())
+}
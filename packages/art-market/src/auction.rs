@@ -0,0 +1,435 @@
+use crate::*;
+
+/// How the price of an auction is found.
+#[derive(Clone, Debug, PartialEq)]
+#[near(serializers = [borsh, json])]
+#[serde(rename_all = "snake_case")]
+pub enum AuctionKind {
+    /// Bids go up until the auction ends and the highest bidder wins.
+    English {
+        /// The lowest first bid
+        reserve_price: NearToken,
+        /// How much every bid has to be above the previous one
+        min_increment: NearToken,
+        /// A bid placed less than this many milliseconds before the end extends the auction to this many milliseconds
+        /// after the bid, so there is always time to outbid it
+        extension_ms: u64,
+    },
+    /// The price goes down from `start_price` at the start to `end_price` at the end and the first buyer wins.
+    Dutch {
+        start_price: NearToken,
+        end_price: NearToken,
+        /// The price drops every this many milliseconds instead of continuously, if set
+        step_ms: Option<u64>,
+    },
+}
+
+/// The highest bid of an English auction, escrowed by the market.
+#[derive(Clone, Debug)]
+#[near(serializers = [borsh, json])]
+pub struct Bid {
+    pub bidder_id: AccountId,
+    pub amount: NearToken,
+}
+
+#[derive(Clone, Debug)]
+#[near(serializers = [borsh, json])]
+pub struct Auction {
+    /// ID of the auction
+    pub auction_id: u64,
+    /// The account the token was escrowed from, which is paid out when the auction is settled
+    pub seller_id: AccountId,
+    /// The NFT contract the token lives on
+    pub nft_contract_id: AccountId,
+    /// The token that is auctioned, owned by the market until the auction is settled
+    pub token_id: TokenId,
+    pub kind: AuctionKind,
+    /// When bidding opens, in milliseconds since the Unix epoch
+    pub start_at: u64,
+    /// When bidding closes, in milliseconds since the Unix epoch
+    pub end_at: u64,
+    /// The highest bid of an English auction so far
+    pub highest_bid: Option<Bid>,
+}
+
+impl Auction {
+    /// The price of a Dutch auction at `timestamp_ms`, or the lowest bid an English auction accepts.
+    pub fn price_at(&self, timestamp_ms: u64) -> NearToken {
+        match &self.kind {
+            AuctionKind::English { reserve_price, min_increment, .. } => match &self.highest_bid {
+                Some(bid) => bid.amount.saturating_add(*min_increment),
+                None => *reserve_price,
+            },
+            AuctionKind::Dutch { start_price, end_price, step_ms } => {
+                if timestamp_ms <= self.start_at {
+                    return *start_price;
+                }
+                if timestamp_ms >= self.end_at {
+                    return *end_price;
+                }
+
+                let mut elapsed = timestamp_ms - self.start_at;
+                if let Some(step_ms) = step_ms {
+                    elapsed -= elapsed % step_ms;
+                }
+
+                // Split the multiplication so it can't overflow for any price or duration.
+                let duration = u128::from(self.end_at - self.start_at);
+                let elapsed = u128::from(elapsed);
+                let range = start_price.as_yoctonear() - end_price.as_yoctonear();
+                let decay = range / duration * elapsed + range % duration * elapsed / duration;
+
+                start_price.saturating_sub(NearToken::from_yoctonear(decay))
+            }
+        }
+    }
+}
+
+#[near]
+impl ArtMarket {
+    /// Bids on an English auction with the attached deposit. The deposit is escrowed until the bid is outbid,
+    /// when it's refunded, or the auction is settled.
+    #[payable]
+    pub fn bid(&mut self, auction_id: u64) {
+        let mut auction = self.auctions.get(&auction_id).expect("No auction");
+        let bidder_id = env::predecessor_account_id();
+        let amount = env::attached_deposit();
+        let now = env::block_timestamp_ms();
+
+        let extension_ms = match auction.kind {
+            AuctionKind::English { extension_ms, .. } => extension_ms,
+            AuctionKind::Dutch { .. } => env::panic_str("Auction is a Dutch auction, use buy"),
+        };
+        require!(now >= auction.start_at, "Auction hasn't started");
+        require!(now < auction.end_at, "Auction has ended");
+        require!(auction.seller_id != bidder_id, "Cannot bid on your own auction.");
+        require!(
+            amount >= auction.price_at(now),
+            format!("Bid must be at least {}", auction.price_at(now))
+        );
+
+        // Return the escrow of the bid that was outbid.
+        if let Some(outbid) = auction.highest_bid.replace(Bid { bidder_id, amount }) {
+            Promise::new(outbid.bidder_id).transfer(outbid.amount);
+        }
+
+        if auction.end_at - now < extension_ms {
+            auction.end_at = now + extension_ms;
+        }
+
+        self.auctions.insert(&auction_id, &auction);
+    }
+
+    /// Buys the token of a Dutch auction at its current price. The part of the attached deposit
+    /// above the price is refunded.
+    #[payable]
+    pub fn buy(&mut self, auction_id: u64) -> Promise {
+        let auction = self.auctions.get(&auction_id).expect("No auction");
+        let buyer_id = env::predecessor_account_id();
+        let deposit = env::attached_deposit();
+        let now = env::block_timestamp_ms();
+
+        require!(
+            matches!(auction.kind, AuctionKind::Dutch { .. }),
+            "Auction is an English auction, use bid"
+        );
+        require!(now >= auction.start_at, "Auction hasn't started");
+        require!(now < auction.end_at, "Auction has ended");
+        require!(auction.seller_id != buyer_id, "Cannot bid on your own auction.");
+
+        let price = auction.price_at(now);
+        require!(deposit >= price, "Attached deposit must be greater than or equal to the current price");
+
+        let refund = deposit.saturating_sub(price);
+        if refund > NO_DEPOSIT {
+            Promise::new(buyer_id.clone()).transfer(refund);
+        }
+
+        let auction = self.internal_remove_auction(auction_id);
+        self.internal_settle_auction(auction, Bid { bidder_id: buyer_id, amount: price })
+    }
+
+    /// Settles an auction once it has ended. Anyone can call this. The token goes to the highest bidder and
+    /// the bid is paid out through `nft_transfer_payout`, or the token goes back to the seller if nobody bid.
+    pub fn settle(&mut self, auction_id: u64) -> Promise {
+        let auction = self.auctions.get(&auction_id).expect("No auction");
+        require!(env::block_timestamp_ms() >= auction.end_at, "Auction hasn't ended");
+
+        let mut auction = self.internal_remove_auction(auction_id);
+        match auction.highest_bid.take() {
+            Some(bid) => self.internal_settle_auction(auction, bid),
+            None => ext_nft_contract::ext(auction.nft_contract_id)
+                .with_attached_deposit(ONE_YOCTONEAR)
+                .with_static_gas(GAS_FOR_NFT_TRANSFER)
+                .nft_transfer(auction.seller_id, auction.token_id, None, Some("unsold at auction".to_string())),
+        }
+    }
+
+    /// Pays out a settled auction once the token has been transferred to the winner. The share of the payout that
+    /// goes to the market as the owner of the token is paid to the seller. If the transfer failed, the winner is
+    /// refunded and the token is returned to the seller. If the payout is invalid, the seller is paid the whole price.
+    #[private]
+    pub fn resolve_auction(
+        &mut self,
+        seller_id: AccountId,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        bidder_id: AccountId,
+        price: U128,
+    ) -> U128 {
+        let Some(value) = promise_result_as_success() else {
+            Promise::new(bidder_id).transfer(NearToken::from_yoctonear(price.0));
+            ext_nft_contract::ext(nft_contract_id)
+                .with_attached_deposit(ONE_YOCTONEAR)
+                .with_static_gas(GAS_FOR_NFT_TRANSFER)
+                .nft_transfer(seller_id, token_id, None, Some("auction failed".to_string()));
+            return price;
+        };
+
+        // The token now belongs to the winner, so the seller is paid even if the payout can't be used.
+        let payout = parse_payout(&value, price.0).unwrap_or_else(|| HashMap::from([(seller_id.clone(), price)]));
+
+        for (receiver_id, amount) in payout {
+            let receiver_id = if receiver_id == env::current_account_id() { seller_id.clone() } else { receiver_id };
+            Promise::new(receiver_id).transfer(NearToken::from_yoctonear(amount.0));
+        }
+
+        price
+    }
+}
+
+impl ArtMarket {
+    /// Starts an auction of a token the market was just transferred and returns its ID.
+    pub(crate) fn internal_start_auction(
+        &mut self,
+        seller_id: AccountId,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        kind: AuctionKind,
+        start_at: Option<u64>,
+        end_at: u64,
+    ) -> u64 {
+        let start_at = start_at.unwrap_or_else(env::block_timestamp_ms);
+        require!(end_at > start_at && end_at > env::block_timestamp_ms(), "Auction must end after it starts");
+
+        match &kind {
+            AuctionKind::English { min_increment, .. } => {
+                require!(*min_increment > NO_DEPOSIT, "Minimum increment must be greater than 0");
+            }
+            AuctionKind::Dutch { start_price, end_price, step_ms } => {
+                require!(start_price > end_price, "Start price must be greater than the end price");
+                require!(step_ms.is_none_or(|step_ms| step_ms > 0), "Price step must be greater than 0");
+            }
+        }
+
+        // Make sure the seller has covered the storage of all their sales, offers and auctions, including this one.
+        self.internal_assert_storage_for_new_item(&seller_id);
+
+        let auction_id = self.next_auction_id;
+        self.next_auction_id += 1;
+
+        self.internal_add_auction(Auction {
+            auction_id,
+            seller_id,
+            nft_contract_id,
+            token_id,
+            kind,
+            start_at,
+            end_at,
+            highest_bid: None,
+        });
+
+        auction_id
+    }
+
+    /// Transfers the token of an auction to the winner through `nft_transfer_payout` and resolves the payout.
+    fn internal_settle_auction(&mut self, auction: Auction, bid: Bid) -> Promise {
+        let price = U128(bid.amount.as_yoctonear());
+        let callback = Self::ext(env::current_account_id())
+            .with_static_gas(GAS_FOR_RESOLVE_PURCHASE)
+            .resolve_auction(
+                auction.seller_id,
+                auction.nft_contract_id.clone(),
+                auction.token_id.clone(),
+                bid.bidder_id.clone(),
+                price,
+            );
+
+        self.internal_process_purchase(auction.nft_contract_id, auction.token_id, None, bid.bidder_id, price, callback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::{testing_env, PromiseResult, RuntimeFeesConfig};
+
+    fn seller() -> AccountId {
+        account("seller.near")
+    }
+
+    fn bidder() -> AccountId {
+        account("bidder.near")
+    }
+
+    fn dutch(step_ms: Option<u64>) -> Auction {
+        Auction {
+            auction_id: 0,
+            seller_id: seller(),
+            nft_contract_id: account("echo.near"),
+            token_id: "1".to_string(),
+            kind: AuctionKind::Dutch {
+                start_price: NearToken::from_near(10),
+                end_price: NearToken::from_near(2),
+                step_ms,
+            },
+            start_at: 1_000,
+            end_at: 5_000,
+            highest_bid: None,
+        }
+    }
+
+    /// A market with an English auction by seller.near from 0 to 10 000 ms that extends by 1 000 ms.
+    fn setup_english() -> (ArtMarket, u64) {
+        let mut contract = setup_market();
+
+        set_context(seller(), contract.storage_minimum_balance());
+        contract.storage_deposit(None);
+
+        set_context(account("echo.near"), NO_DEPOSIT);
+        let kind = AuctionKind::English {
+            reserve_price: NearToken::from_near(1),
+            min_increment: NearToken::from_near(1),
+            extension_ms: 1_000,
+        };
+        let auction_id =
+            contract.internal_start_auction(seller(), account("echo.near"), "1".to_string(), kind, None, 10_000);
+        (contract, auction_id)
+    }
+
+    #[test]
+    fn dutch_price_decays_linearly() {
+        let auction = dutch(None);
+
+        assert_eq!(auction.price_at(0), NearToken::from_near(10));
+        assert_eq!(auction.price_at(1_000), NearToken::from_near(10));
+        assert_eq!(auction.price_at(2_000), NearToken::from_near(8));
+        assert_eq!(auction.price_at(3_000), NearToken::from_near(6));
+        assert_eq!(auction.price_at(3_500), NearToken::from_near(5));
+        assert_eq!(auction.price_at(5_000), NearToken::from_near(2));
+        assert_eq!(auction.price_at(9_000), NearToken::from_near(2));
+    }
+
+    #[test]
+    fn dutch_price_drops_in_steps() {
+        let auction = dutch(Some(1_000));
+
+        assert_eq!(auction.price_at(1_999), NearToken::from_near(10));
+        assert_eq!(auction.price_at(2_000), NearToken::from_near(8));
+        assert_eq!(auction.price_at(3_500), NearToken::from_near(6));
+        assert_eq!(auction.price_at(4_999), NearToken::from_near(4));
+    }
+
+    #[test]
+    fn dutch_price_does_not_overflow() {
+        let mut auction = dutch(None);
+        auction.kind = AuctionKind::Dutch {
+            start_price: NearToken::from_yoctonear(u128::MAX),
+            end_price: NO_DEPOSIT,
+            step_ms: None,
+        };
+
+        assert_eq!(auction.price_at(3_000), NearToken::from_yoctonear(u128::MAX - u128::MAX / 2));
+    }
+
+    #[test]
+    fn late_bid_extends_the_auction() {
+        let (mut contract, auction_id) = setup_english();
+
+        set_context_at(bidder(), NearToken::from_near(1), 9_500);
+        contract.bid(auction_id);
+
+        assert_eq!(contract.get_auction(auction_id).unwrap().end_at, 10_500);
+    }
+
+    #[test]
+    fn early_bid_does_not_extend_the_auction() {
+        let (mut contract, auction_id) = setup_english();
+
+        set_context_at(bidder(), NearToken::from_near(1), 9_000);
+        contract.bid(auction_id);
+
+        assert_eq!(contract.get_auction(auction_id).unwrap().end_at, 10_000);
+    }
+
+    #[test]
+    fn extended_auction_can_be_outbid() {
+        let (mut contract, auction_id) = setup_english();
+
+        set_context_at(bidder(), NearToken::from_near(1), 9_500);
+        contract.bid(auction_id);
+
+        set_context_at(account("other.near"), NearToken::from_near(2), 10_200);
+        contract.bid(auction_id);
+
+        let auction = contract.get_auction(auction_id).unwrap();
+        assert_eq!(auction.end_at, 11_200);
+        assert_eq!(auction.highest_bid.unwrap().bidder_id, account("other.near"));
+        assert_eq!(transferred_to(&bidder()), NearToken::from_near(1));
+    }
+
+    #[test]
+    fn invalid_payout_of_auction_pays_the_seller() {
+        let mut contract = setup_market();
+
+        testing_env!(
+            VMContextBuilder::new().current_account_id(account("market.near")).build(),
+            near_sdk::test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(b"{}".to_vec())]
+        );
+        contract.resolve_auction(seller(), account("echo.near"), "1".to_string(), bidder(), U128(5));
+
+        assert_eq!(transferred_to(&seller()), NearToken::from_yoctonear(5));
+        assert_eq!(transferred_to(&bidder()), NO_DEPOSIT);
+    }
+
+    #[test]
+    fn failed_transfer_of_auction_refunds_the_winner() {
+        let mut contract = setup_market();
+
+        testing_env!(
+            VMContextBuilder::new().current_account_id(account("market.near")).build(),
+            near_sdk::test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        contract.resolve_auction(seller(), account("echo.near"), "1".to_string(), bidder(), U128(5));
+
+        assert_eq!(transferred_to(&bidder()), NearToken::from_yoctonear(5));
+        assert_eq!(transferred_to(&seller()), NO_DEPOSIT);
+    }
+
+    #[test]
+    #[should_panic(expected = "previous_owner_id should be signer_id")]
+    fn only_the_owner_can_start_an_auction() {
+        let mut contract = setup_market();
+
+        // A call the bidder signed can't put the seller's token up for auction.
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(account("market.near"))
+            .predecessor_account_id(account("echo.near"))
+            .signer_account_id(bidder())
+            .build());
+        contract.nft_on_transfer(
+            bidder(),
+            seller(),
+            "1".to_string(),
+            r#"{"auction": {"english": {"reserve_price": "1", "min_increment": "1"}}, "end_at": 1}"#.to_string(),
+        );
+    }
+}
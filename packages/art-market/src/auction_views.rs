@@ -0,0 +1,64 @@
+use crate::*;
+
+#[near]
+impl ArtMarket {
+    /// Returns the auction with `auction_id`, if it hasn't been settled.
+    pub fn get_auction(&self, auction_id: u64) -> Option<Auction> {
+        self.auctions.get(&auction_id)
+    }
+
+    /// Returns the current price of a Dutch auction, or the lowest bid an English auction accepts.
+    pub fn get_auction_price(&self, auction_id: u64) -> NearToken {
+        self.auctions
+            .get(&auction_id)
+            .expect("No auction")
+            .price_at(env::block_timestamp_ms())
+    }
+
+    /// Returns the number of auctions that haven't been settled.
+    pub fn get_supply_auctions(&self) -> U64 {
+        U64(self.auctions.len())
+    }
+
+    /// Returns the auctions that haven't been settled using pagination.
+    pub fn get_auctions(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<Auction> {
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        self.auctions
+            .values()
+            .skip(start as usize)
+            .take(page_limit(limit))
+            .collect()
+    }
+
+    /// Returns the number of auctions started by `account_id` that haven't been settled.
+    pub fn get_supply_auctions_by_seller_id(&self, account_id: AccountId) -> U64 {
+        self.auctions_by_seller_id
+            .get(&account_id)
+            .map(|auctions_by_seller_id| U64(auctions_by_seller_id.len()))
+            .unwrap_or(U64(0))
+    }
+
+    /// Returns the auctions started by `account_id` that haven't been settled using pagination.
+    pub fn get_auctions_by_seller_id(
+        &self,
+        account_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Auction> {
+        let auctions_by_seller_id = if let Some(auctions_by_seller_id) = self.auctions_by_seller_id.get(&account_id) {
+            auctions_by_seller_id
+        } else {
+            return vec![];
+        };
+
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        auctions_by_seller_id
+            .iter()
+            .skip(start as usize)
+            .take(page_limit(limit))
+            .map(|auction_id| self.auctions.get(&auction_id).unwrap())
+            .collect()
+    }
+}
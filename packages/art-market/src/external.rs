@@ -0,0 +1,32 @@
+use crate::*;
+use near_sdk::ext_contract;
+
+/// The NFT contract a sale or auction is listed on.
+#[allow(dead_code)]
+#[ext_contract(ext_nft_contract)]
+trait ExtNftContract {
+    fn nft_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u32>,
+        memo: Option<String>,
+    );
+
+    fn nft_transfer_payout(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u32>,
+        memo: Option<String>,
+        balance: U128,
+        max_len_payout: u32,
+    ) -> Payout;
+}
+
+/// The SPOT token contract sales priced in SPOT are payed out with.
+#[allow(dead_code)]
+#[ext_contract(ext_ft_contract)]
+trait ExtFtContract {
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+}
@@ -0,0 +1,175 @@
+use crate::*;
+use near_sdk::PromiseResult;
+
+/// The message passed to `ft_transfer_call` on the SPOT contract to buy a token.
+#[near(serializers = [json])]
+pub struct PurchaseArgs {
+    pub nft_contract_id: AccountId,
+    pub token_id: TokenId,
+}
+
+/// The message passed to `ft_transfer_call` on the SPOT contract to make an offer on a token.
+#[near(serializers = [json])]
+pub struct OfferArgs {
+    pub nft_contract_id: AccountId,
+    pub token_id: TokenId,
+    pub expires_at: u64,
+}
+
+/// The messages `ft_on_transfer` accepts. An offer is told apart from a purchase by its `expires_at`.
+#[near(serializers = [json])]
+#[serde(untagged)]
+pub enum FtTransferArgs {
+    Offer(OfferArgs),
+    Purchase(PurchaseArgs),
+}
+
+#[near]
+impl ArtMarket {
+    /// Buys a sale priced in SPOT or makes an offer in SPOT. Called by the SPOT contract during `ft_transfer_call`
+    /// with a `msg` of the form `{"nft_contract_id": "echo.near", "token_id": "1"}` to buy the token, or
+    /// `{"nft_contract_id": "echo.near", "token_id": "1", "expires_at": 1700000000000}` to escrow the amount as an offer.
+    /// Returns the amount of SPOT that wasn't used.
+    pub fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        require!(
+            env::predecessor_account_id() == self.spot_token_id,
+            "Only SPOT can be used to buy tokens"
+        );
+
+        let PurchaseArgs { nft_contract_id, token_id } =
+            match near_sdk::serde_json::from_str(&msg).expect("Not valid PurchaseArgs or OfferArgs") {
+                FtTransferArgs::Purchase(purchase_args) => purchase_args,
+                FtTransferArgs::Offer(OfferArgs { nft_contract_id, token_id, expires_at }) => {
+                    // The whole amount is escrowed.
+                    self.internal_make_offer(sender_id, nft_contract_id, token_id, SalePrice::Spot(amount), expires_at);
+                    return PromiseOrValue::Value(U128(0));
+                }
+            };
+
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+        let sale = self.sales.get(&contract_and_token_id).expect("No sale");
+        require!(sale.owner_id != sender_id, "Cannot bid on your own sale.");
+
+        let price = match sale.sale_conditions {
+            SalePrice::Spot(price) => price,
+            SalePrice::Near(_) => env::panic_str("Sale is priced in NEAR, use offer"),
+        };
+        require!(amount.0 >= price.0, "Amount must be greater than or equal to the current price");
+
+        let sale = self.internal_remove_sale(nft_contract_id, token_id);
+        let callback = Self::ext(env::current_account_id())
+            .with_static_gas(GAS_FOR_RESOLVE_PURCHASE)
+            .resolve_ft_purchase(sender_id.clone(), sale.owner_id.clone(), price, amount);
+
+        self.internal_process_purchase(
+            sale.nft_contract_id,
+            sale.token_id,
+            Some(sale.approval_id),
+            sender_id,
+            price,
+            callback,
+        )
+        .into()
+    }
+
+    /// Claims the SPOT that couldn't be transferred to the caller, e.g. before they registered with the SPOT contract.
+    #[payable]
+    pub fn claim_spot(&mut self) -> Promise {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+
+        let amount = self.unpaid_spot.remove(&account_id).expect("No unpaid SPOT");
+        self.internal_transfer_spot(account_id, amount, "claim from market")
+    }
+
+    /// Resolves a transfer of SPOT out of the market. If it failed, the amount is kept so the receiver can claim it
+    /// with `claim_spot`. Returns whether the SPOT was transferred.
+    #[private]
+    pub fn resolve_spot_transfer(&mut self, receiver_id: AccountId, amount: U128) -> bool {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return true;
+        }
+
+        near_sdk::log!(
+            "{} SPOT could not be transferred to @{}, it can be claimed with claim_spot",
+            amount.0,
+            receiver_id
+        );
+        let unpaid = self.unpaid_spot.get(&receiver_id).map_or(0, |unpaid| unpaid.0);
+        self.unpaid_spot.insert(&receiver_id, &U128(unpaid + amount.0));
+        false
+    }
+
+    /// The SPOT that couldn't be transferred to `account_id` and can be claimed with `claim_spot`.
+    pub fn get_unpaid_spot(&self, account_id: AccountId) -> U128 {
+        self.unpaid_spot.get(&account_id).unwrap_or(U128(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::{testing_env, RuntimeFeesConfig};
+
+    fn seller() -> AccountId {
+        account("seller.near")
+    }
+
+    /// Resolves a transfer of `amount` SPOT to seller.near that failed.
+    fn fail_spot_transfer(contract: &mut ArtMarket, amount: u128) {
+        testing_env!(
+            VMContextBuilder::new().current_account_id(account("market.near")).build(),
+            near_sdk::test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        assert!(!contract.resolve_spot_transfer(seller(), U128(amount)));
+    }
+
+    #[test]
+    fn failed_payouts_add_up_and_can_be_claimed() {
+        let mut contract = setup_market();
+
+        fail_spot_transfer(&mut contract, 300);
+        fail_spot_transfer(&mut contract, 200);
+        assert_eq!(contract.get_unpaid_spot(seller()), U128(500));
+
+        set_context(seller(), ONE_YOCTONEAR);
+        contract.claim_spot();
+
+        assert_eq!(spot_transfers(), vec![(seller(), U128(500))]);
+        assert_eq!(contract.get_unpaid_spot(seller()), U128(0));
+    }
+
+    #[test]
+    fn successful_payout_is_not_kept() {
+        let mut contract = setup_market();
+
+        testing_env!(
+            VMContextBuilder::new().current_account_id(account("market.near")).build(),
+            near_sdk::test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])]
+        );
+        assert!(contract.resolve_spot_transfer(seller(), U128(500)));
+        assert_eq!(contract.get_unpaid_spot(seller()), U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "No unpaid SPOT")]
+    fn nothing_to_claim() {
+        let mut contract = setup_market();
+
+        set_context(seller(), ONE_YOCTONEAR);
+        contract.claim_spot();
+    }
+}
@@ -0,0 +1,344 @@
+use crate::*;
+use near_sdk::PromiseResult;
+
+/// Used to generate a unique prefix in the storage collections (this is to avoid data collisions).
+pub(crate) fn hash_account_id(account_id: &AccountId) -> CryptoHash {
+    let mut hash = CryptoHash::default();
+    hash.copy_from_slice(&env::sha256(account_id.as_bytes()));
+    hash
+}
+
+/// Used to generate a unique prefix for the offers made on a token.
+pub(crate) fn hash_contract_and_token_id(contract_and_token_id: &ContractAndTokenId) -> CryptoHash {
+    let mut hash = CryptoHash::default();
+    hash.copy_from_slice(&env::sha256(contract_and_token_id.as_bytes()));
+    hash
+}
+
+/// Returns the value of the promise this callback resolves, if it succeeded.
+pub(crate) fn promise_result_as_success() -> Option<Vec<u8>> {
+    require!(
+        env::promise_results_count() == 1,
+        "Contract expected a result on the callback"
+    );
+
+    match env::promise_result(0) {
+        PromiseResult::Successful(value) => Some(value),
+        _ => None,
+    }
+}
+
+/// The number of items a paginated view returns for the passed in `limit`, capped at `MAX_PAGE_LIMIT`.
+pub(crate) fn page_limit(limit: Option<u64>) -> usize {
+    limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT) as usize
+}
+
+/// Parses the payout returned by `nft_transfer_payout` and makes sure it pays out
+/// exactly `price` to no more than `MAX_LEN_PAYOUT` receivers.
+pub(crate) fn parse_payout(value: &[u8], price: u128) -> Option<HashMap<AccountId, U128>> {
+    let payout_object = near_sdk::serde_json::from_slice::<Payout>(value).ok()?;
+
+    if payout_object.payout.is_empty() || payout_object.payout.len() > MAX_LEN_PAYOUT as usize {
+        return None;
+    }
+
+    let mut remainder = price;
+    for value in payout_object.payout.values() {
+        remainder = remainder.checked_sub(value.0)?;
+    }
+
+    if remainder == 0 {
+        Some(payout_object.payout)
+    } else {
+        None
+    }
+}
+
+impl ArtMarket {
+    /// The number of sales, offers and auctions the storage deposit of `account_id` has to cover.
+    pub(crate) fn internal_storage_items(&self, account_id: &AccountId) -> u64 {
+        self.get_supply_by_owner_id(account_id.clone()).0
+            + self.get_supply_offers_by_bidder_id(account_id.clone()).0
+            + self.get_supply_auctions_by_seller_id(account_id.clone()).0
+    }
+
+    /// Makes sure `account_id` has covered the storage of all their sales, offers and auctions, including a new one.
+    pub(crate) fn internal_assert_storage_for_new_item(&self, account_id: &AccountId) {
+        let storage_amount = self.storage_minimum_balance();
+        let paid_storage = self.storage_deposits.get(account_id).unwrap_or(NO_DEPOSIT);
+        let required_storage = storage_amount
+            .saturating_mul(u128::from(self.internal_storage_items(account_id)) + 1);
+
+        require!(
+            paid_storage >= required_storage,
+            format!(
+                "Insufficient storage paid: {}, for {} sales, offers and auctions at {} rate of per item",
+                paid_storage,
+                required_storage.saturating_div(storage_amount.as_yoctonear()),
+                storage_amount,
+            )
+        );
+    }
+
+    /// Removes a sale from the market and from the indices of its owner and NFT contract.
+    pub(crate) fn internal_remove_sale(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+    ) -> Sale {
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+        let sale = self.sales.remove(&contract_and_token_id).expect("No sale");
+
+        let mut by_owner_id = self.by_owner_id.get(&sale.owner_id).expect("No sale by_owner_id");
+        by_owner_id.remove(&contract_and_token_id);
+
+        if by_owner_id.is_empty() {
+            self.by_owner_id.remove(&sale.owner_id);
+        } else {
+            self.by_owner_id.insert(&sale.owner_id, &by_owner_id);
+        }
+
+        let mut by_nft_contract_id = self
+            .by_nft_contract_id
+            .get(&nft_contract_id)
+            .expect("No sale by nft_contract_id");
+        by_nft_contract_id.remove(&token_id);
+
+        if by_nft_contract_id.is_empty() {
+            self.by_nft_contract_id.remove(&nft_contract_id);
+        } else {
+            self.by_nft_contract_id.insert(&nft_contract_id, &by_nft_contract_id);
+        }
+
+        sale
+    }
+
+    /// Adds a sale to the market and to the indices of its owner and NFT contract.
+    pub(crate) fn internal_add_sale(&mut self, sale: Sale) {
+        let contract_and_token_id = format!("{}{}{}", sale.nft_contract_id, DELIMITER, sale.token_id);
+
+        let mut by_owner_id = self.by_owner_id.get(&sale.owner_id).unwrap_or_else(|| {
+            UnorderedSet::new(StorageKey::ByOwnerIdInner {
+                account_id_hash: hash_account_id(&sale.owner_id),
+            })
+        });
+        by_owner_id.insert(&contract_and_token_id);
+        self.by_owner_id.insert(&sale.owner_id, &by_owner_id);
+
+        let mut by_nft_contract_id = self
+            .by_nft_contract_id
+            .get(&sale.nft_contract_id)
+            .unwrap_or_else(|| {
+                UnorderedSet::new(StorageKey::ByNFTContractIdInner {
+                    account_id_hash: hash_account_id(&sale.nft_contract_id),
+                })
+            });
+        by_nft_contract_id.insert(&sale.token_id);
+        self.by_nft_contract_id.insert(&sale.nft_contract_id, &by_nft_contract_id);
+
+        self.sales.insert(&contract_and_token_id, &sale);
+    }
+
+    /// Adds an offer to the market and to the indices of its token and bidder.
+    pub(crate) fn internal_add_offer(&mut self, offer: Offer) {
+        let contract_and_token_id = format!("{}{}{}", offer.nft_contract_id, DELIMITER, offer.token_id);
+
+        let mut offers_by_token = self.offers_by_token.get(&contract_and_token_id).unwrap_or_else(|| {
+            UnorderedSet::new(StorageKey::OffersByTokenInner {
+                contract_and_token_id_hash: hash_contract_and_token_id(&contract_and_token_id),
+            })
+        });
+        offers_by_token.insert(&offer.offer_id);
+        self.offers_by_token.insert(&contract_and_token_id, &offers_by_token);
+
+        let mut offers_by_bidder_id = self.offers_by_bidder_id.get(&offer.bidder_id).unwrap_or_else(|| {
+            UnorderedSet::new(StorageKey::OffersByBidderIdInner {
+                account_id_hash: hash_account_id(&offer.bidder_id),
+            })
+        });
+        offers_by_bidder_id.insert(&offer.offer_id);
+        self.offers_by_bidder_id.insert(&offer.bidder_id, &offers_by_bidder_id);
+
+        self.offers.insert(&offer.offer_id, &offer);
+    }
+
+    /// Removes an offer from the market and from the indices of its token and bidder.
+    /// The escrowed amount isn't refunded.
+    pub(crate) fn internal_remove_offer(&mut self, offer_id: u64) -> Offer {
+        let offer = self.offers.remove(&offer_id).expect("No offer");
+        let contract_and_token_id = format!("{}{}{}", offer.nft_contract_id, DELIMITER, offer.token_id);
+
+        let mut offers_by_token = self
+            .offers_by_token
+            .get(&contract_and_token_id)
+            .expect("No offer by token");
+        offers_by_token.remove(&offer_id);
+
+        if offers_by_token.is_empty() {
+            self.offers_by_token.remove(&contract_and_token_id);
+        } else {
+            self.offers_by_token.insert(&contract_and_token_id, &offers_by_token);
+        }
+
+        let mut offers_by_bidder_id = self
+            .offers_by_bidder_id
+            .get(&offer.bidder_id)
+            .expect("No offer by bidder_id");
+        offers_by_bidder_id.remove(&offer_id);
+
+        if offers_by_bidder_id.is_empty() {
+            self.offers_by_bidder_id.remove(&offer.bidder_id);
+        } else {
+            self.offers_by_bidder_id.insert(&offer.bidder_id, &offers_by_bidder_id);
+        }
+
+        offer
+    }
+
+    /// Adds an auction to the market and to the index of its seller.
+    pub(crate) fn internal_add_auction(&mut self, auction: Auction) {
+        let mut auctions_by_seller_id = self.auctions_by_seller_id.get(&auction.seller_id).unwrap_or_else(|| {
+            UnorderedSet::new(StorageKey::AuctionsBySellerIdInner {
+                account_id_hash: hash_account_id(&auction.seller_id),
+            })
+        });
+        auctions_by_seller_id.insert(&auction.auction_id);
+        self.auctions_by_seller_id.insert(&auction.seller_id, &auctions_by_seller_id);
+
+        self.auctions.insert(&auction.auction_id, &auction);
+    }
+
+    /// Removes an auction from the market and from the index of its seller.
+    /// The escrowed token and bid aren't returned.
+    pub(crate) fn internal_remove_auction(&mut self, auction_id: u64) -> Auction {
+        let auction = self.auctions.remove(&auction_id).expect("No auction");
+
+        let mut auctions_by_seller_id = self
+            .auctions_by_seller_id
+            .get(&auction.seller_id)
+            .expect("No auction by seller_id");
+        auctions_by_seller_id.remove(&auction_id);
+
+        if auctions_by_seller_id.is_empty() {
+            self.auctions_by_seller_id.remove(&auction.seller_id);
+        } else {
+            self.auctions_by_seller_id.insert(&auction.seller_id, &auctions_by_seller_id);
+        }
+
+        auction
+    }
+
+    /// Sends the escrowed amount of an offer back to the bidder.
+    pub(crate) fn internal_refund_offer(&self, offer: Offer) {
+        match offer.amount {
+            SalePrice::Near(amount) => {
+                Promise::new(offer.bidder_id).transfer(amount);
+            }
+            SalePrice::Spot(amount) => {
+                self.internal_transfer_spot(offer.bidder_id, amount, "refund from market");
+            }
+        }
+    }
+
+    /// Pays out a sale priced in SPOT to every receiver of the payout.
+    pub(crate) fn internal_ft_payout(&self, payout: HashMap<AccountId, U128>) {
+        for (receiver_id, payout_amount) in payout {
+            self.internal_transfer_spot(receiver_id, payout_amount, "payout from market");
+        }
+    }
+
+    /// Transfers SPOT out of the market. If the transfer fails, the receiver can claim the amount with `claim_spot`.
+    pub(crate) fn internal_transfer_spot(&self, receiver_id: AccountId, amount: U128, memo: &str) -> Promise {
+        ext_ft_contract::ext(self.spot_token_id.clone())
+            .with_attached_deposit(ONE_YOCTONEAR)
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(receiver_id.clone(), amount, Some(memo.to_string()))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_SPOT_TRANSFER)
+                    .resolve_spot_transfer(receiver_id, amount),
+            )
+    }
+
+    /// Transfers the token to the buyer through `nft_transfer_payout` and resolves the payout.
+    /// `approval_id` is `None` when the market owns the token, as it does for auctions.
+    pub(crate) fn internal_process_purchase(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u32>,
+        buyer_id: AccountId,
+        price: U128,
+        callback: Promise,
+    ) -> Promise {
+        ext_nft_contract::ext(nft_contract_id)
+            .with_attached_deposit(ONE_YOCTONEAR)
+            .with_static_gas(GAS_FOR_NFT_TRANSFER)
+            .nft_transfer_payout(
+                buyer_id,
+                token_id,
+                approval_id,
+                Some("payout from market".to_string()),
+                price,
+                MAX_LEN_PAYOUT,
+            )
+            .then(callback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payout(amounts: &[(&str, u128)]) -> Vec<u8> {
+        let payout = amounts
+            .iter()
+            .map(|(account_id, amount)| (account_id.parse().unwrap(), U128(*amount)))
+            .collect();
+        near_sdk::serde_json::to_vec(&Payout { payout }).unwrap()
+    }
+
+    #[test]
+    fn page_limit_is_capped() {
+        assert_eq!(page_limit(None), DEFAULT_PAGE_LIMIT as usize);
+        assert_eq!(page_limit(Some(10)), 10);
+        assert_eq!(page_limit(Some(u64::MAX)), MAX_PAGE_LIMIT as usize);
+    }
+
+    #[test]
+    fn payout_adding_up_to_the_price_is_accepted() {
+        let payout = parse_payout(&payout(&[("seller.near", 900), ("artist.near", 100)]), 1000).unwrap();
+
+        assert_eq!(payout.len(), 2);
+        assert_eq!(payout[&"artist.near".parse::<AccountId>().unwrap()], U128(100));
+    }
+
+    #[test]
+    fn payout_short_of_the_price_is_rejected() {
+        assert!(parse_payout(&payout(&[("seller.near", 999)]), 1000).is_none());
+    }
+
+    #[test]
+    fn payout_over_the_price_is_rejected() {
+        assert!(parse_payout(&payout(&[("seller.near", 900), ("artist.near", 101)]), 1000).is_none());
+    }
+
+    #[test]
+    fn empty_payout_is_rejected() {
+        assert!(parse_payout(&payout(&[]), 0).is_none());
+    }
+
+    #[test]
+    fn payout_to_too_many_receivers_is_rejected() {
+        let receivers: Vec<(String, u128)> =
+            (0..=MAX_LEN_PAYOUT).map(|i| (format!("receiver{}.near", i), 1)).collect();
+        let receivers: Vec<(&str, u128)> = receivers.iter().map(|(id, amount)| (id.as_str(), *amount)).collect();
+
+        assert!(parse_payout(&payout(&receivers), u128::from(MAX_LEN_PAYOUT) + 1).is_none());
+    }
+
+    #[test]
+    fn malformed_payout_is_rejected() {
+        assert!(parse_payout(b"{\"seller.near\": \"1000\"}", 1000).is_none());
+    }
+}
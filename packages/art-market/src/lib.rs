@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{
+    assert_one_yocto, env, near, require, AccountId, BorshStorageKey, CryptoHash, Gas, NearToken, PanicOnDefault, Promise, PromiseOrValue
+};
+
+pub use crate::auction::*;
+pub use crate::offer::*;
+pub use crate::sale::*;
+
+mod auction;
+mod auction_views;
+mod external;
+mod ft_callbacks;
+mod internal;
+mod nft_callbacks;
+mod offer;
+mod offer_views;
+mod sale;
+mod sale_views;
+#[cfg(test)]
+mod test_utils;
+
+use crate::external::*;
+use crate::internal::*;
+
+/// Gas for the cross contract call to `nft_transfer_payout`.
+const GAS_FOR_NFT_TRANSFER: Gas = Gas::from_tgas(15);
+/// Gas for the callback that pays out the sale.
+const GAS_FOR_RESOLVE_PURCHASE: Gas = Gas::from_tgas(125);
+/// Gas for each `ft_transfer` made while paying out a sale priced in SPOT.
+const GAS_FOR_FT_TRANSFER: Gas = Gas::from_tgas(5);
+/// Gas for the callback that keeps track of an `ft_transfer` that failed.
+const GAS_FOR_RESOLVE_SPOT_TRANSFER: Gas = Gas::from_tgas(5);
+
+const ONE_YOCTONEAR: NearToken = NearToken::from_yoctonear(1);
+const NO_DEPOSIT: NearToken = NearToken::from_yoctonear(0);
+
+/// The maximum amount of receivers a sale can be payed out to.
+const MAX_LEN_PAYOUT: u32 = 10;
+/// The number of items a paginated view returns if no limit is passed.
+const DEFAULT_PAGE_LIMIT: u64 = 50;
+/// The most items a paginated view returns, so it can't run out of gas.
+const MAX_PAGE_LIMIT: u64 = 100;
+/// The storage in bytes that has to be covered for every sale an account has listed, every offer it has made
+/// and every auction it has started.
+const STORAGE_BYTES_PER_SALE: u128 = 1000;
+
+pub type TokenId = String;
+/// The key of a sale: `{nft_contract_id}{DELIMITER}{token_id}`.
+pub type ContractAndTokenId = String;
+
+pub const DELIMITER: &str = ".";
+
+/// The payout returned by `nft_transfer_payout` on the NFT contract.
+#[near(serializers = [json])]
+pub struct Payout {
+    pub payout: HashMap<AccountId, U128>,
+}
+
+#[derive(PanicOnDefault)]
+#[near(contract_state)]
+pub struct ArtMarket {
+    /// Owner of the contract
+    pub owner_id: AccountId,
+
+    /// The SPOT token contract that sales can be priced in
+    pub spot_token_id: AccountId,
+
+    /// Every listed sale keyed by the NFT contract and token ID
+    pub sales: UnorderedMap<ContractAndTokenId, Sale>,
+
+    /// The sales listed by each account
+    pub by_owner_id: LookupMap<AccountId, UnorderedSet<ContractAndTokenId>>,
+
+    /// The token IDs listed for each NFT contract
+    pub by_nft_contract_id: LookupMap<AccountId, UnorderedSet<TokenId>>,
+
+    /// The storage deposit of each account that lists sales or makes offers
+    pub storage_deposits: LookupMap<AccountId, NearToken>,
+
+    /// Every open offer keyed by its ID
+    pub offers: UnorderedMap<u64, Offer>,
+
+    /// The offers made on each token, keyed by the NFT contract and token ID
+    pub offers_by_token: LookupMap<ContractAndTokenId, UnorderedSet<u64>>,
+
+    /// The offers made by each account
+    pub offers_by_bidder_id: LookupMap<AccountId, UnorderedSet<u64>>,
+
+    /// The ID the next offer gets
+    pub next_offer_id: u64,
+
+    /// Every running auction keyed by its ID
+    pub auctions: UnorderedMap<u64, Auction>,
+
+    /// The auctions started by each account
+    pub auctions_by_seller_id: LookupMap<AccountId, UnorderedSet<u64>>,
+
+    /// The ID the next auction gets
+    pub next_auction_id: u64,
+
+    /// The SPOT that couldn't be transferred to each account and can be claimed with `claim_spot`
+    pub unpaid_spot: LookupMap<AccountId, U128>,
+}
+
+#[near(serializers = [borsh])]
+#[derive(BorshStorageKey)]
+pub enum StorageKey {
+    Sales,
+    ByOwnerId,
+    ByOwnerIdInner { account_id_hash: CryptoHash },
+    ByNFTContractId,
+    ByNFTContractIdInner { account_id_hash: CryptoHash },
+    StorageDeposits,
+    Offers,
+    OffersByToken,
+    OffersByTokenInner { contract_and_token_id_hash: CryptoHash },
+    OffersByBidderId,
+    OffersByBidderIdInner { account_id_hash: CryptoHash },
+    Auctions,
+    AuctionsBySellerId,
+    AuctionsBySellerIdInner { account_id_hash: CryptoHash },
+    UnpaidSpot,
+}
+
+#[near]
+impl ArtMarket {
+    /// Initializes the marketplace with the owner and the SPOT token contract sales can be priced in.
+    #[init]
+    #[private]
+    pub fn new(owner_id: AccountId, spot_token_id: AccountId) -> Self {
+        require!(!env::state_exists(), "Already initialized");
+
+        Self {
+            owner_id,
+            spot_token_id,
+            sales: UnorderedMap::new(StorageKey::Sales),
+            by_owner_id: LookupMap::new(StorageKey::ByOwnerId),
+            by_nft_contract_id: LookupMap::new(StorageKey::ByNFTContractId),
+            storage_deposits: LookupMap::new(StorageKey::StorageDeposits),
+            offers: UnorderedMap::new(StorageKey::Offers),
+            offers_by_token: LookupMap::new(StorageKey::OffersByToken),
+            offers_by_bidder_id: LookupMap::new(StorageKey::OffersByBidderId),
+            next_offer_id: 0,
+            auctions: UnorderedMap::new(StorageKey::Auctions),
+            auctions_by_seller_id: LookupMap::new(StorageKey::AuctionsBySellerId),
+            next_auction_id: 0,
+            unpaid_spot: LookupMap::new(StorageKey::UnpaidSpot),
+        }
+    }
+
+    pub fn get_owner_id(&self) -> AccountId {
+        self.owner_id.clone()
+    }
+
+    /// Deposits storage for `account_id` (defaults to the caller) so it can list sales, make offers and start auctions.
+    #[payable]
+    pub fn storage_deposit(&mut self, account_id: Option<AccountId>) {
+        let storage_account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let deposit = env::attached_deposit();
+
+        require!(
+            deposit >= self.storage_minimum_balance(),
+            format!("Requires minimum deposit of {}", self.storage_minimum_balance())
+        );
+
+        let balance = self.storage_deposits.get(&storage_account_id).unwrap_or(NO_DEPOSIT);
+        self.storage_deposits.insert(&storage_account_id, &balance.saturating_add(deposit));
+    }
+
+    /// Withdraws the storage deposit of the caller that isn't covering listed sales, open offers or running auctions.
+    #[payable]
+    pub fn storage_withdraw(&mut self) {
+        assert_one_yocto();
+        let owner_id = env::predecessor_account_id();
+
+        let amount = self.storage_deposits.remove(&owner_id).unwrap_or(NO_DEPOSIT);
+        let items = self.internal_storage_items(&owner_id);
+        let diff = self.storage_minimum_balance().saturating_mul(items.into());
+        let refund = amount.saturating_sub(diff);
+
+        if refund > NO_DEPOSIT {
+            Promise::new(owner_id.clone()).transfer(refund);
+        }
+
+        if diff > NO_DEPOSIT {
+            self.storage_deposits.insert(&owner_id, &diff);
+        }
+    }
+
+    /// The storage deposit needed for a single sale, offer or auction.
+    pub fn storage_minimum_balance(&self) -> NearToken {
+        env::storage_byte_cost().saturating_mul(STORAGE_BYTES_PER_SALE)
+    }
+
+    /// The storage deposit of `account_id`.
+    pub fn storage_balance_of(&self, account_id: AccountId) -> NearToken {
+        self.storage_deposits.get(&account_id).unwrap_or(NO_DEPOSIT)
+    }
+}
@@ -0,0 +1,112 @@
+use crate::*;
+
+/// The message passed to `nft_approve` to list a token.
+#[near(serializers = [json])]
+pub struct SaleArgs {
+    pub sale_conditions: SalePrice,
+}
+
+/// The message passed to `nft_approve` to accept an offer on a token.
+#[near(serializers = [json])]
+pub struct AcceptOfferArgs {
+    pub offer_id: u64,
+}
+
+/// The messages `nft_on_approve` accepts.
+#[near(serializers = [json])]
+#[serde(untagged)]
+pub enum ApproveArgs {
+    Sale(SaleArgs),
+    AcceptOffer(AcceptOfferArgs),
+}
+
+/// The message passed to `nft_transfer_call` to escrow a token with the market and auction it.
+#[near(serializers = [json])]
+pub struct AuctionArgs {
+    pub auction: AuctionKind,
+    /// When bidding opens, in milliseconds since the Unix epoch. Defaults to now.
+    pub start_at: Option<u64>,
+    /// When bidding closes, in milliseconds since the Unix epoch
+    pub end_at: u64,
+}
+
+#[near]
+impl ArtMarket {
+    /// Lists a token for sale or accepts an offer on it. Called by the NFT contract when the owner approves the market
+    /// with a `msg` of the form `{"sale_conditions": {"near": "1000"}}` or `{"sale_conditions": {"spot": "1000"}}`
+    /// to list the token, or `{"offer_id": 3}` to sell the token to the bidder of the offer.
+    pub fn nft_on_approve(
+        &mut self,
+        token_id: TokenId,
+        owner_id: AccountId,
+        approval_id: u32,
+        msg: String,
+    ) {
+        let nft_contract_id = env::predecessor_account_id();
+        let signer_id = env::signer_account_id();
+
+        require!(
+            nft_contract_id != signer_id,
+            "nft_on_approve should only be called via cross-contract call"
+        );
+        require!(owner_id == signer_id, "owner_id should be signer_id");
+
+        let sale_conditions =
+            match near_sdk::serde_json::from_str(&msg).expect("Not valid SaleArgs or AcceptOfferArgs") {
+                ApproveArgs::Sale(SaleArgs { sale_conditions }) => sale_conditions,
+                ApproveArgs::AcceptOffer(AcceptOfferArgs { offer_id }) => {
+                    self.internal_accept_offer(offer_id, owner_id, nft_contract_id, token_id, approval_id);
+                    return;
+                }
+            };
+
+        // Make sure the owner has covered the storage of all their sales and offers, including this one.
+        self.internal_assert_storage_for_new_item(&signer_id);
+
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+        if self.sales.get(&contract_and_token_id).is_some() {
+            // The token was approved again, so the old listing is replaced.
+            self.internal_remove_sale(nft_contract_id.clone(), token_id.clone());
+        }
+
+        self.internal_add_sale(Sale {
+            owner_id,
+            approval_id,
+            nft_contract_id,
+            token_id,
+            sale_conditions,
+        });
+    }
+
+    /// Starts an auction of a token that was just transferred to the market. Called by the NFT contract during
+    /// `nft_transfer_call` with a `msg` of the form
+    /// `{"auction": {"english": {"reserve_price": "1000", "min_increment": "100", "extension_ms": 600000}}, "end_at": 1700000000000}`
+    /// or `{"auction": {"dutch": {"start_price": "1000", "end_price": "100", "step_ms": 60000}}, "end_at": 1700000000000}`.
+    /// The market keeps the token until the auction is settled. Panicking returns the token to its previous owner.
+    pub fn nft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        previous_owner_id: AccountId,
+        token_id: TokenId,
+        msg: String,
+    ) -> PromiseOrValue<bool> {
+        let nft_contract_id = env::predecessor_account_id();
+        let signer_id = env::signer_account_id();
+
+        require!(
+            nft_contract_id != signer_id,
+            "nft_on_transfer should only be called via cross-contract call"
+        );
+        // Only the owner can auction their token, so a contract can't start auctions on behalf of its callers.
+        require!(previous_owner_id == signer_id, "previous_owner_id should be signer_id");
+
+        let AuctionArgs { auction, start_at, end_at } =
+            near_sdk::serde_json::from_str(&msg).expect("Not valid AuctionArgs");
+        let auction_id =
+            self.internal_start_auction(previous_owner_id, nft_contract_id, token_id, auction, start_at, end_at);
+        near_sdk::log!("Auction {} started by @{}", auction_id, sender_id);
+
+        // Keep the token.
+        PromiseOrValue::Value(false)
+    }
+}
@@ -0,0 +1,292 @@
+use crate::*;
+
+#[derive(Clone, Debug)]
+#[near(serializers = [borsh, json])]
+pub struct Offer {
+    /// ID of the offer
+    pub offer_id: u64,
+    /// The account that made the offer and gets the token if it's accepted
+    pub bidder_id: AccountId,
+    /// The NFT contract the token lives on
+    pub nft_contract_id: AccountId,
+    /// The token the offer is made on
+    pub token_id: TokenId,
+    /// The amount escrowed by the market, which the owner is paid out if they accept
+    pub amount: SalePrice,
+    /// When the offer expires, in milliseconds since the Unix epoch
+    pub expires_at: u64,
+}
+
+#[near]
+impl ArtMarket {
+    /// Makes an offer in NEAR on any token, listed or not. The attached deposit is escrowed until the offer is
+    /// accepted or removed. Offers in SPOT are made through `ft_transfer_call` on the SPOT contract.
+    #[payable]
+    pub fn make_offer(&mut self, nft_contract_id: AccountId, token_id: TokenId, expires_at: u64) -> u64 {
+        let deposit = env::attached_deposit();
+        require!(deposit > NO_DEPOSIT, "Attached deposit must be greater than 0");
+
+        self.internal_make_offer(
+            env::predecessor_account_id(),
+            nft_contract_id,
+            token_id,
+            SalePrice::Near(deposit),
+            expires_at,
+        )
+    }
+
+    /// Removes an offer and refunds the escrowed amount to the bidder. The bidder can remove their offer
+    /// at any time, anyone else only once it has expired.
+    #[payable]
+    pub fn remove_offer(&mut self, offer_id: u64) {
+        assert_one_yocto();
+        let offer = self.internal_remove_offer(offer_id);
+        require!(
+            env::predecessor_account_id() == offer.bidder_id || env::block_timestamp_ms() >= offer.expires_at,
+            "Must be the bidder or the offer must have expired"
+        );
+
+        self.internal_refund_offer(offer);
+    }
+
+    /// Pays out an accepted offer in SPOT once the token has been transferred to the bidder.
+    /// Refunds the bidder if the transfer failed. If the payout is invalid, the seller is paid the whole amount.
+    #[private]
+    pub fn resolve_ft_offer(&mut self, bidder_id: AccountId, seller_id: AccountId, amount: U128) -> U128 {
+        let Some(value) = promise_result_as_success() else {
+            near_sdk::log!("Refunding {} SPOT to @{}", amount.0, bidder_id);
+            self.internal_transfer_spot(bidder_id, amount, "refund from market");
+            return amount;
+        };
+
+        // The token now belongs to the bidder, so the seller is paid even if the payout can't be used.
+        let payout = parse_payout(&value, amount.0).unwrap_or_else(|| HashMap::from([(seller_id, amount)]));
+        self.internal_ft_payout(payout);
+
+        amount
+    }
+}
+
+impl ArtMarket {
+    /// Records an offer whose amount has already been escrowed by the market and returns its ID.
+    pub(crate) fn internal_make_offer(
+        &mut self,
+        bidder_id: AccountId,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        amount: SalePrice,
+        expires_at: u64,
+    ) -> u64 {
+        require!(expires_at > env::block_timestamp_ms(), "Offer must expire in the future");
+
+        // Make sure the bidder has covered the storage of all their sales and offers, including this one.
+        self.internal_assert_storage_for_new_item(&bidder_id);
+
+        let offer_id = self.next_offer_id;
+        self.next_offer_id += 1;
+
+        self.internal_add_offer(Offer {
+            offer_id,
+            bidder_id,
+            nft_contract_id,
+            token_id,
+            amount,
+            expires_at,
+        });
+
+        offer_id
+    }
+
+    /// Sells the token to the bidder of an offer through `nft_transfer_payout`, using the approval the owner just
+    /// gave the market. A sale of the token is removed since the token is no longer for sale.
+    pub(crate) fn internal_accept_offer(
+        &mut self,
+        offer_id: u64,
+        owner_id: AccountId,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        approval_id: u32,
+    ) -> Promise {
+        let offer = self.internal_remove_offer(offer_id);
+        require!(
+            offer.nft_contract_id == nft_contract_id && offer.token_id == token_id,
+            "Offer is for another token"
+        );
+        require!(env::block_timestamp_ms() < offer.expires_at, "Offer has expired");
+
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+        if self.sales.get(&contract_and_token_id).is_some() {
+            self.internal_remove_sale(nft_contract_id.clone(), token_id.clone());
+        }
+
+        let callback = match offer.amount {
+            SalePrice::Near(amount) => Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_PURCHASE)
+                .resolve_purchase(offer.bidder_id.clone(), owner_id, U128(amount.as_yoctonear())),
+            SalePrice::Spot(amount) => Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_PURCHASE)
+                .resolve_ft_offer(offer.bidder_id.clone(), owner_id, amount),
+        };
+
+        self.internal_process_purchase(
+            nft_contract_id,
+            token_id,
+            Some(approval_id),
+            offer.bidder_id,
+            U128(offer.amount.as_u128()),
+            callback,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::{testing_env, PromiseResult, RuntimeFeesConfig};
+
+    const EXPIRES_AT: u64 = 1_000;
+
+    fn bidder() -> AccountId {
+        account("bidder.near")
+    }
+
+    fn seller() -> AccountId {
+        account("seller.near")
+    }
+
+    fn echo() -> AccountId {
+        account("echo.near")
+    }
+
+    /// A market with an offer of 5 NEAR by bidder.near on echo.near's token "1".
+    fn setup_offer() -> (ArtMarket, u64) {
+        let mut contract = setup_market();
+
+        set_context(bidder(), contract.storage_minimum_balance());
+        contract.storage_deposit(None);
+
+        set_context(bidder(), NearToken::from_near(5));
+        let offer_id = contract.make_offer(echo(), "1".to_string(), EXPIRES_AT);
+        (contract, offer_id)
+    }
+
+    #[test]
+    #[should_panic(expected = "Offer is for another token")]
+    fn offer_cannot_be_accepted_for_another_token() {
+        let (mut contract, offer_id) = setup_offer();
+
+        set_context(echo(), NO_DEPOSIT);
+        contract.internal_accept_offer(offer_id, seller(), echo(), "2".to_string(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Offer is for another token")]
+    fn offer_cannot_be_accepted_on_another_contract() {
+        let (mut contract, offer_id) = setup_offer();
+
+        set_context(account("other.near"), NO_DEPOSIT);
+        contract.internal_accept_offer(offer_id, seller(), account("other.near"), "1".to_string(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Offer has expired")]
+    fn expired_offer_cannot_be_accepted() {
+        let (mut contract, offer_id) = setup_offer();
+
+        set_context_at(echo(), NO_DEPOSIT, EXPIRES_AT);
+        contract.internal_accept_offer(offer_id, seller(), echo(), "1".to_string(), 0);
+    }
+
+    #[test]
+    fn accepted_offer_is_removed() {
+        let (mut contract, offer_id) = setup_offer();
+
+        set_context(echo(), NO_DEPOSIT);
+        contract.internal_accept_offer(offer_id, seller(), echo(), "1".to_string(), 0);
+
+        assert!(contract.get_offer(offer_id).is_none());
+        assert_eq!(contract.get_supply_offers_by_bidder_id(bidder()), U64(0));
+    }
+
+    #[test]
+    fn removed_offer_refunds_the_bidder() {
+        let (mut contract, offer_id) = setup_offer();
+
+        set_context(bidder(), ONE_YOCTONEAR);
+        contract.remove_offer(offer_id);
+
+        assert!(contract.get_offer(offer_id).is_none());
+        assert_eq!(transferred_to(&bidder()), NearToken::from_near(5));
+    }
+
+    #[test]
+    #[should_panic(expected = "Must be the bidder or the offer must have expired")]
+    fn only_bidder_removes_offer_before_it_expires() {
+        let (mut contract, offer_id) = setup_offer();
+
+        set_context(seller(), ONE_YOCTONEAR);
+        contract.remove_offer(offer_id);
+    }
+
+    #[test]
+    fn expired_offer_is_refunded_to_the_bidder() {
+        let (mut contract, offer_id) = setup_offer();
+
+        set_context_at(seller(), ONE_YOCTONEAR, EXPIRES_AT);
+        contract.remove_offer(offer_id);
+
+        assert_eq!(transferred_to(&bidder()), NearToken::from_near(5));
+        assert_eq!(transferred_to(&seller()), NO_DEPOSIT);
+    }
+
+    #[test]
+    fn removed_spot_offer_refunds_the_bidder() {
+        let mut contract = setup_market();
+
+        set_context(bidder(), contract.storage_minimum_balance());
+        contract.storage_deposit(None);
+
+        set_context(spot(), NO_DEPOSIT);
+        let msg = format!(r#"{{"nft_contract_id": "echo.near", "token_id": "1", "expires_at": {}}}"#, EXPIRES_AT);
+        contract.ft_on_transfer(bidder(), U128(500), msg);
+
+        set_context(bidder(), ONE_YOCTONEAR);
+        contract.remove_offer(0);
+
+        assert_eq!(spot_transfers(), vec![(bidder(), U128(500))]);
+    }
+
+    #[test]
+    fn invalid_payout_of_spot_offer_pays_the_seller() {
+        let mut contract = setup_market();
+
+        testing_env!(
+            VMContextBuilder::new().current_account_id(account("market.near")).build(),
+            near_sdk::test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(b"{}".to_vec())]
+        );
+        contract.resolve_ft_offer(bidder(), seller(), U128(500));
+
+        assert_eq!(spot_transfers(), vec![(seller(), U128(500))]);
+    }
+
+    #[test]
+    fn failed_transfer_of_spot_offer_refunds_the_bidder() {
+        let mut contract = setup_market();
+
+        testing_env!(
+            VMContextBuilder::new().current_account_id(account("market.near")).build(),
+            near_sdk::test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        contract.resolve_ft_offer(bidder(), seller(), U128(500));
+
+        assert_eq!(spot_transfers(), vec![(bidder(), U128(500))]);
+    }
+}
@@ -0,0 +1,74 @@
+use crate::*;
+
+#[near]
+impl ArtMarket {
+    /// Returns the offer with `offer_id`, if it's still open.
+    pub fn get_offer(&self, offer_id: u64) -> Option<Offer> {
+        self.offers.get(&offer_id)
+    }
+
+    /// Returns the number of open offers made on `token_id` on `nft_contract_id`.
+    pub fn get_supply_offers_by_token(&self, nft_contract_id: AccountId, token_id: TokenId) -> U64 {
+        self.offers_by_token
+            .get(&format!("{}{}{}", nft_contract_id, DELIMITER, token_id))
+            .map(|offers_by_token| U64(offers_by_token.len()))
+            .unwrap_or(U64(0))
+    }
+
+    /// Returns the open offers made on `token_id` on `nft_contract_id` using pagination.
+    pub fn get_offers_by_token(
+        &self,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Offer> {
+        let offers_by_token = if let Some(offers_by_token) =
+            self.offers_by_token.get(&format!("{}{}{}", nft_contract_id, DELIMITER, token_id))
+        {
+            offers_by_token
+        } else {
+            return vec![];
+        };
+
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        offers_by_token
+            .iter()
+            .skip(start as usize)
+            .take(page_limit(limit))
+            .map(|offer_id| self.offers.get(&offer_id).unwrap())
+            .collect()
+    }
+
+    /// Returns the number of open offers made by `account_id`.
+    pub fn get_supply_offers_by_bidder_id(&self, account_id: AccountId) -> U64 {
+        self.offers_by_bidder_id
+            .get(&account_id)
+            .map(|offers_by_bidder_id| U64(offers_by_bidder_id.len()))
+            .unwrap_or(U64(0))
+    }
+
+    /// Returns the open offers made by `account_id` using pagination.
+    pub fn get_offers_by_bidder_id(
+        &self,
+        account_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Offer> {
+        let offers_by_bidder_id = if let Some(offers_by_bidder_id) = self.offers_by_bidder_id.get(&account_id) {
+            offers_by_bidder_id
+        } else {
+            return vec![];
+        };
+
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        offers_by_bidder_id
+            .iter()
+            .skip(start as usize)
+            .take(page_limit(limit))
+            .map(|offer_id| self.offers.get(&offer_id).unwrap())
+            .collect()
+    }
+}
@@ -0,0 +1,139 @@
+use crate::*;
+
+/// The price of a sale, either in NEAR or in SPOT.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[near(serializers = [borsh, json])]
+#[serde(rename_all = "snake_case")]
+pub enum SalePrice {
+    Near(NearToken),
+    Spot(U128),
+}
+
+impl SalePrice {
+    pub fn as_u128(&self) -> u128 {
+        match self {
+            Self::Near(price) => price.as_yoctonear(),
+            Self::Spot(price) => price.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+#[near(serializers = [borsh, json])]
+pub struct Sale {
+    /// Owner of the token that is listed
+    pub owner_id: AccountId,
+    /// The approval ID the market was given by the NFT contract
+    pub approval_id: u32,
+    /// The NFT contract the token lives on
+    pub nft_contract_id: AccountId,
+    /// The token that is listed
+    pub token_id: TokenId,
+    /// The price the token is listed for
+    pub sale_conditions: SalePrice,
+}
+
+#[near]
+impl ArtMarket {
+    /// Removes a sale from the market. Only the owner of the sale can remove it.
+    #[payable]
+    pub fn remove_sale(&mut self, nft_contract_id: AccountId, token_id: TokenId) {
+        assert_one_yocto();
+        let sale = self.internal_remove_sale(nft_contract_id, token_id);
+        require!(
+            env::predecessor_account_id() == sale.owner_id,
+            "Must be sale owner"
+        );
+    }
+
+    /// Updates the price of a sale. Only the owner of the sale can update it.
+    #[payable]
+    pub fn update_price(&mut self, nft_contract_id: AccountId, token_id: TokenId, price: SalePrice) {
+        assert_one_yocto();
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+        let mut sale = self.sales.get(&contract_and_token_id).expect("No sale");
+
+        require!(
+            env::predecessor_account_id() == sale.owner_id,
+            "Must be sale owner"
+        );
+
+        sale.sale_conditions = price;
+        self.sales.insert(&contract_and_token_id, &sale);
+    }
+
+    /// Buys a sale priced in NEAR with the attached deposit.
+    #[payable]
+    pub fn offer(&mut self, nft_contract_id: AccountId, token_id: TokenId) -> Promise {
+        let deposit = env::attached_deposit();
+        require!(deposit > NO_DEPOSIT, "Attached deposit must be greater than 0");
+
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+        let sale = self.sales.get(&contract_and_token_id).expect("No sale");
+
+        let buyer_id = env::predecessor_account_id();
+        require!(sale.owner_id != buyer_id, "Cannot bid on your own sale.");
+
+        let price = match sale.sale_conditions {
+            SalePrice::Near(price) => price,
+            SalePrice::Spot(_) => env::panic_str("Sale is priced in SPOT, use ft_transfer_call"),
+        };
+        require!(deposit >= price, "Attached deposit must be greater than or equal to the current price");
+
+        // Refund whatever was attached on top of the price.
+        let refund = deposit.saturating_sub(price);
+        if refund > NO_DEPOSIT {
+            Promise::new(buyer_id.clone()).transfer(refund);
+        }
+
+        let sale = self.internal_remove_sale(nft_contract_id, token_id);
+        let callback = Self::ext(env::current_account_id())
+            .with_static_gas(GAS_FOR_RESOLVE_PURCHASE)
+            .resolve_purchase(buyer_id.clone(), sale.owner_id.clone(), U128(price.as_yoctonear()));
+
+        self.internal_process_purchase(
+            sale.nft_contract_id,
+            sale.token_id,
+            Some(sale.approval_id),
+            buyer_id,
+            U128(price.as_yoctonear()),
+            callback,
+        )
+    }
+
+    /// Pays out a sale priced in NEAR once the token has been transferred to the buyer.
+    /// Refunds the buyer if the transfer failed. If the payout is invalid, the seller is paid the whole price.
+    #[private]
+    pub fn resolve_purchase(&mut self, buyer_id: AccountId, seller_id: AccountId, price: U128) -> U128 {
+        let Some(value) = promise_result_as_success() else {
+            Promise::new(buyer_id).transfer(NearToken::from_yoctonear(price.0));
+            return price;
+        };
+
+        // The token now belongs to the buyer, so the seller is paid even if the payout can't be used.
+        let payout = parse_payout(&value, price.0).unwrap_or_else(|| HashMap::from([(seller_id, price)]));
+
+        for (receiver_id, amount) in payout {
+            Promise::new(receiver_id).transfer(NearToken::from_yoctonear(amount.0));
+        }
+
+        price
+    }
+
+    /// Pays out a sale priced in SPOT once the token has been transferred to the buyer.
+    /// Returns the amount of SPOT that wasn't used so the SPOT contract refunds it to the buyer.
+    /// If the payout is invalid, the seller is paid the whole price.
+    #[private]
+    pub fn resolve_ft_purchase(&mut self, buyer_id: AccountId, seller_id: AccountId, price: U128, amount: U128) -> U128 {
+        let Some(value) = promise_result_as_success() else {
+            near_sdk::log!("Refunding {} SPOT to @{}", amount.0, buyer_id);
+            return amount;
+        };
+
+        // The token now belongs to the buyer, so the seller is paid even if the payout can't be used.
+        let payout = parse_payout(&value, price.0).unwrap_or_else(|| HashMap::from([(seller_id, price)]));
+        self.internal_ft_payout(payout);
+
+        U128(amount.0 - price.0)
+    }
+}
@@ -0,0 +1,81 @@
+use crate::*;
+
+#[near]
+impl ArtMarket {
+    /// Returns the number of sales on the market.
+    pub fn get_supply_sales(&self) -> U64 {
+        U64(self.sales.len())
+    }
+
+    /// Returns the number of sales listed by `account_id`.
+    pub fn get_supply_by_owner_id(&self, account_id: AccountId) -> U64 {
+        self.by_owner_id
+            .get(&account_id)
+            .map(|by_owner_id| U64(by_owner_id.len()))
+            .unwrap_or(U64(0))
+    }
+
+    /// Returns the sales listed by `account_id` using pagination.
+    pub fn get_sales_by_owner_id(
+        &self,
+        account_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Sale> {
+        let by_owner_id = if let Some(by_owner_id) = self.by_owner_id.get(&account_id) {
+            by_owner_id
+        } else {
+            return vec![];
+        };
+
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        by_owner_id
+            .iter()
+            .skip(start as usize)
+            .take(page_limit(limit))
+            .map(|contract_and_token_id| self.sales.get(&contract_and_token_id).unwrap())
+            .collect()
+    }
+
+    /// Returns the number of sales listed for `nft_contract_id`.
+    pub fn get_supply_by_nft_contract_id(&self, nft_contract_id: AccountId) -> U64 {
+        self.by_nft_contract_id
+            .get(&nft_contract_id)
+            .map(|by_nft_contract_id| U64(by_nft_contract_id.len()))
+            .unwrap_or(U64(0))
+    }
+
+    /// Returns the sales listed for `nft_contract_id` using pagination.
+    pub fn get_sales_by_nft_contract_id(
+        &self,
+        nft_contract_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Sale> {
+        let by_nft_contract_id =
+            if let Some(by_nft_contract_id) = self.by_nft_contract_id.get(&nft_contract_id) {
+                by_nft_contract_id
+            } else {
+                return vec![];
+            };
+
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        by_nft_contract_id
+            .iter()
+            .skip(start as usize)
+            .take(page_limit(limit))
+            .map(|token_id| {
+                self.sales
+                    .get(&format!("{}{}{}", nft_contract_id, DELIMITER, token_id))
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    /// Returns the sale of `token_id` on `nft_contract_id`, if it's listed.
+    pub fn get_sale(&self, nft_contract_id: AccountId, token_id: TokenId) -> Option<Sale> {
+        self.sales.get(&format!("{}{}{}", nft_contract_id, DELIMITER, token_id))
+    }
+}
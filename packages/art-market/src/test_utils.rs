@@ -0,0 +1,73 @@
+use crate::*;
+use near_sdk::mock::MockAction;
+use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+use near_sdk::testing_env;
+
+pub(crate) fn account(account_id: &str) -> AccountId {
+    account_id.parse().unwrap()
+}
+
+pub(crate) fn owner() -> AccountId {
+    account("owner.near")
+}
+
+pub(crate) fn spot() -> AccountId {
+    account("spot.near")
+}
+
+/// Sets up the context for the next call on the market.
+pub(crate) fn set_context(predecessor_id: AccountId, deposit: NearToken) {
+    set_context_at(predecessor_id, deposit, 0);
+}
+
+/// Sets up the context for the next call on the market in a block with the given timestamp.
+pub(crate) fn set_context_at(predecessor_id: AccountId, deposit: NearToken, timestamp_ms: u64) {
+    testing_env!(VMContextBuilder::new()
+        .current_account_id(account("market.near"))
+        .predecessor_account_id(predecessor_id.clone())
+        .signer_account_id(predecessor_id)
+        .attached_deposit(deposit)
+        .block_timestamp(timestamp_ms * 1_000_000)
+        .build());
+}
+
+/// A market owned by owner.near that accepts spot.near.
+pub(crate) fn setup_market() -> ArtMarket {
+    set_context(owner(), NO_DEPOSIT);
+    ArtMarket::new(owner(), spot())
+}
+
+/// The total that was transferred to an account in the receipts created by the last call.
+pub(crate) fn transferred_to(account_id: &AccountId) -> NearToken {
+    get_created_receipts()
+        .into_iter()
+        .filter(|receipt| &receipt.receiver_id == account_id)
+        .flat_map(|receipt| receipt.actions)
+        .fold(NO_DEPOSIT, |total, action| match action {
+            MockAction::Transfer { deposit, .. } => total.saturating_add(deposit),
+            _ => total,
+        })
+}
+
+/// The `ft_transfer` calls made on the SPOT contract by the last call, as `(receiver_id, amount)`.
+pub(crate) fn spot_transfers() -> Vec<(AccountId, U128)> {
+    #[derive(near_sdk::serde::Deserialize)]
+    #[serde(crate = "near_sdk::serde")]
+    struct FtTransferArgs {
+        receiver_id: AccountId,
+        amount: U128,
+    }
+
+    get_created_receipts()
+        .into_iter()
+        .filter(|receipt| receipt.receiver_id == spot())
+        .flat_map(|receipt| receipt.actions)
+        .filter_map(|action| match action {
+            MockAction::FunctionCallWeight { method_name, args, .. } if method_name == b"ft_transfer" => {
+                let args: FtTransferArgs = near_sdk::serde_json::from_slice(&args).unwrap();
+                Some((args.receiver_id, args.amount))
+            }
+            _ => None,
+        })
+        .collect()
+}
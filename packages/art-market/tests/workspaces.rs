@@ -0,0 +1,664 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::json;
+use near_workspaces::{types::NearToken, Account, Contract, DevNetwork, Worker};
+use tokio::{fs::File, io::{self, AsyncReadExt}};
+
+const ONE_YOCTO: NearToken = NearToken::from_yoctonear(1);
+const TOKEN_ID: &str = "artwork";
+
+async fn read_file(path: &str) -> io::Result<Vec<u8>> {
+  let mut f = File::open(path).await?;
+  let mut buffer: Vec<u8> = Vec::new();
+
+  // read the whole file
+  f.read_to_end(&mut buffer).await?;
+  Ok(buffer)
+}
+
+struct Env {
+    nft_contract: Contract,
+    ft_contract: Contract,
+    market_contract: Contract,
+    seller: Account,
+    buyer: Account,
+    artist: Account,
+}
+
+async fn register_ft_user(ft_contract: &Contract, account_id: &near_workspaces::AccountId) -> anyhow::Result<()> {
+    let res = ft_contract
+        .call("storage_deposit")
+        .args_json((account_id, Option::<bool>::None))
+        .max_gas()
+        .deposit(near_sdk::env::storage_byte_cost().saturating_mul(125))
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    Ok(())
+}
+
+async fn init(worker: &Worker<impl DevNetwork>) -> anyhow::Result<Env> {
+    let nft_contract = worker.dev_deploy(&read_file("../../compiled/art_echo.wasm").await?).await?;
+    let ft_contract = worker.dev_deploy(&read_file("../../compiled/spot_token.wasm").await?).await?;
+    let market_contract = worker.dev_deploy(&read_file("../../compiled/art_market.wasm").await?).await?;
+
+    let res = nft_contract
+        .call("new_default_meta")
+        .args_json((nft_contract.id(),))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let res = ft_contract
+        .call("new_default_meta")
+        .args_json((ft_contract.id(), U128(NearToken::from_near(10000).as_yoctonear())))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let res = market_contract
+        .call("new")
+        .args_json((market_contract.id(), ft_contract.id()))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let mut accounts = vec![];
+    for name in ["seller", "buyer", "artist"] {
+        let account = nft_contract
+            .as_account()
+            .create_subaccount(name)
+            .initial_balance(NearToken::from_near(30))
+            .transact()
+            .await?
+            .into_result()?;
+        register_ft_user(&ft_contract, account.id()).await?;
+        accounts.push(account);
+    }
+    register_ft_user(&ft_contract, market_contract.id()).await?;
+
+    let artist = accounts.pop().unwrap();
+    let buyer = accounts.pop().unwrap();
+    let seller = accounts.pop().unwrap();
+
+    // The seller owns a token that pays 10% to the artist on every sale.
+    let res = nft_contract
+        .call("nft_mint")
+        .args_json(json!({
+            "token_id": TOKEN_ID,
+            "metadata": { "title": "Artwork" },
+            "receiver_id": seller.id(),
+            "perpetual_royalties": { artist.id().to_string(): 1000 },
+        }))
+        .max_gas()
+        .deposit(NearToken::from_millinear(100))
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let res = seller
+        .call(market_contract.id(), "storage_deposit")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_millinear(100))
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    Ok(Env { nft_contract, ft_contract, market_contract, seller, buyer, artist })
+}
+
+async fn list(env: &Env, sale_conditions: near_sdk::serde_json::Value) -> anyhow::Result<()> {
+    let res = env.seller
+        .call(env.nft_contract.id(), "nft_approve")
+        .args_json(json!({
+            "token_id": TOKEN_ID,
+            "account_id": env.market_contract.id(),
+            "msg": json!({ "sale_conditions": sale_conditions }).to_string(),
+        }))
+        .max_gas()
+        .deposit(NearToken::from_millinear(10))
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    Ok(())
+}
+
+async fn token_owner(env: &Env) -> anyhow::Result<String> {
+    let token: near_sdk::serde_json::Value = env.nft_contract
+        .call("nft_token")
+        .args_json((TOKEN_ID,))
+        .view()
+        .await?
+        .json()?;
+
+    Ok(token["owner_id"].as_str().unwrap().to_string())
+}
+
+async fn ft_balance_of(env: &Env, account_id: &near_workspaces::AccountId) -> anyhow::Result<u128> {
+    let balance: U128 = env.ft_contract
+        .call("ft_balance_of")
+        .args_json((account_id,))
+        .view()
+        .await?
+        .json()?;
+
+    Ok(balance.0)
+}
+
+#[tokio::test]
+async fn test_list_through_nft_on_approve() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let env = init(&worker).await?;
+    let price = NearToken::from_near(1);
+
+    list(&env, json!({ "near": price })).await?;
+
+    let sale: near_sdk::serde_json::Value = env.market_contract
+        .call("get_sale")
+        .args_json((env.nft_contract.id(), TOKEN_ID))
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(sale["owner_id"], env.seller.id().to_string());
+    assert_eq!(sale["sale_conditions"]["near"], price.as_yoctonear().to_string());
+
+    let supply: near_sdk::json_types::U64 = env.market_contract
+        .call("get_supply_by_owner_id")
+        .args_json((env.seller.id(),))
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(supply.0, 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_buy_with_near_pays_out_royalties() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let env = init(&worker).await?;
+    let price = NearToken::from_near(10);
+
+    list(&env, json!({ "near": price })).await?;
+
+    let seller_balance_before = env.seller.view_account().await?.balance;
+    let artist_balance_before = env.artist.view_account().await?.balance;
+
+    let res = env.buyer
+        .call(env.market_contract.id(), "offer")
+        .args_json((env.nft_contract.id(), TOKEN_ID))
+        .max_gas()
+        .deposit(price)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    assert_eq!(token_owner(&env).await?, env.buyer.id().to_string());
+
+    // The artist gets 10% and the seller gets the remaining 90% of the price.
+    let artist_balance_diff = env.artist.view_account().await?.balance.saturating_sub(artist_balance_before);
+    let seller_balance_diff = env.seller.view_account().await?.balance.saturating_sub(seller_balance_before);
+    assert_eq!(artist_balance_diff, NearToken::from_near(1));
+    assert!(seller_balance_diff >= NearToken::from_near(9));
+
+    let supply: near_sdk::json_types::U64 = env.market_contract.call("get_supply_sales").view().await?.json()?;
+    assert_eq!(supply.0, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_buy_with_spot_pays_out_royalties_and_refunds_unused() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let env = init(&worker).await?;
+    let price: u128 = 1_000;
+
+    let res = env.ft_contract
+        .call("ft_transfer")
+        .args_json((env.buyer.id(), U128(5_000), Option::<String>::None))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    list(&env, json!({ "spot": U128(price) })).await?;
+
+    // The buyer sends more than the price, so the rest has to come back.
+    let res = env.buyer
+        .call(env.ft_contract.id(), "ft_transfer_call")
+        .args_json(json!({
+            "receiver_id": env.market_contract.id(),
+            "amount": U128(1_500),
+            "msg": json!({ "nft_contract_id": env.nft_contract.id(), "token_id": TOKEN_ID }).to_string(),
+        }))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    assert_eq!(token_owner(&env).await?, env.buyer.id().to_string());
+    assert_eq!(ft_balance_of(&env, env.buyer.id()).await?, 5_000 - price);
+    assert_eq!(ft_balance_of(&env, env.artist.id()).await?, 100);
+    assert_eq!(ft_balance_of(&env, env.seller.id()).await?, 900);
+    assert_eq!(ft_balance_of(&env, env.market_contract.id()).await?, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_failed_transfer_refunds_buyer() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let env = init(&worker).await?;
+    let price = NearToken::from_near(10);
+
+    list(&env, json!({ "near": price })).await?;
+
+    // Revoking the market makes `nft_transfer_payout` fail.
+    let res = env.seller
+        .call(env.nft_contract.id(), "nft_revoke_all")
+        .args_json((TOKEN_ID,))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let buyer_balance_before = env.buyer.view_account().await?.balance;
+
+    let res = env.buyer
+        .call(env.market_contract.id(), "offer")
+        .args_json((env.nft_contract.id(), TOKEN_ID))
+        .max_gas()
+        .deposit(price)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    assert_eq!(token_owner(&env).await?, env.seller.id().to_string());
+
+    // The buyer only pays for gas.
+    let buyer_balance_diff = buyer_balance_before.saturating_sub(env.buyer.view_account().await?.balance);
+    assert!(buyer_balance_diff < NearToken::from_millinear(100));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_wrong_currency_is_rejected() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let env = init(&worker).await?;
+
+    list(&env, json!({ "spot": U128(1_000) })).await?;
+
+    let res = env.buyer
+        .call(env.market_contract.id(), "offer")
+        .args_json((env.nft_contract.id(), TOKEN_ID))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(format!("{:?}", res).contains("Sale is priced in SPOT, use ft_transfer_call"));
+
+    assert_eq!(token_owner(&env).await?, env.seller.id().to_string());
+
+    Ok(())
+}
+
+async fn storage_deposit(env: &Env, account: &Account) -> anyhow::Result<()> {
+    let res = account
+        .call(env.market_contract.id(), "storage_deposit")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_millinear(100))
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    Ok(())
+}
+
+async fn accept_offer(env: &Env, offer_id: u64) -> anyhow::Result<near_workspaces::result::ExecutionFinalResult> {
+    let res = env.seller
+        .call(env.nft_contract.id(), "nft_approve")
+        .args_json(json!({
+            "token_id": TOKEN_ID,
+            "account_id": env.market_contract.id(),
+            "msg": json!({ "offer_id": offer_id }).to_string(),
+        }))
+        .max_gas()
+        .deposit(NearToken::from_millinear(10))
+        .transact()
+        .await?;
+
+    Ok(res)
+}
+
+async fn offers_by_token(env: &Env) -> anyhow::Result<Vec<near_sdk::serde_json::Value>> {
+    let offers = env.market_contract
+        .call("get_offers_by_token")
+        .args_json(json!({ "nft_contract_id": env.nft_contract.id(), "token_id": TOKEN_ID }))
+        .view()
+        .await?
+        .json()?;
+
+    Ok(offers)
+}
+
+#[tokio::test]
+async fn test_accept_near_offer_on_unlisted_token_pays_out_royalties() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let env = init(&worker).await?;
+    let amount = NearToken::from_near(5);
+
+    storage_deposit(&env, &env.buyer).await?;
+    let res = env.buyer
+        .call(env.market_contract.id(), "make_offer")
+        .args_json(json!({
+            "nft_contract_id": env.nft_contract.id(),
+            "token_id": TOKEN_ID,
+            "expires_at": 4_000_000_000_000u64,
+        }))
+        .max_gas()
+        .deposit(amount)
+        .transact()
+        .await?;
+    let offer_id: u64 = res.json()?;
+
+    let offers = offers_by_token(&env).await?;
+    assert_eq!(offers.len(), 1);
+    assert_eq!(offers[0]["bidder_id"], env.buyer.id().to_string());
+    assert_eq!(offers[0]["amount"]["near"], amount.as_yoctonear().to_string());
+
+    let offers: Vec<near_sdk::serde_json::Value> = env.market_contract
+        .call("get_offers_by_bidder_id")
+        .args_json(json!({ "account_id": env.buyer.id() }))
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(offers.len(), 1);
+
+    let artist_balance_before = env.artist.view_account().await?.balance;
+
+    let res = accept_offer(&env, offer_id).await?;
+    assert!(res.is_success());
+
+    assert_eq!(token_owner(&env).await?, env.buyer.id().to_string());
+    let artist_balance_diff = env.artist.view_account().await?.balance.saturating_sub(artist_balance_before);
+    assert_eq!(artist_balance_diff, NearToken::from_millinear(500));
+    assert!(offers_by_token(&env).await?.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_accept_spot_offer_pays_out_royalties() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let env = init(&worker).await?;
+
+    let res = env.ft_contract
+        .call("ft_transfer")
+        .args_json((env.buyer.id(), U128(5_000), Option::<String>::None))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    storage_deposit(&env, &env.buyer).await?;
+    let res = env.buyer
+        .call(env.ft_contract.id(), "ft_transfer_call")
+        .args_json(json!({
+            "receiver_id": env.market_contract.id(),
+            "amount": U128(2_000),
+            "msg": json!({
+                "nft_contract_id": env.nft_contract.id(),
+                "token_id": TOKEN_ID,
+                "expires_at": 4_000_000_000_000u64,
+            }).to_string(),
+        }))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    // The whole amount is escrowed by the market.
+    assert_eq!(ft_balance_of(&env, env.buyer.id()).await?, 3_000);
+    assert_eq!(ft_balance_of(&env, env.market_contract.id()).await?, 2_000);
+
+    let offers = offers_by_token(&env).await?;
+    let offer_id = offers[0]["offer_id"].as_u64().unwrap();
+    let res = accept_offer(&env, offer_id).await?;
+    assert!(res.is_success());
+
+    assert_eq!(token_owner(&env).await?, env.buyer.id().to_string());
+    assert_eq!(ft_balance_of(&env, env.artist.id()).await?, 200);
+    assert_eq!(ft_balance_of(&env, env.seller.id()).await?, 1_800);
+    assert_eq!(ft_balance_of(&env, env.market_contract.id()).await?, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_expired_offer_cannot_be_accepted_and_is_refunded() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let env = init(&worker).await?;
+    let amount = NearToken::from_near(5);
+
+    storage_deposit(&env, &env.buyer).await?;
+    let now_ms = worker.view_block().await?.timestamp() / 1_000_000;
+    let res = env.buyer
+        .call(env.market_contract.id(), "make_offer")
+        .args_json(json!({
+            "nft_contract_id": env.nft_contract.id(),
+            "token_id": TOKEN_ID,
+            "expires_at": now_ms + 1_000,
+        }))
+        .max_gas()
+        .deposit(amount)
+        .transact()
+        .await?;
+    let offer_id: u64 = res.json()?;
+
+    worker.fast_forward(100).await?;
+
+    let res = accept_offer(&env, offer_id).await?;
+    assert!(format!("{:?}", res).contains("Offer has expired"));
+    assert_eq!(token_owner(&env).await?, env.seller.id().to_string());
+
+    // Anyone can refund an expired offer to the bidder.
+    let buyer_balance_before = env.buyer.view_account().await?.balance;
+    let res = env.artist
+        .call(env.market_contract.id(), "remove_offer")
+        .args_json(json!({ "offer_id": offer_id }))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let buyer_balance_diff = env.buyer.view_account().await?.balance.saturating_sub(buyer_balance_before);
+    assert_eq!(buyer_balance_diff, amount);
+    assert!(offers_by_token(&env).await?.is_empty());
+
+    Ok(())
+}
+
+async fn start_auction(env: &Env, worker: &Worker<impl DevNetwork>, auction: near_sdk::serde_json::Value, duration_ms: u64) -> anyhow::Result<u64> {
+    let now_ms = worker.view_block().await?.timestamp() / 1_000_000;
+    let res = env.seller
+        .call(env.nft_contract.id(), "nft_transfer_call")
+        .args_json(json!({
+            "receiver_id": env.market_contract.id(),
+            "token_id": TOKEN_ID,
+            "msg": json!({ "auction": auction, "end_at": now_ms + duration_ms }).to_string(),
+        }))
+        .max_gas()
+        .deposit(ONE_YOCTO)
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    let auctions: Vec<near_sdk::serde_json::Value> = env.market_contract
+        .call("get_auctions_by_seller_id")
+        .args_json(json!({ "account_id": env.seller.id() }))
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(auctions.len(), 1);
+
+    Ok(auctions[0]["auction_id"].as_u64().unwrap())
+}
+
+async fn settle(env: &Env, auction_id: u64) -> anyhow::Result<near_workspaces::result::ExecutionFinalResult> {
+    let res = env.artist
+        .call(env.market_contract.id(), "settle")
+        .args_json(json!({ "auction_id": auction_id }))
+        .max_gas()
+        .transact()
+        .await?;
+
+    Ok(res)
+}
+
+#[tokio::test]
+async fn test_english_auction_refunds_outbid_and_pays_out_winner() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let env = init(&worker).await?;
+
+    let auction_id = start_auction(&env, &worker, json!({
+        "english": {
+            "reserve_price": NearToken::from_near(1),
+            "min_increment": NearToken::from_millinear(500),
+            "extension_ms": 0,
+        }
+    }), 20_000).await?;
+    assert_eq!(token_owner(&env).await?, env.market_contract.id().to_string());
+
+    let res = env.buyer
+        .call(env.market_contract.id(), "bid")
+        .args_json(json!({ "auction_id": auction_id }))
+        .max_gas()
+        .deposit(NearToken::from_millinear(500))
+        .transact()
+        .await?;
+    assert!(format!("{:?}", res).contains("Bid must be at least"));
+
+    let res = env.buyer
+        .call(env.market_contract.id(), "bid")
+        .args_json(json!({ "auction_id": auction_id }))
+        .max_gas()
+        .deposit(NearToken::from_near(2))
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    // The artist outbids the buyer, who gets their bid back.
+    let buyer_balance_before = env.buyer.view_account().await?.balance;
+    let res = env.artist
+        .call(env.market_contract.id(), "bid")
+        .args_json(json!({ "auction_id": auction_id }))
+        .max_gas()
+        .deposit(NearToken::from_near(3))
+        .transact()
+        .await?;
+    assert!(res.is_success());
+    let buyer_balance_diff = env.buyer.view_account().await?.balance.saturating_sub(buyer_balance_before);
+    assert_eq!(buyer_balance_diff, NearToken::from_near(2));
+
+    let res = settle(&env, auction_id).await?;
+    assert!(format!("{:?}", res).contains("Auction hasn't ended"));
+
+    worker.fast_forward(500).await?;
+
+    let seller_balance_before = env.seller.view_account().await?.balance;
+    let res = settle(&env, auction_id).await?;
+    assert!(res.is_success());
+
+    assert_eq!(token_owner(&env).await?, env.artist.id().to_string());
+    let seller_balance_diff = env.seller.view_account().await?.balance.saturating_sub(seller_balance_before);
+    assert_eq!(seller_balance_diff, NearToken::from_millinear(2700));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dutch_auction_sells_at_current_price() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let env = init(&worker).await?;
+    let price = NearToken::from_near(10);
+
+    // The price only drops after an hour, so it's still the start price when the buyer buys.
+    let auction_id = start_auction(&env, &worker, json!({
+        "dutch": {
+            "start_price": price,
+            "end_price": NearToken::from_near(1),
+            "step_ms": 3_600_000,
+        }
+    }), 36_000_000).await?;
+
+    let current_price: NearToken = env.market_contract
+        .call("get_auction_price")
+        .args_json(json!({ "auction_id": auction_id }))
+        .view()
+        .await?
+        .json()?;
+    assert_eq!(current_price, price);
+
+    let seller_balance_before = env.seller.view_account().await?.balance;
+    let artist_balance_before = env.artist.view_account().await?.balance;
+
+    let res = env.buyer
+        .call(env.market_contract.id(), "buy")
+        .args_json(json!({ "auction_id": auction_id }))
+        .max_gas()
+        .deposit(NearToken::from_near(12))
+        .transact()
+        .await?;
+    assert!(res.is_success());
+
+    assert_eq!(token_owner(&env).await?, env.buyer.id().to_string());
+    let seller_balance_diff = env.seller.view_account().await?.balance.saturating_sub(seller_balance_before);
+    let artist_balance_diff = env.artist.view_account().await?.balance.saturating_sub(artist_balance_before);
+    assert_eq!(seller_balance_diff, NearToken::from_near(9));
+    assert_eq!(artist_balance_diff, NearToken::from_near(1));
+
+    let auction: Option<near_sdk::serde_json::Value> = env.market_contract
+        .call("get_auction")
+        .args_json(json!({ "auction_id": auction_id }))
+        .view()
+        .await?
+        .json()?;
+    assert!(auction.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_settle_without_bids_returns_token_to_seller() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let env = init(&worker).await?;
+
+    let auction_id = start_auction(&env, &worker, json!({
+        "english": {
+            "reserve_price": NearToken::from_near(1),
+            "min_increment": NearToken::from_millinear(100),
+            "extension_ms": 0,
+        }
+    }), 1_000).await?;
+
+    worker.fast_forward(100).await?;
+
+    let res = settle(&env, auction_id).await?;
+    assert!(res.is_success());
+    assert_eq!(token_owner(&env).await?, env.seller.id().to_string());
+
+    Ok(())
+}
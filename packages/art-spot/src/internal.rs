@@ -48,4 +48,15 @@ impl ArtSpot {
   pub(crate) fn internal_unwrap_account(&self, id: &Id) -> User {
     crate::unwrap!(self.internal_get_account(id))
   }
+
+  /// Maps a NEAR account to the ID its user is registered under, e.g. `alice.art-spot.near` to `alice`.
+  /// Returns `None` instead of panicking for accounts that can't be a user of this contract.
+  pub(crate) fn internal_user_id(account_id: &AccountId) -> Option<Id> {
+    let name = account_id
+      .as_str()
+      .strip_suffix(env::current_account_id().as_str())?
+      .strip_suffix('.')?;
+
+    Id::is_valid(name).then(|| Id::new(name))
+  }
 }
@@ -96,6 +96,24 @@ impl ArtSpot {
     self.allowlist.push(account_id);
   }
 
+  /// Returns whether the account `id` belongs to a registered user with the given role.
+  /// Used by other contracts to gate features behind a role, e.g. minting for artists.
+  pub fn has_role(&self, id: AccountId, role: Role) -> bool {
+    Self::internal_user_id(&id)
+      .and_then(|id| self.internal_get_account(&id))
+      .map(|user| user.roles.contains(&role))
+      .unwrap_or(false)
+  }
+
+  /// Returns whether the account `id` belongs to a registered and verified user.
+  /// Used by other contracts to report whether an artist is verified, e.g. the creator of a token.
+  pub fn is_verified(&self, id: AccountId) -> bool {
+    Self::internal_user_id(&id)
+      .and_then(|id| self.internal_get_account(&id))
+      .map(|user| user.verified)
+      .unwrap_or(false)
+  }
+
   /// Create new account and deposit passed funds.
   #[payable]
   pub fn create_account(